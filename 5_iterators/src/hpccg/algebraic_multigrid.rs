@@ -0,0 +1,319 @@
+use super::{sparsemv, waxpby, Preconditioner, SparseMatrix, SymmetricGaussSeidel};
+
+/// Coarsening stops once a level has no more rows than this, as smoothing alone is cheap and
+/// effective on a matrix this small.
+const COARSEST_NROW: usize = 64;
+
+/// The smoothed aggregation algebraic multigrid preconditioner, which is one V-cycle over a
+/// hierarchy of matrices built from the entries of the matrix alone, so it works for matrices
+/// which were not generated on a mesh.
+///
+/// The rows of each level are grouped into aggregates of strongly connected rows, each of which
+/// is a row of the next level. The tentative prolongator copies the value of each aggregate to its
+/// rows, and is smoothed with a damped Jacobi step, `P = (I - ωD⁻¹A)T`, so that it interpolates
+/// smooth errors better. The restriction is `Pᵀ`, and the coarse matrix is the Galerkin product
+/// `PᵀAP`, which keeps the preconditioner symmetric.
+///
+/// # Fields
+/// * `levels` - The smoother of each level, from the finest to the coarsest, which holds a copy of
+///   the matrix of its level.
+/// * `prolongators` - The prolongator from each level to the one before it.
+/// * `restrictions` - The restriction from each level to the next, the transpose of its
+///   prolongator.
+pub struct AlgebraicMultigrid {
+    pub levels: Vec<SymmetricGaussSeidel>,
+    pub prolongators: Vec<SparseMatrix>,
+    pub restrictions: Vec<SparseMatrix>,
+}
+
+impl AlgebraicMultigrid {
+    /// The strength threshold of the preconditioner when it is chosen on the command line. It is
+    /// below `1/27`, the relative size of the entries off the diagonal of the 27 point stencil of
+    /// `generate_matrix`, so that they are all strong connections.
+    pub const DEFAULT_STRENGTH_THRESHOLD: f64 = 0.02;
+
+    /// Create the multigrid hierarchy of a symmetric matrix, coarsening until there are `levels`
+    /// levels, the coarsest level is small, or aggregation no longer reduces the number of rows.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix to precondition.
+    /// * `levels` - The maximum number of levels, including the matrix itself.
+    /// * `strength_threshold` - The entry `a_ij` is a strong connection if
+    ///   `|a_ij| ≥ θ √|a_ii a_jj|` for this threshold `θ`, which is halved on each coarser level,
+    ///   and only strong connections are aggregated together.
+    ///
+    /// # Return values
+    /// * `amg` - The preconditioner, or an error if the matrix should be coarsened but none of its
+    ///   entries are strong connections, which would leave it with no coarse levels.
+    pub fn new(
+        matrix: &SparseMatrix,
+        levels: usize,
+        strength_threshold: f64,
+    ) -> Result<Self, String> {
+        let mut smoothers = vec![SymmetricGaussSeidel::new(matrix)];
+        let mut prolongators = vec![];
+        let mut restrictions = vec![];
+        let mut fine = matrix.clone();
+        let mut threshold = strength_threshold;
+        while smoothers.len() < levels && fine.local_nrow > COARSEST_NROW {
+            let strong = strong_connections(&fine, threshold);
+            let (aggregates, naggregates) = aggregate(&strong);
+            if naggregates == fine.local_nrow {
+                if smoothers.len() == 1 {
+                    return Err(format!(
+                        "Algebraic multigrid has no coarse levels, as no entries of the matrix are \
+                         strong connections with a strength threshold of {strength_threshold}"
+                    ));
+                }
+                break;
+            }
+            let prolongator = smoothed_prolongator(&fine, &aggregates, naggregates);
+            let restriction = prolongator.transpose();
+            let coarse = restriction.multiply(&fine.multiply(&prolongator));
+            smoothers.push(SymmetricGaussSeidel::new(&coarse));
+            prolongators.push(prolongator);
+            restrictions.push(restriction);
+            fine = coarse;
+            // The coarse matrices are denser, with weaker connections
+            threshold /= 2.0;
+        }
+        Ok(AlgebraicMultigrid {
+            levels: smoothers,
+            prolongators,
+            restrictions,
+        })
+    }
+}
+
+impl Preconditioner for AlgebraicMultigrid {
+    fn apply(&self, r: &[f64]) -> Vec<f64> {
+        v_cycle(&self.levels, &self.prolongators, &self.restrictions, r)
+    }
+}
+
+/// The columns of the strong connections of each row, which are the entries off the diagonal
+/// that are large compared to the diagonals of their row and column.
+///
+/// # Arguments
+/// * `matrix` - The matrix of the level.
+/// * `threshold` - The strength threshold `θ`.
+fn strong_connections(matrix: &SparseMatrix, threshold: f64) -> Vec<Vec<usize>> {
+    let diagonal = matrix.diagonal();
+    (0..matrix.local_nrow)
+        .map(|row| {
+            matrix
+                .row_entries(row)
+                .filter(|&(col, value)| {
+                    col != row
+                        && value != 0.0
+                        && value.abs() >= threshold * (diagonal[row] * diagonal[col]).abs().sqrt()
+                })
+                .map(|(col, _)| col)
+                .collect()
+        })
+        .collect()
+}
+
+/// Greedily group the rows into aggregates of strongly connected rows.
+///
+/// # Arguments
+/// * `strong` - The strong connections of each row.
+///
+/// # Return values
+/// * `aggregates` - The aggregate of each row.
+/// * `naggregates` - The number of aggregates.
+fn aggregate(strong: &[Vec<usize>]) -> (Vec<usize>, usize) {
+    let nrow = strong.len();
+    let mut aggregates: Vec<Option<usize>> = vec![None; nrow];
+    let mut naggregates = 0;
+
+    // A row with no aggregated strong connections forms a new aggregate with all of them
+    for row in 0..nrow {
+        if aggregates[row].is_none() && strong[row].iter().all(|&col| aggregates[col].is_none()) {
+            aggregates[row] = Some(naggregates);
+            for &col in &strong[row] {
+                aggregates[col] = Some(naggregates);
+            }
+            naggregates += 1;
+        }
+    }
+
+    // The rest of the rows join the aggregate of one of their strong connections from the first
+    // pass, which each of them has if the connections are symmetric
+    let roots = aggregates.clone();
+    for row in 0..nrow {
+        if aggregates[row].is_none() {
+            aggregates[row] = strong[row].iter().find_map(|&col| roots[col]);
+        }
+    }
+
+    let aggregates = aggregates
+        .into_iter()
+        .map(|aggregate| {
+            aggregate.unwrap_or_else(|| {
+                naggregates += 1;
+                naggregates - 1
+            })
+        })
+        .collect();
+    (aggregates, naggregates)
+}
+
+/// The prolongator from the aggregates to the rows, `P = (I - ωD⁻¹A)T`, where the tentative
+/// prolongator `T` gives each row the value of its aggregate, scaled so its columns have unit
+/// length. The damping is `ω = 4 / 3ρ`, where `ρ` is a bound on the spectral radius of `D⁻¹A`.
+///
+/// # Arguments
+/// * `matrix` - The matrix of the level.
+/// * `aggregates` - The aggregate of each row.
+/// * `naggregates` - The number of aggregates.
+fn smoothed_prolongator(
+    matrix: &SparseMatrix,
+    aggregates: &[usize],
+    naggregates: usize,
+) -> SparseMatrix {
+    let mut sizes = vec![0; naggregates];
+    for &aggregate in aggregates {
+        sizes[aggregate] += 1;
+    }
+    let tentative_rows = aggregates
+        .iter()
+        .map(|&aggregate| vec![(aggregate, 1.0 / (sizes[aggregate] as f64).sqrt())])
+        .collect();
+    let tentative = SparseMatrix::from_rows(tentative_rows, naggregates);
+
+    // Each row sum of `|D⁻¹A|` bounds the spectral radius, by Gershgorin's theorem
+    let diagonal = matrix.diagonal();
+    let radius = (0..matrix.local_nrow)
+        .map(|row| {
+            let sum: f64 = matrix.row_entries(row).map(|(_, value)| value.abs()).sum();
+            sum / diagonal[row].abs()
+        })
+        .fold(0.0, f64::max);
+    let omega = 4.0 / (3.0 * radius);
+
+    let product = matrix.multiply(&tentative);
+    let rows = (0..matrix.local_nrow)
+        .map(|row| {
+            let (aggregate, value) = tentative.row_entries(row).next().unwrap();
+            product
+                .row_entries(row)
+                .map(|(col, product_value)| {
+                    let smoothed = -omega * product_value / diagonal[row];
+                    if col == aggregate {
+                        (col, value + smoothed)
+                    } else {
+                        (col, smoothed)
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    SparseMatrix::from_rows(rows, naggregates)
+}
+
+/// Approximately solve `Az = r` on the first of the levels with a V-cycle: smooth, correct with
+/// the approximate solution of the restricted residual on the coarser levels, and smooth again.
+///
+/// # Arguments
+/// * `levels` - The smoothers of this level and the coarser ones.
+/// * `prolongators` - The prolongator from each coarser level to the one before it.
+/// * `restrictions` - The restriction from this level and each coarser one to the next.
+/// * `r` - The right hand side vector on this level.
+fn v_cycle(
+    levels: &[SymmetricGaussSeidel],
+    prolongators: &[SparseMatrix],
+    restrictions: &[SparseMatrix],
+    r: &[f64],
+) -> Vec<f64> {
+    let (smoother, coarser) = levels.split_first().unwrap();
+    let nrow = smoother.matrix.local_nrow;
+    let mut z = vec![0.0; nrow];
+    smoother.smooth(r, &mut z);
+    // The coarsest level is only smoothed
+    let (Some((prolongator, coarser_prolongators)), Some((restriction, coarser_restrictions))) =
+        (prolongators.split_first(), restrictions.split_first())
+    else {
+        return z;
+    };
+
+    let residual = waxpby(nrow, 1.0, r, -1.0, &sparsemv(&smoother.matrix, &z));
+    let coarse_r = sparsemv(restriction, &residual);
+    let correction = v_cycle(
+        coarser,
+        coarser_prolongators,
+        coarser_restrictions,
+        &coarse_r,
+    );
+    z = waxpby(nrow, 1.0, &z, 1.0, &sparsemv(prolongator, &correction));
+    smoother.smooth(r, &mut z);
+    z
+}
+
+#[test]
+fn test_algebraic_multigrid() {
+    // Without any strong connections, every row is its own aggregate
+    let (matrix, _, _, _) = SparseMatrix::generate_matrix(4, 4, 4);
+    let strong = strong_connections(&matrix, 0.5);
+    assert!(strong.iter().all(Vec::is_empty));
+    assert_eq!(aggregate(&strong).1, 64);
+    // Otherwise, the first row is aggregated with its neighbours, and the rest of the rows are
+    // covered by aggregates
+    let strong = strong_connections(&matrix, 0.0);
+    let (aggregates, naggregates) = aggregate(&strong);
+    assert_eq!(strong[0], vec![1, 4, 5, 16, 17, 20, 21]);
+    assert!(strong[0].iter().all(|&col| aggregates[col] == 0));
+    assert!(naggregates < 64 / 4);
+
+    // The layers of the coefficients are only weakly connected to each other
+    let diffusion = super::DiffusionConfig::new(super::CoefficientField::Layered)
+        .contrast(1e4)
+        .layers(1);
+    let (matrix, guess, rhs, exact) =
+        SparseMatrix::generate_diffusion_matrix(16, 16, 16, diffusion);
+    let amg = AlgebraicMultigrid::new(&matrix, 10, 0.08).unwrap();
+    let nrows: Vec<usize> = amg
+        .levels
+        .iter()
+        .map(|level| level.matrix.local_nrow)
+        .collect();
+    assert!(nrows.windows(2).all(|pair| pair[1] < pair[0]));
+    assert!(*nrows.last().unwrap() <= COARSEST_NROW);
+    // The coarse matrices are symmetric
+    let coarse = &amg.levels[1].matrix;
+    let transpose = coarse.transpose();
+    assert_eq!(coarse.list_of_inds, transpose.list_of_inds);
+    for (value, transpose_value) in coarse.list_of_vals.iter().zip(transpose.list_of_vals) {
+        assert!((value - transpose_value).abs() < 1e-12 * value.abs().max(1.0));
+    }
+
+    let mut config = super::SolverConfig::new()
+        .tolerance(1e-10)
+        .verbosity(super::Verbosity::Quiet);
+    let report = super::pcg(&matrix, &rhs, &guess, &amg, &mut config);
+    let symgs = SymmetricGaussSeidel::new(&matrix);
+    let symgs_report = super::pcg(&matrix, &rhs, &guess, &symgs, &mut config);
+    assert_eq!(report.reason, super::ConvergenceReason::Converged);
+    assert!(report.iterations < symgs_report.iterations);
+    for (actual, expected) in report.solution.iter().zip(exact) {
+        assert!((expected - actual).abs() < 1e-6);
+    }
+    assert_eq!(
+        "amg".parse(),
+        Ok(super::PreconditionerKind::AlgebraicMultigrid)
+    );
+
+    // The entries of the 27 point stencil are strong connections with the default threshold, so
+    // the matrix of `generate_matrix` is coarsened, and converges faster than with Gauss-Seidel
+    let (matrix, guess, rhs, _) = SparseMatrix::generate_matrix(16, 16, 16);
+    let threshold = AlgebraicMultigrid::DEFAULT_STRENGTH_THRESHOLD;
+    let amg = AlgebraicMultigrid::new(&matrix, 10, threshold).unwrap();
+    assert!(amg.levels.len() > 1);
+    let report = super::pcg(&matrix, &rhs, &guess, &amg, &mut config);
+    let symgs = SymmetricGaussSeidel::new(&matrix);
+    let symgs_report = super::pcg(&matrix, &rhs, &guess, &symgs, &mut config);
+    assert_eq!(report.reason, super::ConvergenceReason::Converged);
+    assert!(report.iterations < symgs_report.iterations);
+    // With a threshold above `1/27`, no entries are strong connections
+    assert!(AlgebraicMultigrid::new(&matrix, 10, 0.08).is_err());
+}
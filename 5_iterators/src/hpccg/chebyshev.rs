@@ -0,0 +1,284 @@
+use super::{ddot, sparsemv, waxpby, Preconditioner, SparseMatrix};
+
+/// The number of Lanczos iterations used to estimate the extreme eigenvalues.
+const LANCZOS_STEPS: usize = 10;
+
+/// The ratio of the ends of the interval of eigenvalues which the smoother reduces.
+const SMOOTHING_RATIO: f64 = 30.0;
+
+/// The Chebyshev polynomial preconditioner, which is a fixed number of steps of the Chebyshev
+/// iteration on the Jacobi preconditioned matrix `D⁻¹A` from a zero initial guess. It only needs
+/// the `sparsemv` and `waxpby` kernels, with no triangular solves or dot products, and can also be
+/// used as a smoother.
+///
+/// The polynomial is chosen to be small over an interval containing the eigenvalues of `D⁻¹A`,
+/// whose ends are estimated with a few Lanczos iterations when the preconditioner is created.
+///
+/// # Fields
+/// * `matrix` - A copy of the matrix being preconditioned.
+/// * `inverse_diagonal` - The reciprocal of the diagonal entry of each row.
+/// * `lower` - The lower end of the interval of eigenvalues.
+/// * `upper` - The upper end of the interval of eigenvalues.
+/// * `degree` - The number of steps of the iteration, which is the degree of the polynomial plus
+///   one.
+pub struct Chebyshev {
+    pub matrix: SparseMatrix,
+    pub inverse_diagonal: Vec<f64>,
+    pub lower: f64,
+    pub upper: f64,
+    pub degree: usize,
+}
+
+impl Chebyshev {
+    /// Create the Chebyshev preconditioner of a symmetric matrix, which must have a positive
+    /// diagonal. The largest eigenvalue estimate is increased by a tenth, as the polynomial grows
+    /// quickly past the end of its interval, and Lanczos underestimates it.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix to precondition.
+    /// * `degree` - The number of steps of the iteration.
+    pub fn new(matrix: &SparseMatrix, degree: usize) -> Self {
+        let inverse_diagonal: Vec<f64> = matrix.diagonal().iter().map(|d| 1.0 / d).collect();
+        let (lower, upper) = estimate_eigenvalues(matrix, &inverse_diagonal, LANCZOS_STEPS);
+        Chebyshev {
+            matrix: matrix.clone(),
+            inverse_diagonal,
+            lower,
+            upper: 1.1 * upper,
+            degree,
+        }
+    }
+
+    /// Create the Chebyshev smoother of a matrix for multigrid, whose polynomial is only small over
+    /// the top of the interval of eigenvalues. The coarser levels remove the smooth errors, so the
+    /// smoother only needs to reduce the oscillatory ones, whose eigenvalues are large.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix of the level.
+    /// * `degree` - The number of steps of the iteration.
+    pub fn smoother(matrix: &SparseMatrix, degree: usize) -> Self {
+        let mut chebyshev = Chebyshev::new(matrix, degree);
+        chebyshev.lower = chebyshev.upper / SMOOTHING_RATIO;
+        chebyshev
+    }
+
+    /// Improve an approximate solution `z` of `Az = r` with the steps of the iteration.
+    ///
+    /// # Arguments
+    /// * `r` - The right hand side vector.
+    /// * `z` - The approximate solution.
+    pub fn smooth(&self, r: &[f64], z: &mut [f64]) {
+        let nrow = self.matrix.local_nrow;
+        let residual = waxpby(nrow, 1.0, r, -1.0, &sparsemv(&self.matrix, z));
+        let correction = self.iterate(residual);
+        for (z, correction) in z.iter_mut().zip(correction) {
+            *z += correction;
+        }
+    }
+
+    /// The steps of the Chebyshev iteration for `Az = r` from a zero initial guess, with the
+    /// three-term recurrence of Saad's Algorithm 12.1.
+    ///
+    /// # Arguments
+    /// * `r` - The right hand side vector, which is the initial residual.
+    fn iterate(&self, mut r: Vec<f64>) -> Vec<f64> {
+        let nrow = self.matrix.local_nrow;
+        let theta = (self.upper + self.lower) / 2.0;
+        let delta = (self.upper - self.lower) / 2.0;
+        let sigma = theta / delta;
+        let mut rho = 1.0 / sigma;
+
+        let mut z = vec![0.0; nrow];
+        let mut d = self.scale(1.0 / theta, &r);
+        for step in 0..self.degree {
+            z = waxpby(nrow, 1.0, &z, 1.0, &d);
+            if step + 1 == self.degree {
+                break;
+            }
+            r = waxpby(nrow, 1.0, &r, -1.0, &sparsemv(&self.matrix, &d));
+            let next_rho = 1.0 / (2.0 * sigma - rho);
+            d = waxpby(
+                nrow,
+                next_rho * rho,
+                &d,
+                1.0,
+                &self.scale(2.0 * next_rho / delta, &r),
+            );
+            rho = next_rho;
+        }
+        z
+    }
+
+    /// The vector scaled by `D⁻¹` and a constant.
+    fn scale(&self, alpha: f64, vector: &[f64]) -> Vec<f64> {
+        vector
+            .iter()
+            .zip(self.inverse_diagonal.iter())
+            .map(|(x, d)| alpha * x * d)
+            .collect()
+    }
+}
+
+impl Preconditioner for Chebyshev {
+    fn apply(&self, r: &[f64]) -> Vec<f64> {
+        self.iterate(r.to_vec())
+    }
+}
+
+/// Estimate the extreme eigenvalues of `D⁻¹A` with the Lanczos iteration on the symmetric matrix
+/// `D^-½AD^-½`, which has the same eigenvalues. The extreme eigenvalues of the tridiagonal matrix
+/// it builds approach those of the matrix from inside the spectrum.
+///
+/// # Arguments
+/// * `matrix` - The matrix.
+/// * `inverse_diagonal` - The reciprocal of the diagonal entry of each row.
+/// * `steps` - The maximum number of iterations.
+///
+/// # Return values
+/// * `lower` - The estimate of the smallest eigenvalue.
+/// * `upper` - The estimate of the largest eigenvalue.
+fn estimate_eigenvalues(
+    matrix: &SparseMatrix,
+    inverse_diagonal: &[f64],
+    steps: usize,
+) -> (f64, f64) {
+    let nrow = matrix.local_nrow;
+    let scaling: Vec<f64> = inverse_diagonal.iter().map(|d| d.sqrt()).collect();
+    let scale = |vector: &[f64]| -> Vec<f64> {
+        vector
+            .iter()
+            .zip(scaling.iter())
+            .map(|(x, s)| x * s)
+            .collect()
+    };
+
+    // An arbitrary starting vector, which is unlikely to be orthogonal to any eigenvector
+    let start: Vec<f64> = (0..nrow)
+        .map(|row| ((matrix.start_row + row) as f64).sin() + 1.5)
+        .collect();
+    let mut v = waxpby(
+        nrow,
+        1.0 / ddot(nrow, &start, &start).sqrt(),
+        &start,
+        0.0,
+        &start,
+    );
+    let mut previous = vec![0.0; nrow];
+    let mut beta = 0.0;
+    let (mut alphas, mut betas) = (vec![], vec![]);
+    for step in 0..steps {
+        let product = scale(&sparsemv(matrix, &scale(&v)));
+        let w = waxpby(nrow, 1.0, &product, -beta, &previous);
+        let alpha = ddot(nrow, &w, &v);
+        let w = waxpby(nrow, 1.0, &w, -alpha, &v);
+        alphas.push(alpha);
+        beta = ddot(nrow, &w, &w).sqrt();
+        // The iteration stops early if it has found an invariant subspace
+        if step + 1 == steps || beta <= 1e-12 * alpha.abs() {
+            break;
+        }
+        betas.push(beta);
+        previous = v;
+        v = waxpby(nrow, 1.0 / beta, &w, 0.0, &w);
+    }
+    tridiagonal_extreme_eigenvalues(&alphas, &betas)
+}
+
+/// The smallest and largest eigenvalues of a symmetric tridiagonal matrix, found by bisection
+/// inside its Gershgorin interval.
+///
+/// # Arguments
+/// * `alphas` - The diagonal of the matrix.
+/// * `betas` - The entries next to the diagonal.
+fn tridiagonal_extreme_eigenvalues(alphas: &[f64], betas: &[f64]) -> (f64, f64) {
+    let n = alphas.len();
+    let radius = |i: usize| {
+        let before = if i > 0 { betas[i - 1].abs() } else { 0.0 };
+        let after = betas.get(i).map_or(0.0, |beta| beta.abs());
+        before + after
+    };
+    let low = (0..n)
+        .map(|i| alphas[i] - radius(i))
+        .fold(f64::MAX, f64::min);
+    let high = (0..n)
+        .map(|i| alphas[i] + radius(i))
+        .fold(f64::MIN, f64::max);
+
+    // The number of eigenvalues below `x`, which is the number of negative pivots of the
+    // factorisation of the matrix minus `x` times the identity, by Sylvester's law of inertia
+    let count_below = |x: f64| {
+        let mut pivot = 1.0;
+        let mut count = 0;
+        for i in 0..n {
+            let coupling = if i > 0 { betas[i - 1].powi(2) } else { 0.0 };
+            pivot = alphas[i] - x - coupling / pivot;
+            if pivot == 0.0 {
+                pivot = f64::EPSILON;
+            }
+            if pivot < 0.0 {
+                count += 1;
+            }
+        }
+        count
+    };
+    // The smallest `x` with at least `k` eigenvalues below it is the `k`th smallest eigenvalue
+    let bisect = |k: usize| {
+        let (mut low, mut high) = (low, high);
+        for _ in 0..100 {
+            let middle = (low + high) / 2.0;
+            if count_below(middle) >= k {
+                high = middle;
+            } else {
+                low = middle;
+            }
+        }
+        high
+    };
+    (bisect(1), bisect(n))
+}
+
+#[test]
+fn test_chebyshev() {
+    // The eigenvalues of the 1D Laplacian `tridiag(-1, 2, -1)` are `2 - 2cos(kπ/(n + 1))`
+    let (alphas, betas) = (vec![2.0; 4], vec![-1.0; 3]);
+    let (lower, upper) = tridiagonal_extreme_eigenvalues(&alphas, &betas);
+    let exact = |k: f64| 2.0 - 2.0 * (k * std::f64::consts::PI / 5.0).cos();
+    assert!((lower - exact(1.0)).abs() < 1e-12);
+    assert!((upper - exact(4.0)).abs() < 1e-12);
+
+    let stencil = super::StencilConfig::new(super::Stencil::SevenPoint).weights(6.0, -1.0);
+    let (matrix, guess, rhs, exact) =
+        SparseMatrix::generate_matrix_with_stencil(16, 16, 16, stencil);
+    let chebyshev = Chebyshev::new(&matrix, 4);
+    // The eigenvalues of `D⁻¹A` for the Laplacian are inside `(0, 2)`
+    assert!(0.0 < chebyshev.lower && chebyshev.lower < chebyshev.upper);
+    assert!(chebyshev.upper > 1.5 && chebyshev.upper < 2.2);
+
+    // The preconditioner is symmetric, so `x·M⁻¹y = y·M⁻¹x`
+    let x: Vec<f64> = (0..4096).map(|i| (i as f64).sin()).collect();
+    let y: Vec<f64> = (0..4096).map(|i| (i as f64).cos()).collect();
+    let xy: f64 = x.iter().zip(chebyshev.apply(&y)).map(|(a, b)| a * b).sum();
+    let yx: f64 = y.iter().zip(chebyshev.apply(&x)).map(|(a, b)| a * b).sum();
+    assert!((xy - yx).abs() < 1e-10 * xy.abs());
+
+    // Smoothing from zero is the same as applying the preconditioner
+    let mut z = vec![0.0; 4096];
+    chebyshev.smooth(&x, &mut z);
+    assert_eq!(z, chebyshev.apply(&x));
+
+    let mut config = super::SolverConfig::new()
+        .tolerance(1e-10)
+        .verbosity(super::Verbosity::Quiet);
+    let report = super::pcg(&matrix, &rhs, &guess, &chebyshev, &mut config);
+    let jacobi = super::Jacobi::new(&matrix);
+    let jacobi_report = super::pcg(&matrix, &rhs, &guess, &jacobi, &mut config);
+    assert_eq!(report.reason, super::ConvergenceReason::Converged);
+    assert!(report.iterations < jacobi_report.iterations);
+    for (actual, expected) in report.solution.iter().zip(exact) {
+        assert!((expected - actual).abs() < 1e-8);
+    }
+    assert_eq!(
+        "chebyshev".parse(),
+        Ok(super::PreconditionerKind::Chebyshev)
+    );
+}
@@ -0,0 +1,400 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use super::{SparseMatrix, Stencil, StencilConfig};
+
+/// The pattern of the diffusion coefficient of each cell of a heterogeneous diffusion problem.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoefficientField {
+    /// Every cell has a coefficient of one.
+    Constant,
+    /// Each cell has an independent random coefficient, log-uniformly distributed between one and
+    /// the contrast.
+    Random,
+    /// Layers stacked along the z dimension, alternating between one and the contrast.
+    Layered,
+    /// Channels running along the x dimension with a coefficient of the contrast, in a background
+    /// with a coefficient of one.
+    Channelised,
+}
+
+impl FromStr for CoefficientField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "constant" => Ok(CoefficientField::Constant),
+            "random" => Ok(CoefficientField::Random),
+            "layered" => Ok(CoefficientField::Layered),
+            "channelised" | "channelized" | "channels" => Ok(CoefficientField::Channelised),
+            _ => Err(format!(
+                "Unknown coefficient field `{s}`, expected one of `constant`, `random`, `layered` \
+                 or `channelised`"
+            )),
+        }
+    }
+}
+
+/// The manufactured solution that the right hand side of a generated problem is chosen for, where
+/// the mesh spans the unit cube and each value is taken at the centre of its cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ManufacturedSolution {
+    /// The solution is one everywhere.
+    Ones,
+    /// The solution is `x + y + z`.
+    Linear,
+    /// The solution is `sin(πx) sin(πy) sin(πz)`.
+    Sine,
+}
+
+impl FromStr for ManufacturedSolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ones" => Ok(ManufacturedSolution::Ones),
+            "linear" => Ok(ManufacturedSolution::Linear),
+            "sine" => Ok(ManufacturedSolution::Sine),
+            _ => Err(format!(
+                "Unknown manufactured solution `{s}`, expected one of `ones`, `linear` or `sine`"
+            )),
+        }
+    }
+}
+
+impl ManufacturedSolution {
+    /// The value of the solution at a cell of the mesh.
+    ///
+    /// # Arguments
+    /// * `cell` - The position of the cell in the mesh.
+    /// * `mesh` - The size of the mesh in each dimension.
+    pub fn value(&self, cell: [usize; 3], mesh: [usize; 3]) -> f64 {
+        let [x, y, z] = [0, 1, 2].map(|dim| (cell[dim] as f64 + 0.5) / mesh[dim] as f64);
+        match self {
+            ManufacturedSolution::Ones => 1.0,
+            ManufacturedSolution::Linear => x + y + z,
+            ManufacturedSolution::Sine => (PI * x).sin() * (PI * y).sin() * (PI * z).sin(),
+        }
+    }
+}
+
+/// The coefficients and manufactured solution of a heterogeneous diffusion problem, built up from
+/// the pattern of its coefficients, for example
+/// `DiffusionConfig::new(CoefficientField::Random).contrast(1e4).seed(7)`.
+///
+/// # Fields
+/// * `field` - The pattern of the coefficient of each cell.
+/// * `contrast` - The ratio between the largest and smallest coefficients.
+/// * `seed` - The seed of the random coefficients.
+/// * `layers` - The number of layers, or the number of channels across each of the y and z
+///   dimensions.
+/// * `solution` - The manufactured solution the right hand side is chosen for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffusionConfig {
+    pub field: CoefficientField,
+    pub contrast: f64,
+    pub seed: u64,
+    pub layers: usize,
+    pub solution: ManufacturedSolution,
+}
+
+impl Default for DiffusionConfig {
+    fn default() -> Self {
+        DiffusionConfig::new(CoefficientField::Random)
+    }
+}
+
+impl From<CoefficientField> for DiffusionConfig {
+    fn from(field: CoefficientField) -> Self {
+        DiffusionConfig::new(field)
+    }
+}
+
+impl DiffusionConfig {
+    /// Create a configuration with a contrast of `100`, a seed of `0`, `4` layers and a solution
+    /// of all ones.
+    ///
+    /// # Arguments
+    /// * `field` - The pattern of the coefficient of each cell.
+    pub fn new(field: CoefficientField) -> Self {
+        DiffusionConfig {
+            field,
+            contrast: 100.0,
+            seed: 0,
+            layers: 4,
+            solution: ManufacturedSolution::Ones,
+        }
+    }
+
+    /// Set the ratio between the largest and smallest coefficients.
+    pub fn contrast(mut self, contrast: f64) -> Self {
+        self.contrast = contrast;
+        self
+    }
+
+    /// Set the seed of the random coefficients.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the number of layers, or of channels across each dimension.
+    pub fn layers(mut self, layers: usize) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Set the manufactured solution the right hand side is chosen for.
+    pub fn solution(mut self, solution: ManufacturedSolution) -> Self {
+        self.solution = solution;
+        self
+    }
+
+    /// The diffusion coefficient of a cell of the mesh, which only depends on its position in the
+    /// whole mesh, so is the same however the mesh is divided up.
+    ///
+    /// # Arguments
+    /// * `cell` - The position of the cell in the mesh.
+    /// * `mesh` - The size of the mesh in each dimension.
+    pub fn coefficient(&self, cell: [usize; 3], mesh: [usize; 3]) -> f64 {
+        let [gx, gy, gz] = cell;
+        let [mx, my, mz] = mesh;
+        let high = match self.field {
+            CoefficientField::Constant => return 1.0,
+            CoefficientField::Random => {
+                let index = (gz * my + gy) * mx + gx;
+                return self.contrast.powf(unit_random(self.seed, index as u64));
+            }
+            CoefficientField::Layered => (gz * self.layers / mz) % 2 == 1,
+            CoefficientField::Channelised => {
+                // Alternate between background and channel bands across each of the y and z
+                // dimensions, so the channels are where two channel bands cross
+                let bands = 2 * self.layers + 1;
+                (gy * bands / my) % 2 == 1 && (gz * bands / mz) % 2 == 1
+            }
+        };
+        if high {
+            self.contrast
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A uniformly distributed random number in `[0, 1)`, from the SplitMix64 hash of an index.
+///
+/// # Arguments
+/// * `seed` - The seed of the random sequence.
+/// * `index` - The index into the random sequence.
+fn unit_random(seed: u64, index: u64) -> f64 {
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// The coefficient of the face between two cells, which is the harmonic mean of their
+/// coefficients, so the coupling between them is the same from either side.
+fn face_coefficient(k1: f64, k2: f64) -> f64 {
+    2.0 * k1 * k2 / (k1 + k2)
+}
+
+impl SparseMatrix {
+    /// Generates a heterogeneous diffusion problem, discretised with the 7-point finite volume
+    /// scheme and zero Dirichlet boundaries, so the matrix is symmetric positive definite for any
+    /// positive coefficients. The right hand side is chosen so that the exact solution is the
+    /// manufactured solution.
+    ///
+    /// # Arguments
+    ///  * `nx` - Size of x dimension.
+    ///  * `ny` - Size of y dimension.
+    ///  * `nz` - Size of z dimension.
+    ///  * `diffusion` - The coefficients of the cells, and the manufactured solution.
+    ///
+    /// # Return values
+    ///  * `matrix` - Generated sparse matrix.
+    ///  * `guess` - Inital guess for the mesh.
+    ///  * `rhs` - Right hand side.
+    ///  * `exact` - Exact solution (the manufactured solution).
+    pub fn generate_diffusion_matrix(
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        diffusion: DiffusionConfig,
+    ) -> (Self, Vec<f64>, Vec<f64>, Vec<f64>) {
+        let mesh = [nx, ny, nz];
+        // The size of our sub-block (must be non-zero)
+        let local_nrow = nx * ny * nz;
+        assert!(local_nrow > 0);
+        // The approximate number of non-zeros per row (excluding boundary nodes)
+        let local_nnz = 7 * local_nrow;
+        let start_row = 0;
+        let stop_row = local_nrow - 1;
+
+        // In non-mpi mode, the total row, column, and non-zero sizes are the same as the local ones
+        let (total_nnz, total_nrow, local_ncol) = (local_nnz, local_nrow, local_nrow);
+        // The finite volume scheme couples each cell to the cells it shares a face with
+        let stencil = StencilConfig::new(Stencil::SevenPoint);
+
+        let mut nnz_in_row = Vec::with_capacity(local_nrow);
+        let mut row_start_inds: Vec<usize> = Vec::with_capacity(local_nrow);
+
+        let mut guess: Vec<f64> = Vec::with_capacity(local_nrow);
+        let mut rhs: Vec<f64> = Vec::with_capacity(local_nrow);
+        let mut exact: Vec<f64> = Vec::with_capacity(local_nrow);
+
+        let mut list_of_vals: Vec<f64> = Vec::with_capacity(local_nnz);
+        let mut list_of_inds: Vec<usize> = Vec::with_capacity(local_nnz);
+
+        for iz in 0..nz {
+            for iy in 0..ny {
+                for ix in 0..nx {
+                    let coefficient = diffusion.coefficient([ix, iy, iz], mesh);
+                    let start_ind = list_of_vals.len();
+                    let mut diagonal_ind = start_ind;
+                    let mut diagonal = 0.0;
+                    let mut row_product = 0.0;
+                    for sz in -1..=1 {
+                        for sy in -1..=1 {
+                            for sx in -1..=1 {
+                                if !stencil.shape.contains(sx, sy, sz) {
+                                    continue;
+                                }
+                                if (sx, sy, sz) == (0, 0, 0) {
+                                    // The diagonal is filled in once all of the faces are known
+                                    diagonal_ind = list_of_vals.len();
+                                    list_of_vals.push(0.0);
+                                    list_of_inds.push(start_row + iz * nx * ny + iy * nx + ix);
+                                    continue;
+                                }
+                                let Some(cell) =
+                                    stencil.neighbour([ix, iy, iz], [sx, sy, sz], mesh)
+                                else {
+                                    // A boundary face, half a cell from the fixed zero boundary
+                                    diagonal += 2.0 * coefficient;
+                                    continue;
+                                };
+                                let [cx, cy, cz] = cell;
+                                let weight = face_coefficient(
+                                    coefficient,
+                                    diffusion.coefficient(cell, mesh),
+                                );
+                                diagonal += weight;
+                                row_product -= weight * diffusion.solution.value(cell, mesh);
+                                list_of_vals.push(-weight);
+                                list_of_inds.push(start_row + cz * nx * ny + cy * nx + cx);
+                            }
+                        }
+                    }
+                    list_of_vals[diagonal_ind] = diagonal;
+                    let value = diffusion.solution.value([ix, iy, iz], mesh);
+                    row_start_inds.push(start_ind);
+                    nnz_in_row.push(list_of_vals.len() - start_ind);
+                    guess.push(0.0);
+                    rhs.push(row_product + diagonal * value);
+                    exact.push(value);
+                }
+            }
+        }
+
+        let matrix = SparseMatrix {
+            start_row,
+            stop_row,
+            local_nrow,
+            total_nnz,
+            total_nrow,
+            local_ncol,
+            local_nnz,
+            nnz_in_row,
+            row_start_inds,
+            list_of_vals,
+            list_of_inds,
+            geometry: None,
+        };
+        (matrix, guess, rhs, exact)
+    }
+}
+
+#[test]
+fn test_diffusion_config() {
+    let config = DiffusionConfig::new(CoefficientField::Layered)
+        .contrast(10.0)
+        .layers(2);
+    let mesh = [4, 4, 4];
+    let layers: Vec<f64> = (0..4)
+        .map(|gz| config.coefficient([0, 0, gz], mesh))
+        .collect();
+    assert_eq!(layers, vec![1.0, 1.0, 10.0, 10.0]);
+
+    let config = DiffusionConfig {
+        field: CoefficientField::Channelised,
+        ..config
+    };
+    assert_eq!(config.coefficient([0, 0, 0], [5, 5, 5]), 1.0);
+    assert_eq!(config.coefficient([3, 1, 1], [5, 5, 5]), 10.0);
+    assert_eq!(config.coefficient([3, 1, 2], [5, 5, 5]), 1.0);
+
+    let config = DiffusionConfig::new(CoefficientField::Random).contrast(1e4);
+    let coefficients: Vec<f64> = (0..64)
+        .map(|gx| config.coefficient([gx, 0, 0], [64, 1, 1]))
+        .collect();
+    assert!(coefficients.iter().all(|&k| (1.0..1e4).contains(&k)));
+    assert!(coefficients.iter().any(|&k| k > 100.0));
+    assert!(coefficients.iter().any(|&k| k < 100.0));
+    // The field is reproducible from its seed, and changes with it
+    assert_eq!(
+        config.coefficient([5, 6, 7], [8, 8, 8]),
+        config.coefficient([5, 6, 7], [8, 8, 8])
+    );
+    assert_ne!(
+        config.coefficient([5, 6, 7], [8, 8, 8]),
+        config.seed(1).coefficient([5, 6, 7], [8, 8, 8])
+    );
+
+    assert_eq!("channels".parse(), Ok(CoefficientField::Channelised));
+    assert_eq!("sine".parse(), Ok(ManufacturedSolution::Sine));
+    assert!("smooth".parse::<ManufacturedSolution>().is_err());
+}
+
+#[test]
+fn test_diffusion_matrix() {
+    let (nx, ny, nz) = (4, 3, 5);
+    let diffusion = DiffusionConfig::new(CoefficientField::Random)
+        .contrast(1e3)
+        .seed(42)
+        .solution(ManufacturedSolution::Sine);
+    let (matrix, guess, rhs, exact) =
+        SparseMatrix::generate_diffusion_matrix(nx, ny, nz, diffusion);
+    assert_eq!(matrix.local_nrow, 60);
+    assert_eq!(matrix.nnz_in_row[0], 4);
+    assert_eq!(matrix.nnz_in_row[nx * ny + nx + 1], 7);
+    assert_eq!(guess, vec![0.0; 60]);
+
+    // The matrix is symmetric and diagonally dominant, strictly so on the boundary, so it is
+    // positive definite
+    let entry = |row: usize, col: usize| {
+        let start = matrix.row_start_inds[row];
+        (start..start + matrix.nnz_in_row[row])
+            .find(|&ind| matrix.list_of_inds[ind] == col)
+            .map(|ind| matrix.list_of_vals[ind])
+    };
+    for row in 0..matrix.local_nrow {
+        let start = matrix.row_start_inds[row];
+        let mut off_diagonal = 0.0;
+        for ind in start..start + matrix.nnz_in_row[row] {
+            let col = matrix.list_of_inds[ind];
+            if col != row {
+                assert_eq!(entry(col, row), Some(matrix.list_of_vals[ind]));
+                off_diagonal += matrix.list_of_vals[ind].abs();
+            }
+        }
+        assert!(entry(row, row).unwrap() >= off_diagonal);
+    }
+    assert!(entry(0, 0).unwrap() > 1.5 * (entry(0, 1).unwrap() + entry(0, nx).unwrap()).abs());
+
+    let product = super::hpccg_internals::sparsemv(&matrix, &exact);
+    for (actual, expected) in product.iter().zip(rhs) {
+        assert!((actual - expected).abs() < 1e-9 * expected.abs().max(1.0));
+    }
+}
@@ -0,0 +1,252 @@
+use std::ops::ControlFlow;
+
+use super::{
+    ddot, mytimer, sparsemv, tick, tock, waxpby, ConvergenceReason, IterationInfo, SolveReport,
+    SolverConfig, SparseMatrix, StoppingCriteria, Timings, Verbosity,
+};
+
+/// A method to compute the approximate solution to `Ax = b` with the restarted generalised minimal
+/// residual method (GMRES(m)), which also works for non-symmetric matrices.
+///
+/// Each new basis vector is orthogonalised with modified Gram-Schmidt (twice if
+/// `config.reorthogonalise` is set), and the least squares problem is kept in upper triangular
+/// form with Givens rotations, so the residual is known at every iteration without computing it.
+/// The basis is discarded and rebuilt from the true residual every `config.restart` iterations.
+///
+/// # Arguments
+/// * `A` - The input sparse matrix.
+/// * `b` - The known right hand side vector.
+/// * `x` - The current approximate solution, which starts as the initial guess.
+/// * `config` - The settings of the solver.
+///
+/// # Return values
+/// * `report` - The approximate result, and how the solver got there.
+#[allow(non_snake_case)]
+pub fn gmres(A: &SparseMatrix, b: &[f64], x: &[f64], config: &mut SolverConfig) -> SolveReport {
+    let t_begin: f64 = mytimer();
+    let mut t_total: f64 = 0.0;
+    let mut t_ddot: f64 = 0.0;
+    let mut t_waxpby: f64 = 0.0;
+    let mut t_sparsemv: f64 = 0.0;
+
+    let nrow = A.local_nrow;
+    let restart = config.restart;
+    let passes = if config.reorthogonalise { 2 } else { 1 };
+
+    let mut result = x.to_owned();
+    let mut iteration = 0;
+
+    let max_iterations = config.max_iterations;
+
+    tick(&mut t_total);
+    let Ax = sparsemv(A, &result);
+    tock(&t_total, &mut t_sparsemv);
+
+    tick(&mut t_total);
+    let mut r = waxpby(nrow, 1.0, b, -1.0, &Ax);
+    tock(&t_total, &mut t_waxpby);
+
+    tick(&mut t_total);
+    let mut normr = ddot(nrow, &r, &r).sqrt();
+    tock(&t_total, &mut t_ddot);
+
+    if config.verbosity > Verbosity::Quiet {
+        println!("Initial Residual = {normr:+.5e}");
+    }
+    let flow = config.notify(IterationInfo {
+        iteration: 0,
+        residual: normr,
+        alpha: None,
+        beta: None,
+        elapsed: mytimer() - t_begin,
+    });
+
+    tick(&mut t_total);
+    let mut criteria = StoppingCriteria::new(config, normr, || ddot(nrow, b, b).sqrt());
+    tock(&t_total, &mut t_ddot);
+    let mut reason = criteria.check(0, normr, flow);
+
+    // Each cycle builds a basis of up to `restart` vectors from the residual, and then updates the
+    // solution with the combination of them which minimises the residual
+    while reason.is_none() && iteration + 1 < max_iterations {
+        let mut basis: Vec<Vec<f64>> = Vec::with_capacity(restart + 1);
+        tick(&mut t_total);
+        basis.push(waxpby(nrow, 1.0 / normr, &r, 0.0, &r));
+        tock(&t_total, &mut t_waxpby);
+
+        // The columns of the Hessenberg matrix, rotated to be upper triangular, the sines and
+        // cosines of the rotations, and the rotated right hand side of the least squares problem
+        let mut hessenberg: Vec<Vec<f64>> = Vec::with_capacity(restart);
+        let mut rotations: Vec<(f64, f64)> = Vec::with_capacity(restart);
+        let mut g = vec![normr];
+
+        for j in 0..restart {
+            if reason.is_some() || iteration + 1 >= max_iterations {
+                break;
+            }
+            let k = iteration + 1;
+
+            tick(&mut t_total);
+            let mut w = sparsemv(A, &basis[j]);
+            tock(&t_total, &mut t_sparsemv);
+
+            let mut h = vec![0.0; j + 2];
+            for _ in 0..passes {
+                for (i, v) in basis.iter().enumerate() {
+                    tick(&mut t_total);
+                    let projection = ddot(nrow, &w, v);
+                    tock(&t_total, &mut t_ddot);
+                    tick(&mut t_total);
+                    w = waxpby(nrow, 1.0, &w, -projection, v);
+                    tock(&t_total, &mut t_waxpby);
+                    h[i] += projection;
+                }
+            }
+            tick(&mut t_total);
+            h[j + 1] = ddot(nrow, &w, &w).sqrt();
+            tock(&t_total, &mut t_ddot);
+
+            // Apply the previous rotations to the new column, then rotate away its subdiagonal
+            for (i, &(c, s)) in rotations.iter().enumerate() {
+                let (upper, lower) = (h[i], h[i + 1]);
+                h[i] = c * upper + s * lower;
+                h[i + 1] = -s * upper + c * lower;
+            }
+            let denominator = h[j].hypot(h[j + 1]);
+            let (c, s) = if denominator == 0.0 {
+                (1.0, 0.0)
+            } else {
+                (h[j] / denominator, h[j + 1] / denominator)
+            };
+            let subdiagonal = h[j + 1];
+            let last_g = g[j];
+            h[j] = denominator;
+            h.truncate(j + 1);
+            rotations.push((c, s));
+            g.push(-s * g[j]);
+            g[j] *= c;
+
+            normr = g[j + 1].abs();
+            if config.should_print(k) {
+                println!("Iteration = {k} , Residual = {normr:+.5e}");
+            }
+            let flow = config.notify(IterationInfo {
+                iteration: k,
+                residual: normr,
+                alpha: None,
+                beta: None,
+                elapsed: mytimer() - t_begin,
+            });
+            reason = criteria.check(k, normr, flow);
+            // A step which broke down or diverged is dropped, so the solution is built from the
+            // good ones before it
+            if reason.is_some_and(ConvergenceReason::is_failure) {
+                g.truncate(j + 1);
+                g[j] = last_g;
+                break;
+            }
+            iteration = k;
+            hessenberg.push(h);
+
+            // The basis spans the solution when the new vector vanishes
+            if subdiagonal == 0.0 {
+                break;
+            }
+            tick(&mut t_total);
+            basis.push(waxpby(nrow, 1.0 / subdiagonal, &w, 0.0, &w));
+            tock(&t_total, &mut t_waxpby);
+        }
+
+        // Solve the upper triangular system for the coefficients of the basis vectors
+        let size = hessenberg.len();
+        let mut y = vec![0.0; size];
+        for i in (0..size).rev() {
+            let sum: f64 = (i + 1..size).map(|col| hessenberg[col][i] * y[col]).sum();
+            y[i] = (g[i] - sum) / hessenberg[i][i];
+        }
+        tick(&mut t_total);
+        for (coefficient, v) in y.iter().zip(basis.iter()) {
+            result = waxpby(nrow, 1.0, &result, *coefficient, v);
+        }
+        tock(&t_total, &mut t_waxpby);
+
+        // Restart from the true residual, which rounding errors may have drifted away from
+        tick(&mut t_total);
+        let Ax = sparsemv(A, &result);
+        tock(&t_total, &mut t_sparsemv);
+
+        tick(&mut t_total);
+        r = waxpby(nrow, 1.0, b, -1.0, &Ax);
+        tock(&t_total, &mut t_waxpby);
+
+        tick(&mut t_total);
+        normr = ddot(nrow, &r, &r).sqrt();
+        tock(&t_total, &mut t_ddot);
+        // The true residual decides whether the solver converged, unless it stopped for another
+        // reason
+        if matches!(reason, None | Some(ConvergenceReason::Converged)) {
+            reason = criteria.check(iteration, normr, ControlFlow::Continue(()));
+        }
+
+        if size == 0 {
+            break;
+        }
+    }
+
+    let reason = reason.unwrap_or(ConvergenceReason::MaxIterations);
+
+    SolveReport {
+        solution: result,
+        iterations: iteration,
+        final_residual: normr,
+        reason,
+        residual_history: config.take_residual_history(),
+        times: Timings {
+            total: mytimer() - t_begin,
+            ddot: t_ddot,
+            waxpby: t_waxpby,
+            sparsemv: t_sparsemv,
+            precondition: 0.0,
+        },
+    }
+}
+
+#[test]
+fn test_gmres() {
+    let stencil = super::StencilConfig::convection_diffusion(2.5);
+    let (matrix, guess, rhs, exact) = SparseMatrix::generate_matrix_with_stencil(6, 6, 6, stencil);
+    for reorthogonalise in [false, true] {
+        let mut residuals = vec![];
+        let mut config = SolverConfig::new()
+            .max_iterations(300)
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet)
+            .restart(10)
+            .reorthogonalise(reorthogonalise)
+            .callback(|_, normr| residuals.push(normr));
+        let report = gmres(&matrix, &rhs, &guess, &mut config);
+        drop(config);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.final_residual <= 1e-10);
+        assert!(report.iterations > 10);
+        assert_eq!(residuals.len(), report.iterations as usize + 1);
+        // The residual never increases within a cycle, as each iteration minimises it over a
+        // larger space
+        for cycle in residuals[1..].chunks(10) {
+            assert!(cycle.windows(2).all(|pair| pair[1] <= pair[0]));
+        }
+        for (actual, expected) in report.solution.iter().zip(exact.iter()) {
+            assert!((expected - actual).abs() < 1e-8);
+        }
+    }
+
+    // Without restarts, GMRES finds the exact solution within as many iterations as there are
+    // distinct eigenvalues
+    let (matrix, guess, rhs, _) = SparseMatrix::generate_matrix(2, 2, 2);
+    let mut config = SolverConfig::new()
+        .tolerance(1e-12)
+        .verbosity(Verbosity::Quiet);
+    let report = gmres(&matrix, &rhs, &guess, &mut config);
+    assert_eq!(report.reason, ConvergenceReason::Converged);
+    assert!(report.iterations <= 2);
+}
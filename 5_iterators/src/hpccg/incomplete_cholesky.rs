@@ -0,0 +1,211 @@
+use super::{Preconditioner, SparseMatrix};
+
+/// The incomplete Cholesky factorisation with zero fill-in, IC(0), of a symmetric matrix, which
+/// is `A ≈ LLᵀ` where `L` is lower triangular with the same sparsity pattern as the lower triangle
+/// of `A`.
+///
+/// The triangular solves are level scheduled: the rows are grouped into levels so that each row
+/// only depends on the rows of earlier levels, and all the rows of a level can be solved at once.
+///
+/// # Fields
+/// * `diagonal` - The diagonal of `L`.
+/// * `lower` - The column and value of each entry of `L` before the diagonal, for each row.
+/// * `upper` - The column and value of each entry of `Lᵀ` after the diagonal, for each row.
+/// * `forward_levels` - The rows of each level of the forward solve with `L`.
+/// * `backward_levels` - The rows of each level of the backward solve with `Lᵀ`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncompleteCholesky {
+    pub diagonal: Vec<f64>,
+    pub lower: Vec<Vec<(usize, f64)>>,
+    pub upper: Vec<Vec<(usize, f64)>>,
+    pub forward_levels: Vec<Vec<usize>>,
+    pub backward_levels: Vec<Vec<usize>>,
+}
+
+impl IncompleteCholesky {
+    /// Factorise a symmetric matrix. The factorisation exists for diagonally dominant matrices
+    /// with non-positive off-diagonal entries, such as the generated problems, but may break down
+    /// for other positive definite matrices.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix to precondition.
+    ///
+    /// # Return values
+    /// * `ic` - The preconditioner, or an error if the factorisation breaks down at a pivot which
+    ///   is not positive.
+    pub fn new(matrix: &SparseMatrix) -> Result<Self, String> {
+        let nrow = matrix.local_nrow;
+        // The entries of the lower triangle of each row, in column order
+        let mut lower: Vec<Vec<(usize, f64)>> = (0..nrow)
+            .map(|row| {
+                let start = matrix.row_start_inds[row];
+                let mut entries: Vec<(usize, f64)> = (start..start + matrix.nnz_in_row[row])
+                    .map(|ind| (matrix.list_of_inds[ind], matrix.list_of_vals[ind]))
+                    .filter(|&(col, _)| col < row)
+                    .collect();
+                entries.sort_by_key(|&(col, _)| col);
+                entries
+            })
+            .collect();
+        let mut diagonal = matrix.diagonal();
+
+        // Each row of `L` only depends on the rows before it, which have already been factorised
+        for row in 0..nrow {
+            let (factorised, rest) = lower.split_at_mut(row);
+            let entries = &mut rest[0];
+            for k in 0..entries.len() {
+                let (col, value) = entries[k];
+                let product = sparse_dot(&entries[..k], &factorised[col]);
+                entries[k].1 = (value - product) / diagonal[col];
+            }
+            let squares: f64 = entries.iter().map(|(_, value)| value * value).sum();
+            let pivot = diagonal[row] - squares;
+            if pivot.is_nan() || pivot <= 0.0 {
+                return Err(format!(
+                    "The incomplete Cholesky factorisation breaks down at row {row}, whose pivot \
+                     {pivot} is not positive"
+                ));
+            }
+            diagonal[row] = pivot.sqrt();
+        }
+
+        let mut upper: Vec<Vec<(usize, f64)>> = vec![vec![]; nrow];
+        for (row, entries) in lower.iter().enumerate() {
+            for &(col, value) in entries {
+                upper[col].push((row, value));
+            }
+        }
+
+        let forward_levels = level_schedule(&lower, 0..nrow);
+        let backward_levels = level_schedule(&upper, (0..nrow).rev());
+        Ok(IncompleteCholesky {
+            diagonal,
+            lower,
+            upper,
+            forward_levels,
+            backward_levels,
+        })
+    }
+
+    /// Solve a triangular system with the diagonal of `L`, one level at a time.
+    ///
+    /// # Arguments
+    /// * `levels` - The rows of each level of the solve.
+    /// * `off_diagonal` - The entries off the diagonal of each row of the triangular matrix.
+    /// * `rhs` - The right hand side vector.
+    fn solve(
+        &self,
+        levels: &[Vec<usize>],
+        off_diagonal: &[Vec<(usize, f64)>],
+        rhs: &[f64],
+    ) -> Vec<f64> {
+        let mut x = vec![0.0; self.diagonal.len()];
+        for rows in levels {
+            for &row in rows {
+                let sum: f64 = off_diagonal[row]
+                    .iter()
+                    .map(|&(col, value)| value * x[col])
+                    .sum();
+                x[row] = (rhs[row] - sum) / self.diagonal[row];
+            }
+        }
+        x
+    }
+}
+
+impl Preconditioner for IncompleteCholesky {
+    fn apply(&self, r: &[f64]) -> Vec<f64> {
+        let y = self.solve(&self.forward_levels, &self.lower, r);
+        self.solve(&self.backward_levels, &self.upper, &y)
+    }
+}
+
+/// The dot product of two sparse rows, whose entries are in column order.
+fn sparse_dot(lhs: &[(usize, f64)], rhs: &[(usize, f64)]) -> f64 {
+    let (mut i, mut j) = (0, 0);
+    let mut sum = 0.0;
+    while i < lhs.len() && j < rhs.len() {
+        match lhs[i].0.cmp(&rhs[j].0) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                sum += lhs[i].1 * rhs[j].1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    sum
+}
+
+/// Group the rows of a triangular matrix into levels, where each row is in the level after the
+/// last of the levels of the rows it depends on.
+///
+/// # Arguments
+/// * `dependencies` - The entries off the diagonal of each row of the triangular matrix.
+/// * `order` - The order the rows are solved in, so each row comes after its dependencies.
+fn level_schedule(
+    dependencies: &[Vec<(usize, f64)>],
+    order: impl Iterator<Item = usize>,
+) -> Vec<Vec<usize>> {
+    let mut row_levels = vec![0; dependencies.len()];
+    let mut levels: Vec<Vec<usize>> = vec![];
+    for row in order {
+        let level = dependencies[row]
+            .iter()
+            .map(|&(col, _)| row_levels[col] + 1)
+            .max()
+            .unwrap_or(0);
+        if level == levels.len() {
+            levels.push(vec![]);
+        }
+        levels[level].push(row);
+        row_levels[row] = level;
+    }
+    levels
+}
+
+#[test]
+fn test_incomplete_cholesky() {
+    // A tridiagonal matrix has no fill-in, so its incomplete factorisation is exact
+    let stencil = super::StencilConfig::new(super::Stencil::SevenPoint).weights(2.0, -1.0);
+    let (matrix, _, rhs, exact) = SparseMatrix::generate_matrix_with_stencil(10, 1, 1, stencil);
+    let ic = IncompleteCholesky::new(&matrix).unwrap();
+    assert_eq!(ic.forward_levels.len(), 10);
+    assert_eq!(ic.upper[0], vec![(1, ic.lower[1][0].1)]);
+    for (actual, expected) in ic.apply(&rhs).iter().zip(exact) {
+        assert!((expected - actual).abs() < 1e-12);
+    }
+
+    // Rows of the 7-point stencil on the same anti-diagonal plane of the mesh are independent
+    let stencil = super::StencilConfig::new(super::Stencil::SevenPoint)
+        .weights(24.0, -1.0)
+        .anisotropy(1.0, 1.0, 10.0);
+    let (matrix, guess, rhs, exact) = SparseMatrix::generate_matrix_with_stencil(8, 8, 8, stencil);
+    let ic = IncompleteCholesky::new(&matrix).unwrap();
+    assert_eq!(ic.forward_levels.len(), 8 * 3 - 2);
+    assert_eq!(ic.backward_levels[0], vec![8 * 8 * 8 - 1]);
+
+    // The strong coupling in the z dimension is captured by the factorisation, but not by the
+    // diagonal alone
+    let mut config = super::SolverConfig::new()
+        .tolerance(1e-10)
+        .verbosity(super::Verbosity::Quiet);
+    let report = super::pcg(&matrix, &rhs, &guess, &ic, &mut config);
+    let jacobi = super::Jacobi::new(&matrix);
+    let jacobi_report = super::pcg(&matrix, &rhs, &guess, &jacobi, &mut config);
+    assert_eq!(report.reason, super::ConvergenceReason::Converged);
+    assert!(report.iterations < jacobi_report.iterations);
+    for (actual, expected) in report.solution.iter().zip(exact) {
+        assert!((expected - actual).abs() < 1e-8);
+    }
+
+    // A negative definite matrix has no positive pivots, which is an error rather than a
+    // factorisation of not-a-numbers
+    let stencil = super::StencilConfig::new(super::Stencil::SevenPoint).weights(-6.0, 1.0);
+    let (matrix, _, _, _) = SparseMatrix::generate_matrix_with_stencil(4, 4, 4, stencil);
+    assert!(IncompleteCholesky::new(&matrix).is_err());
+    let kind = super::PreconditionerKind::IncompleteCholesky;
+    let smoother = super::SmootherKind::SymmetricGaussSeidel;
+    assert!(kind.build(&matrix, smoother).is_err());
+}
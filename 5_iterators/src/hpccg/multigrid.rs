@@ -0,0 +1,249 @@
+use std::str::FromStr;
+
+use super::{
+    sparsemv, waxpby, Chebyshev, Geometry, Preconditioner, SparseMatrix, SymmetricGaussSeidel,
+};
+
+/// The number of steps of the Chebyshev iteration when it is the smoother of a level.
+const CHEBYSHEV_STEPS: usize = 4;
+
+/// The smoothers which can be chosen for the levels of multigrid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmootherKind {
+    /// A forward and a backward Gauss-Seidel sweep in a multicolour ordering.
+    SymmetricGaussSeidel,
+    /// A few steps of the Chebyshev iteration, which only needs `sparsemv` and `waxpby`.
+    Chebyshev,
+}
+
+impl FromStr for SmootherKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "symgs" => Ok(SmootherKind::SymmetricGaussSeidel),
+            "chebyshev" => Ok(SmootherKind::Chebyshev),
+            _ => Err(format!(
+                "Unknown smoother `{s}`, expected `symgs` or `chebyshev`"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for SmootherKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SmootherKind::SymmetricGaussSeidel => write!(f, "symgs"),
+            SmootherKind::Chebyshev => write!(f, "chebyshev"),
+        }
+    }
+}
+
+/// The smoother of one level of multigrid, which holds a copy of the matrix of its level.
+pub enum Smoother {
+    SymmetricGaussSeidel(SymmetricGaussSeidel),
+    Chebyshev(Chebyshev),
+}
+
+impl Smoother {
+    /// Create a smoother of a matrix.
+    ///
+    /// # Arguments
+    /// * `kind` - The smoother to create.
+    /// * `matrix` - The matrix of the level.
+    pub fn new(kind: SmootherKind, matrix: &SparseMatrix) -> Self {
+        match kind {
+            SmootherKind::SymmetricGaussSeidel => {
+                Smoother::SymmetricGaussSeidel(SymmetricGaussSeidel::new(matrix))
+            }
+            SmootherKind::Chebyshev => {
+                Smoother::Chebyshev(Chebyshev::smoother(matrix, CHEBYSHEV_STEPS))
+            }
+        }
+    }
+
+    /// The matrix of the level.
+    pub fn matrix(&self) -> &SparseMatrix {
+        match self {
+            Smoother::SymmetricGaussSeidel(symgs) => &symgs.matrix,
+            Smoother::Chebyshev(chebyshev) => &chebyshev.matrix,
+        }
+    }
+
+    /// Improve an approximate solution `z` of `Az = r` on the level.
+    ///
+    /// # Arguments
+    /// * `r` - The right hand side vector.
+    /// * `z` - The approximate solution.
+    pub fn smooth(&self, r: &[f64], z: &mut [f64]) {
+        match self {
+            Smoother::SymmetricGaussSeidel(symgs) => symgs.smooth(r, z),
+            Smoother::Chebyshev(chebyshev) => chebyshev.smooth(r, z),
+        }
+    }
+}
+
+/// The geometric multigrid preconditioner of HPCG, which is one V-cycle over a hierarchy of
+/// meshes, each with half as many points in each dimension as the one before it.
+///
+/// The coarse matrices are generated from the same stencil as the matrix, on the coarser meshes.
+/// The residual is restricted to a coarser mesh by injection, taking the values at the fine points
+/// which are also coarse points, and the correction is prolongated back to the same points.
+/// Each level is smoothed with a symmetric Gauss-Seidel sweep or a few Chebyshev steps before and
+/// after the correction, which keeps the preconditioner symmetric.
+///
+/// # Fields
+/// * `levels` - The smoother of each level, from the finest to the coarsest, which holds a copy of
+///   the matrix of its level.
+/// * `injections` - The fine row of each coarse row, between each level and the next.
+pub struct Multigrid {
+    pub levels: Vec<Smoother>,
+    pub injections: Vec<Vec<usize>>,
+}
+
+impl Multigrid {
+    /// Create the multigrid hierarchy of a matrix generated from a stencil, coarsening until there
+    /// are `levels` levels or a dimension of the mesh has an odd size.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix to precondition.
+    /// * `levels` - The maximum number of levels, including the matrix itself.
+    /// * `smoother` - The smoother of every level.
+    ///
+    /// # Return values
+    /// * `multigrid` - The preconditioner, or an error if the matrix has no mesh to coarsen.
+    pub fn new(
+        matrix: &SparseMatrix,
+        levels: usize,
+        smoother: SmootherKind,
+    ) -> Result<Self, String> {
+        let Some(mut fine) = matrix.geometry else {
+            return Err("Multigrid needs a matrix generated from a stencil".to_string());
+        };
+        let mut smoothers = vec![Smoother::new(smoother, matrix)];
+        let mut injections = vec![];
+        while smoothers.len() < levels {
+            let Some(coarse) = fine.coarsen() else {
+                break;
+            };
+            let (coarse_matrix, _, _, _) = SparseMatrix::generate_matrix_with_stencil(
+                coarse.nx,
+                coarse.ny,
+                coarse.nz,
+                coarse.stencil,
+            );
+            smoothers.push(Smoother::new(smoother, &coarse_matrix));
+            injections.push(injection(&fine, &coarse));
+            fine = coarse;
+        }
+        Ok(Multigrid {
+            levels: smoothers,
+            injections,
+        })
+    }
+}
+
+impl Preconditioner for Multigrid {
+    fn apply(&self, r: &[f64]) -> Vec<f64> {
+        v_cycle(&self.levels, &self.injections, r)
+    }
+}
+
+/// The fine row of each point of a coarse mesh, which is the point with twice its coordinates.
+fn injection(fine: &Geometry, coarse: &Geometry) -> Vec<usize> {
+    let mut rows = Vec::with_capacity(coarse.nx * coarse.ny * coarse.nz);
+    for iz in 0..coarse.nz {
+        for iy in 0..coarse.ny {
+            for ix in 0..coarse.nx {
+                rows.push(2 * iz * fine.nx * fine.ny + 2 * iy * fine.nx + 2 * ix);
+            }
+        }
+    }
+    rows
+}
+
+/// Approximately solve `Az = r` on the first of the levels with a V-cycle: smooth, correct with
+/// the approximate solution of the restricted residual on the coarser levels, and smooth again.
+///
+/// # Arguments
+/// * `levels` - The smoothers of this level and the coarser ones.
+/// * `injections` - The fine row of each coarse row, between each level and the next.
+/// * `r` - The right hand side vector on this level.
+fn v_cycle(levels: &[Smoother], injections: &[Vec<usize>], r: &[f64]) -> Vec<f64> {
+    let (smoother, coarser) = levels.split_first().unwrap();
+    let nrow = smoother.matrix().local_nrow;
+    let mut z = vec![0.0; nrow];
+    smoother.smooth(r, &mut z);
+    // The coarsest level is only smoothed
+    let Some((injection, coarser_injections)) = injections.split_first() else {
+        return z;
+    };
+
+    let residual = waxpby(nrow, 1.0, r, -1.0, &sparsemv(smoother.matrix(), &z));
+    let coarse_r: Vec<f64> = injection.iter().map(|&row| residual[row]).collect();
+    let correction = v_cycle(coarser, coarser_injections, &coarse_r);
+    for (&row, value) in injection.iter().zip(correction) {
+        z[row] += value;
+    }
+    smoother.smooth(r, &mut z);
+    z
+}
+
+#[test]
+fn test_multigrid() {
+    let smoother = SmootherKind::SymmetricGaussSeidel;
+    let (matrix, _, _, _) = SparseMatrix::generate_matrix(16, 16, 16);
+    let multigrid = Multigrid::new(&matrix, 4, smoother).unwrap();
+    let nrows: Vec<usize> = multigrid
+        .levels
+        .iter()
+        .map(|level| level.matrix().local_nrow)
+        .collect();
+    assert_eq!(nrows, vec![4096, 512, 64, 8]);
+    assert_eq!(multigrid.injections[0][..3], [0, 2, 4]);
+    assert_eq!(multigrid.injections[0][8], 2 * 16);
+    assert_eq!(multigrid.injections[2].len(), 8);
+    // Coarsening stops at a dimension of odd size
+    let (matrix, _, _, _) = SparseMatrix::generate_matrix(12, 4, 8);
+    assert_eq!(
+        Multigrid::new(&matrix, 4, smoother).unwrap().levels.len(),
+        3
+    );
+
+    let diffusion = super::DiffusionConfig::new(super::CoefficientField::Constant);
+    let (matrix, _, _, _) = SparseMatrix::generate_diffusion_matrix(4, 4, 4, diffusion);
+    assert!(Multigrid::new(&matrix, 4, smoother).is_err());
+
+    // The coarse levels remove the smooth error which Gauss-Seidel alone is slow to reduce
+    let stencil = super::StencilConfig::new(super::Stencil::SevenPoint).weights(6.0, -1.0);
+    let (matrix, guess, rhs, exact) =
+        SparseMatrix::generate_matrix_with_stencil(16, 16, 16, stencil);
+    let mut config = super::SolverConfig::new()
+        .tolerance(1e-10)
+        .verbosity(super::Verbosity::Quiet);
+    let multigrid = Multigrid::new(&matrix, 4, smoother).unwrap();
+    let report = super::pcg(&matrix, &rhs, &guess, &multigrid, &mut config);
+    let symgs = SymmetricGaussSeidel::new(&matrix);
+    let symgs_report = super::pcg(&matrix, &rhs, &guess, &symgs, &mut config);
+    assert_eq!(report.reason, super::ConvergenceReason::Converged);
+    assert!(report.iterations < symgs_report.iterations);
+    for (actual, expected) in report.solution.iter().zip(exact.iter()) {
+        assert!((expected - actual).abs() < 1e-8);
+    }
+    assert_eq!("mg".parse(), Ok(super::PreconditionerKind::Multigrid));
+
+    // The levels can instead be smoothed with Chebyshev steps, which need no sweeps, and the coarse
+    // levels still make it converge faster than the Chebyshev preconditioner alone
+    let multigrid = Multigrid::new(&matrix, 4, SmootherKind::Chebyshev).unwrap();
+    assert!(matches!(multigrid.levels[3], Smoother::Chebyshev(_)));
+    let report = super::pcg(&matrix, &rhs, &guess, &multigrid, &mut config);
+    let chebyshev = Chebyshev::new(&matrix, 4);
+    let chebyshev_report = super::pcg(&matrix, &rhs, &guess, &chebyshev, &mut config);
+    assert_eq!(report.reason, super::ConvergenceReason::Converged);
+    assert!(report.iterations < chebyshev_report.iterations);
+    for (actual, expected) in report.solution.iter().zip(exact) {
+        assert!((expected - actual).abs() < 1e-8);
+    }
+    assert_eq!("chebyshev".parse(), Ok(SmootherKind::Chebyshev));
+    assert!("jacobi".parse::<SmootherKind>().is_err());
+}
@@ -0,0 +1,219 @@
+use std::ops::ControlFlow;
+
+use super::monitor::Callback;
+use super::{IterationInfo, Monitor, ResidualHistory};
+
+/// How much the solver reports about its progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Print nothing.
+    Quiet,
+    /// Print the initial residual, and the residual at regular intervals.
+    Normal,
+    /// Print the residual at every iteration.
+    Verbose,
+}
+
+/// The settings of a run of the solver, built up from the defaults of the benchmark, for example
+/// `SolverConfig::new().max_iterations(500).tolerance(1e-10)`.
+///
+/// # Fields
+/// * `max_iterations` - The maximum number of iterations to perform.
+/// * `tolerance` - The value the residual needs to be less than for convergence (how "good" of a
+///   solution do we need).
+/// * `relative_tolerance` - The fraction of the initial residual the residual needs to be less
+///   than for convergence.
+/// * `rhs_tolerance` - The fraction of the norm of the right hand side the residual needs to be
+///   less than for convergence.
+/// * `divergence_tolerance` - The multiple of the initial residual above which the solver has
+///   diverged.
+/// * `stagnation_window` - The number of iterations without a new smallest residual after which
+///   the solver has stagnated, or zero to never stagnate.
+/// * `verbosity` - How much to print about the progress of the solver.
+/// * `print_freq` - The number of iterations between printing the residual, which defaults to a
+///   tenth of the maximum number of iterations (clamped to between 1 and 50).
+/// * `restart` - The number of iterations between restarts of GMRES, which is the largest number
+///   of basis vectors it stores.
+/// * `reorthogonalise` - Whether GMRES orthogonalises each new basis vector a second time, which
+///   keeps the basis orthogonal when the matrix is badly conditioned.
+/// * `monitors` - The monitors which look at the state of the solver whenever the residual is
+///   computed.
+pub struct SolverConfig<'a> {
+    pub max_iterations: i32,
+    pub tolerance: f64,
+    pub relative_tolerance: f64,
+    pub rhs_tolerance: f64,
+    pub divergence_tolerance: f64,
+    pub stagnation_window: i32,
+    pub verbosity: Verbosity,
+    pub print_freq: Option<i32>,
+    pub restart: usize,
+    pub reorthogonalise: bool,
+    monitors: Vec<Box<dyn Monitor + 'a>>,
+}
+
+impl Default for SolverConfig<'_> {
+    fn default() -> Self {
+        SolverConfig {
+            max_iterations: 150,
+            tolerance: 0.0,
+            relative_tolerance: 0.0,
+            rhs_tolerance: 0.0,
+            divergence_tolerance: f64::INFINITY,
+            stagnation_window: 0,
+            verbosity: Verbosity::Normal,
+            print_freq: None,
+            restart: 30,
+            reorthogonalise: false,
+            monitors: vec![],
+        }
+    }
+}
+
+impl<'a> SolverConfig<'a> {
+    /// Create a configuration with the defaults of the benchmark, which runs 150 iterations
+    /// without stopping early.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of iterations to perform.
+    pub fn max_iterations(mut self, max_iterations: i32) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Set the value the residual needs to be less than for convergence.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Set the fraction of the initial residual the residual needs to be less than for
+    /// convergence.
+    pub fn relative_tolerance(mut self, relative_tolerance: f64) -> Self {
+        self.relative_tolerance = relative_tolerance;
+        self
+    }
+
+    /// Set the fraction of the norm of the right hand side the residual needs to be less than for
+    /// convergence.
+    pub fn rhs_tolerance(mut self, rhs_tolerance: f64) -> Self {
+        self.rhs_tolerance = rhs_tolerance;
+        self
+    }
+
+    /// Set the multiple of the initial residual above which the solver has diverged.
+    pub fn divergence_tolerance(mut self, divergence_tolerance: f64) -> Self {
+        self.divergence_tolerance = divergence_tolerance;
+        self
+    }
+
+    /// Set the number of iterations without a new smallest residual after which the solver has
+    /// stagnated, or zero to never stagnate.
+    pub fn stagnation_window(mut self, stagnation_window: i32) -> Self {
+        self.stagnation_window = stagnation_window.max(0);
+        self
+    }
+
+    /// Set how much to print about the progress of the solver.
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Set the number of iterations between printing the residual.
+    pub fn print_freq(mut self, print_freq: i32) -> Self {
+        self.print_freq = Some(print_freq.max(1));
+        self
+    }
+
+    /// Set the number of iterations between restarts of GMRES.
+    pub fn restart(mut self, restart: usize) -> Self {
+        self.restart = restart.max(1);
+        self
+    }
+
+    /// Set whether GMRES orthogonalises each new basis vector a second time.
+    pub fn reorthogonalise(mut self, reorthogonalise: bool) -> Self {
+        self.reorthogonalise = reorthogonalise;
+        self
+    }
+
+    /// Add a function to be called with the iteration number and residual whenever the residual
+    /// is computed, starting with the initial residual at iteration zero.
+    pub fn callback(self, callback: impl FnMut(i32, f64) + 'a) -> Self {
+        self.monitor(Callback(callback))
+    }
+
+    /// Add a monitor to look at the state of the solver whenever the residual is computed, which
+    /// can stop the solver early.
+    pub fn monitor(mut self, monitor: impl Monitor + 'a) -> Self {
+        self.monitors.push(Box::new(monitor));
+        self
+    }
+
+    /// Record the residual at every iteration in the `residual_history` of the report.
+    pub fn residual_history(self) -> Self {
+        self.monitor(ResidualHistory::default())
+    }
+
+    /// Whether the residual should be printed at an iteration.
+    ///
+    /// # Arguments
+    /// * `iteration` - The current iteration number.
+    pub(crate) fn should_print(&self, iteration: i32) -> bool {
+        let print_freq = match (self.verbosity, self.print_freq) {
+            (Verbosity::Quiet, _) => return false,
+            (Verbosity::Verbose, _) => 1,
+            (Verbosity::Normal, Some(print_freq)) => print_freq,
+            (Verbosity::Normal, None) => (self.max_iterations / 10).clamp(1, 50),
+        };
+        iteration % print_freq == 0 || iteration + 1 == self.max_iterations
+    }
+
+    /// Pass the state of the solver to each of the monitors.
+    ///
+    /// # Arguments
+    /// * `info` - The state of the solver.
+    ///
+    /// # Return values
+    /// * `flow` - Whether the solver should keep iterating, which is to stop if any of the
+    ///   monitors asked it to.
+    pub(crate) fn notify(&mut self, info: IterationInfo) -> ControlFlow<()> {
+        let mut flow = ControlFlow::Continue(());
+        // Every monitor sees the state, even after one of them asks to stop
+        for monitor in self.monitors.iter_mut() {
+            if monitor.monitor(&info).is_break() {
+                flow = ControlFlow::Break(());
+            }
+        }
+        flow
+    }
+
+    /// Take the residuals recorded by the first of the monitors which records them, or nothing
+    /// if none of them do.
+    pub(crate) fn take_residual_history(&mut self) -> Vec<f64> {
+        self.monitors
+            .iter_mut()
+            .find_map(|monitor| monitor.residual_history())
+            .unwrap_or_default()
+    }
+}
+
+#[test]
+fn test_solver_config() {
+    let config = SolverConfig::new();
+    assert_eq!((config.max_iterations, config.tolerance), (150, 0.0));
+    assert!(config.should_print(15));
+    assert!(!config.should_print(16));
+    assert!(config.should_print(149));
+
+    let config = SolverConfig::new().max_iterations(1000).print_freq(7);
+    assert!(config.should_print(14));
+    assert!(!config.should_print(50));
+    let config = config.verbosity(Verbosity::Verbose);
+    assert!(config.should_print(51));
+    let config = config.verbosity(Verbosity::Quiet);
+    assert!(!config.should_print(999));
+}
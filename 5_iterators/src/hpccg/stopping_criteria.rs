@@ -0,0 +1,141 @@
+use std::ops::ControlFlow;
+
+use super::{ConvergenceReason, SolverConfig};
+
+/// The stopping criteria of a run of a solver, which are checked against the residual whenever it
+/// is computed.
+///
+/// # Fields
+/// * `threshold` - The residual at or below which the solver has converged, which is the largest
+///   of the absolute tolerance and the tolerances relative to the initial residual and the right
+///   hand side.
+/// * `divergence_limit` - The residual above which the solver has diverged.
+/// * `stagnation_window` - The number of iterations without a new smallest residual after which
+///   the solver has stagnated, or zero to never stagnate.
+/// * `best_residual` - The smallest residual so far.
+/// * `best_iteration` - The iteration of the smallest residual so far.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StoppingCriteria {
+    threshold: f64,
+    divergence_limit: f64,
+    stagnation_window: i32,
+    best_residual: f64,
+    best_iteration: i32,
+}
+
+impl StoppingCriteria {
+    /// Set up the stopping criteria of a configuration for a run of a solver.
+    ///
+    /// # Arguments
+    /// * `config` - The settings of the solver.
+    /// * `initial_residual` - The norm of the residual of the initial guess.
+    /// * `rhs_norm` - A function computing the norm of the right hand side, which is only called
+    ///   if a tolerance relative to it is set.
+    pub(crate) fn new(
+        config: &SolverConfig,
+        initial_residual: f64,
+        rhs_norm: impl FnOnce() -> f64,
+    ) -> Self {
+        let rhs_threshold = if config.rhs_tolerance > 0.0 {
+            config.rhs_tolerance * rhs_norm()
+        } else {
+            0.0
+        };
+        StoppingCriteria {
+            threshold: config
+                .tolerance
+                .max(config.relative_tolerance * initial_residual)
+                .max(rhs_threshold),
+            divergence_limit: config.divergence_tolerance * initial_residual,
+            stagnation_window: config.stagnation_window,
+            best_residual: initial_residual,
+            best_iteration: 0,
+        }
+    }
+
+    /// Whether a residual is small enough for the solver to have converged.
+    pub(crate) fn is_converged(&self, normr: f64) -> bool {
+        normr <= self.threshold
+    }
+
+    /// Why the solver should stop with a residual, or `None` if it should keep iterating.
+    ///
+    /// # Arguments
+    /// * `iteration` - The current iteration number.
+    /// * `normr` - The residual at the current iteration.
+    /// * `flow` - Whether the monitors of the solver asked it to stop.
+    pub(crate) fn check(
+        &mut self,
+        iteration: i32,
+        normr: f64,
+        flow: ControlFlow<()>,
+    ) -> Option<ConvergenceReason> {
+        if !normr.is_finite() {
+            Some(ConvergenceReason::Breakdown)
+        } else if self.is_converged(normr) {
+            Some(ConvergenceReason::Converged)
+        } else if normr > self.divergence_limit {
+            Some(ConvergenceReason::Diverged)
+        } else if flow.is_break() {
+            Some(ConvergenceReason::Stopped)
+        } else if normr < self.best_residual {
+            self.best_residual = normr;
+            self.best_iteration = iteration;
+            None
+        } else if self.stagnation_window > 0
+            && iteration - self.best_iteration >= self.stagnation_window
+        {
+            Some(ConvergenceReason::Stagnated)
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn test_stopping_criteria() {
+    let proceed = ControlFlow::Continue(());
+    let config = SolverConfig::new();
+    let mut criteria = StoppingCriteria::new(&config, 10.0, || unreachable!());
+    assert_eq!(criteria.check(0, 10.0, proceed), None);
+    assert_eq!(criteria.check(1, 1e5, proceed), None);
+    assert_eq!(
+        criteria.check(2, 0.0, proceed),
+        Some(ConvergenceReason::Converged)
+    );
+    assert_eq!(
+        criteria.check(3, f64::NAN, proceed),
+        Some(ConvergenceReason::Breakdown)
+    );
+
+    let config = SolverConfig::new()
+        .tolerance(1e-3)
+        .relative_tolerance(1e-2)
+        .rhs_tolerance(1e-1);
+    let criteria = StoppingCriteria::new(&config, 10.0, || 2.0);
+    assert_eq!(criteria.threshold, 0.2);
+    let config = config.rhs_tolerance(0.0);
+    let criteria = StoppingCriteria::new(&config, 10.0, || unreachable!());
+    assert_eq!(criteria.threshold, 0.1);
+
+    let config = SolverConfig::new()
+        .divergence_tolerance(100.0)
+        .stagnation_window(3);
+    let mut criteria = StoppingCriteria::new(&config, 10.0, || unreachable!());
+    assert_eq!(criteria.check(1, 999.0, proceed), None);
+    assert_eq!(
+        criteria.check(2, 1001.0, proceed),
+        Some(ConvergenceReason::Diverged)
+    );
+    assert_eq!(criteria.check(3, 5.0, proceed), None);
+    assert_eq!(criteria.check(5, 6.0, proceed), None);
+    assert_eq!(
+        criteria.check(6, 5.0, proceed),
+        Some(ConvergenceReason::Stagnated)
+    );
+    let stop = ControlFlow::Break(());
+    assert_eq!(
+        criteria.check(7, 1.0, stop),
+        Some(ConvergenceReason::Stopped)
+    );
+}
@@ -1,16 +1,60 @@
+mod algebraic_multigrid;
+mod bicgstab;
+mod chebyshev;
 pub mod compute_residual;
 mod ddot;
+mod diffusion;
+mod gauss_seidel;
+mod gmres;
+mod incomplete_cholesky;
+mod matrix_market;
+mod method;
+mod monitor;
+mod multigrid;
 mod mytimer;
+mod pcg;
+mod preconditioner;
+mod read_hpc_row;
+mod solve_report;
+mod solver_config;
+mod sparse_algebra;
 pub mod sparse_matrix;
 mod sparsemv;
+mod stencil;
+mod stopping_criteria;
 mod waxpby;
+mod yaml_doc;
 
+pub mod hpccg_internals {
+    pub use super::ddot::ddot;
+    pub use super::sparsemv::sparsemv;
+    pub use super::waxpby::waxpby;
+}
+
+pub use algebraic_multigrid::AlgebraicMultigrid;
+pub use bicgstab::bicgstab;
+pub use chebyshev::Chebyshev;
 pub use compute_residual::compute_residual;
 use ddot::ddot;
+pub use diffusion::{CoefficientField, DiffusionConfig, ManufacturedSolution};
+pub use gauss_seidel::SymmetricGaussSeidel;
+pub use gmres::gmres;
+pub use incomplete_cholesky::IncompleteCholesky;
+pub use matrix_market::{read_matrix_market_vector, write_matrix_market_vector, Symmetry};
+pub use method::Method;
+pub use monitor::{IterationInfo, Monitor, ResidualHistory};
+pub use multigrid::{Multigrid, Smoother, SmootherKind};
 use mytimer::mytimer;
-pub use sparse_matrix::SparseMatrix;
+pub use pcg::pcg;
+pub use preconditioner::{Jacobi, Preconditioner, PreconditionerKind};
+pub use solve_report::{ConvergenceReason, SolveReport, Timings};
+pub use solver_config::{SolverConfig, Verbosity};
+pub use sparse_matrix::{Geometry, SparseMatrix};
 use sparsemv::sparsemv;
+pub use stencil::{Stencil, StencilConfig};
+use stopping_criteria::StoppingCriteria;
 use waxpby::waxpby;
+pub use yaml_doc::{YamlDoc, YamlElement, YamlValue};
 
 /// Store the start time for a code section.
 fn tick(t0: &mut f64) {
@@ -28,30 +72,17 @@ fn tock(t0: &f64, t: &mut f64) {
 /// * `A` - The input sparse matrix.
 /// * `b` - The known right hand side vector.
 /// * `x` - The current approximate solution, which starts as the initial guess.
-/// * `max_iter` - The maximum number of iterations to perform.
-/// * `tolerance` - The value the residual needs to be less than for convergence (how "good" of a
-///                 solution do we need).
+/// * `config` - The settings of the solver.
 ///
 /// # Return values
-/// * `result` - The approximate result at the end of the solver loop
-/// * `iterations` - The number of iterations for which the solver ran
-/// * `normr` - The residual difference between the current approximate solution and the exact
-///             solution.
-/// * `times` - An array of times spent for each operation (ddot/waxpby/sparse_mv/total).
+/// * `report` - The approximate result, and how the solver got there.
 #[allow(non_snake_case, unused_assignments, unused_mut)]
-pub fn solver(
-    A: &SparseMatrix,
-    b: &[f64],
-    x: &[f64],
-    max_iterations: i32,
-    tolerance: f64,
-) -> (Vec<f64>, i32, f64, Vec<f64>) {
+pub fn solver(A: &SparseMatrix, b: &[f64], x: &[f64], config: &mut SolverConfig) -> SolveReport {
     let t_begin: f64 = mytimer();
     let mut t_total: f64 = 0.0;
     let mut t_ddot: f64 = 0.0;
     let mut t_waxpby: f64 = 0.0;
     let mut t_sparsemv: f64 = 0.0;
-    let mut t_mpi_allreduce: f64 = 0.0;
 
     let nrow = A.local_nrow;
     let ncol = A.local_ncol;
@@ -67,8 +98,10 @@ pub fn solver(
     let mut normr = 0.0;
     let mut rtrans: f64 = 0.0;
     let mut oldrtrans: f64 = 0.0;
+    let mut alpha: f64 = 0.0;
+    let mut beta: f64 = 0.0;
 
-    let print_freq = (max_iterations/10).max(1).min(50);
+    let max_iterations = config.max_iterations;
 
     // `p` is of length `ncols`, so copy `x` to `p` for sparse matrix-vector operation
     tick(&mut t_total);
@@ -89,10 +122,24 @@ pub fn solver(
 
     normr = rtrans.sqrt();
 
-    println!("Initial Residual = {normr:+.5e}");
+    if config.verbosity > Verbosity::Quiet {
+        println!("Initial Residual = {normr:+.5e}");
+    }
+    let flow = config.notify(IterationInfo {
+        iteration: 0,
+        residual: normr,
+        alpha: None,
+        beta: None,
+        elapsed: mytimer() - t_begin,
+    });
+
+    tick(&mut t_total);
+    let mut criteria = StoppingCriteria::new(config, normr, || ddot(nrow, b, b).sqrt());
+    tock(&t_total, &mut t_ddot);
+    let mut reason = criteria.check(0, normr, flow);
 
     for k in 1..max_iterations {
-        if normr <= tolerance {
+        if reason.is_some() {
             break;
         }
 
@@ -101,49 +148,73 @@ pub fn solver(
             p = waxpby(nrow, 1.0, &r, 0.0, &r);
             tock(&t_total, &mut t_waxpby);
         } else {
-            oldrtrans = rtrans;
-            tick(&mut t_total);
-            rtrans = ddot(nrow, &r, &r);
-            tock(&t_total, &mut t_ddot);
-            let beta = rtrans / oldrtrans;
+            beta = rtrans / oldrtrans;
             tick(&mut t_total);
             p = waxpby(nrow, 1.0, &r, beta, &p);
             tock(&t_total, &mut t_waxpby);
         }
 
-        normr = rtrans.sqrt();
-        if k % print_freq == 0 || k + 1 == max_iterations {
-            println!("Iteration = {k} , Residual = {normr:+.5e}");
-        }
-
         tick(&mut t_total);
         Ap = sparsemv(A, &p);
         tock(&t_total, &mut t_sparsemv);
 
         tick(&mut t_total);
-        let alpha = ddot(r.len(), &p, &Ap);
+        let pAp = ddot(r.len(), &p, &Ap);
         tock(&t_total, &mut t_ddot);
 
-        let alpha = rtrans / alpha;
+        // The method cannot take a step along a direction without positive curvature
+        if pAp.is_nan() || pAp <= 0.0 {
+            reason = Some(ConvergenceReason::Breakdown);
+            break;
+        }
+        alpha = rtrans / pAp;
         tick(&mut t_total);
-        result = waxpby(nrow, 1.0, &result, alpha, &p);
+        let next = waxpby(nrow, 1.0, &result, alpha, &p);
+        let previous = std::mem::replace(&mut result, next);
         r = waxpby(nrow, 1.0, &r, -alpha, &Ap);
         tock(&t_total, &mut t_waxpby);
+
+        oldrtrans = rtrans;
+        tick(&mut t_total);
+        rtrans = ddot(nrow, &r, &r);
+        tock(&t_total, &mut t_ddot);
+
+        let previous_normr = normr;
+        normr = rtrans.sqrt();
         iteration = k;
+        if config.should_print(k) {
+            println!("Iteration = {k} , Residual = {normr:+.5e}");
+        }
+        let flow = config.notify(IterationInfo {
+            iteration: k,
+            residual: normr,
+            alpha: Some(alpha),
+            beta: (k > 1).then_some(beta),
+            elapsed: mytimer() - t_begin,
+        });
+        reason = criteria.check(k, normr, flow);
+        // A step which broke down or diverged is undone, so the last good iterate is returned
+        if reason.is_some_and(ConvergenceReason::is_failure) {
+            (result, normr, iteration) = (previous, previous_normr, k - 1);
+        }
     }
 
-    (
-        result,
-        iteration,
-        normr,
-        vec![
-            mytimer() - t_begin,
-            t_ddot,
-            t_waxpby,
-            t_sparsemv,
-            t_mpi_allreduce,
-        ],
-    )
+    let reason = reason.unwrap_or(ConvergenceReason::MaxIterations);
+
+    SolveReport {
+        solution: result,
+        iterations: iteration,
+        final_residual: normr,
+        reason,
+        residual_history: config.take_residual_history(),
+        times: Timings {
+            total: mytimer() - t_begin,
+            ddot: t_ddot,
+            waxpby: t_waxpby,
+            sparsemv: t_sparsemv,
+            precondition: 0.0,
+        },
+    }
 }
 
 #[test]
@@ -152,12 +223,55 @@ fn test_solver() {
     let (matrix, guess, rhs, exact) = SparseMatrix::generate_matrix(nx, ny, nz);
     let max_iter = 150;
     let tolerance = 5e-40;
-    let (result, iterations, normr, _) = solver(&matrix, &rhs, &guess, max_iter, tolerance);
+    let mut residuals = vec![];
+    let mut config = SolverConfig::new()
+        .max_iterations(max_iter)
+        .tolerance(tolerance)
+        .callback(|_, normr| residuals.push(normr));
+    let report = solver(&matrix, &rhs, &guess, &mut config);
+    drop(config);
+    let (result, iterations, normr) = (report.solution, report.iterations, report.final_residual);
     let residual = compute_residual(matrix.local_nrow, &result, &exact);
     assert!(normr < tolerance);
     assert!(iterations < max_iter);
+    assert_eq!(report.reason, ConvergenceReason::Converged);
+    assert_eq!(residuals.len(), iterations as usize + 1);
     assert!(residual < 1e-15);
     for (actual, expected) in result.iter().zip(exact) {
         assert!((expected - actual).abs() < 1e-5);
     }
+
+    // The initial residual is only checked once, and the residual falls at every step after it
+    let mut config = SolverConfig::new()
+        .stagnation_window(1)
+        .verbosity(Verbosity::Quiet);
+    let report = solver(&matrix, &rhs, &guess, &mut config);
+    assert_eq!(report.reason, ConvergenceReason::Converged);
+}
+
+#[test]
+fn test_divergence() {
+    // The convection makes the matrix non-symmetric, which the conjugate gradient method does not
+    // converge for, so the residual grows past the limit partway through the run
+    let stencil = StencilConfig::convection_diffusion(1.5);
+    let (matrix, guess, rhs, _) = SparseMatrix::generate_matrix_with_stencil(6, 6, 6, stencil);
+    let nrow = matrix.local_nrow;
+    let mut config = SolverConfig::new()
+        .divergence_tolerance(1.5)
+        .verbosity(Verbosity::Quiet)
+        .residual_history();
+    let report = solver(&matrix, &rhs, &guess, &mut config);
+    assert_eq!(report.reason, ConvergenceReason::Diverged);
+    assert!(report.iterations > 1);
+    let history = &report.residual_history;
+    assert!(*history.last().unwrap() > 1.5 * history[0]);
+
+    // The step which diverged is undone, so the solution is the last iterate within the limit
+    assert_eq!(history.len(), report.iterations as usize + 2);
+    assert_eq!(report.final_residual, history[history.len() - 2]);
+    assert!(report.final_residual <= 1.5 * history[0]);
+    assert!(report.solution.iter().all(|value| value.is_finite()));
+    let residual = waxpby(nrow, 1.0, &rhs, -1.0, &sparsemv(&matrix, &report.solution));
+    let normr = ddot(nrow, &residual, &residual).sqrt();
+    assert!((normr - report.final_residual).abs() < 1e-8 * normr);
 }
@@ -0,0 +1,223 @@
+use super::{
+    ddot, mytimer, sparsemv, tick, tock, waxpby, ConvergenceReason, IterationInfo, SolveReport,
+    SolverConfig, SparseMatrix, StoppingCriteria, Timings, Verbosity,
+};
+
+/// A method to compute the approximate solution to `Ax = b` with the biconjugate gradient
+/// stabilised method (BiCGSTAB), which unlike the conjugate gradient solver also works for
+/// non-symmetric matrices.
+///
+/// # Arguments
+/// * `A` - The input sparse matrix.
+/// * `b` - The known right hand side vector.
+/// * `x` - The current approximate solution, which starts as the initial guess.
+/// * `config` - The settings of the solver.
+///
+/// # Return values
+/// * `report` - The approximate result, and how the solver got there.
+#[allow(non_snake_case)]
+pub fn bicgstab(A: &SparseMatrix, b: &[f64], x: &[f64], config: &mut SolverConfig) -> SolveReport {
+    let t_begin: f64 = mytimer();
+    let mut t_total: f64 = 0.0;
+    let mut t_ddot: f64 = 0.0;
+    let mut t_waxpby: f64 = 0.0;
+    let mut t_sparsemv: f64 = 0.0;
+
+    let nrow = A.local_nrow;
+
+    let mut result = x.to_owned();
+    let mut iteration = 0;
+
+    let max_iterations = config.max_iterations;
+
+    tick(&mut t_total);
+    let Ax = sparsemv(A, &result);
+    tock(&t_total, &mut t_sparsemv);
+
+    tick(&mut t_total);
+    let mut r = waxpby(nrow, 1.0, b, -1.0, &Ax);
+    tock(&t_total, &mut t_waxpby);
+
+    // The shadow residual, which the residuals are kept biorthogonal to
+    let r_hat = r.clone();
+    let mut p = vec![0.0; nrow];
+    let mut v = vec![0.0; nrow];
+    let (mut rho, mut alpha, mut omega) = (1.0, 1.0, 1.0);
+
+    tick(&mut t_total);
+    let mut normr = ddot(nrow, &r, &r).sqrt();
+    tock(&t_total, &mut t_ddot);
+
+    if config.verbosity > Verbosity::Quiet {
+        println!("Initial Residual = {normr:+.5e}");
+    }
+    let flow = config.notify(IterationInfo {
+        iteration: 0,
+        residual: normr,
+        alpha: None,
+        beta: None,
+        elapsed: mytimer() - t_begin,
+    });
+
+    tick(&mut t_total);
+    let mut criteria = StoppingCriteria::new(config, normr, || ddot(nrow, b, b).sqrt());
+    tock(&t_total, &mut t_ddot);
+    let mut reason = criteria.check(0, normr, flow);
+
+    for k in 1..max_iterations {
+        if reason.is_some() {
+            break;
+        }
+
+        tick(&mut t_total);
+        let old_rho = rho;
+        rho = ddot(nrow, &r_hat, &r);
+        tock(&t_total, &mut t_ddot);
+        // The method breaks down if the residual becomes orthogonal to the shadow residual, or
+        // the stabilising step makes no progress
+        if rho == 0.0 || omega == 0.0 {
+            reason = Some(ConvergenceReason::Breakdown);
+            break;
+        }
+
+        let beta = (rho / old_rho) * (alpha / omega);
+        tick(&mut t_total);
+        p = waxpby(nrow, 1.0, &p, -omega, &v);
+        p = waxpby(nrow, 1.0, &r, beta, &p);
+        tock(&t_total, &mut t_waxpby);
+
+        tick(&mut t_total);
+        v = sparsemv(A, &p);
+        tock(&t_total, &mut t_sparsemv);
+
+        tick(&mut t_total);
+        let r_hat_v = ddot(nrow, &r_hat, &v);
+        tock(&t_total, &mut t_ddot);
+        // The method also breaks down if the search direction is orthogonal to the shadow residual
+        if r_hat_v == 0.0 || !r_hat_v.is_finite() {
+            reason = Some(ConvergenceReason::Breakdown);
+            break;
+        }
+        alpha = rho / r_hat_v;
+
+        tick(&mut t_total);
+        let s = waxpby(nrow, 1.0, &r, -alpha, &v);
+        tock(&t_total, &mut t_waxpby);
+
+        tick(&mut t_total);
+        let t = sparsemv(A, &s);
+        tock(&t_total, &mut t_sparsemv);
+
+        tick(&mut t_total);
+        let (ts, tt, ss) = (ddot(nrow, &t, &s), ddot(nrow, &t, &t), ddot(nrow, &s, &s));
+        tock(&t_total, &mut t_ddot);
+
+        let (previous, previous_normr) = (result.clone(), normr);
+        if criteria.is_converged(ss.sqrt()) {
+            // The half step has converged, so take it and stop, as `omega` may be undefined
+            tick(&mut t_total);
+            result = waxpby(nrow, 1.0, &result, alpha, &p);
+            tock(&t_total, &mut t_waxpby);
+            normr = ss.sqrt();
+        } else {
+            if tt == 0.0 || !tt.is_finite() {
+                reason = Some(ConvergenceReason::Breakdown);
+                break;
+            }
+            omega = ts / tt;
+
+            tick(&mut t_total);
+            result = waxpby(nrow, 1.0, &result, alpha, &p);
+            result = waxpby(nrow, 1.0, &result, omega, &s);
+            r = waxpby(nrow, 1.0, &s, -omega, &t);
+            tock(&t_total, &mut t_waxpby);
+
+            tick(&mut t_total);
+            normr = ddot(nrow, &r, &r).sqrt();
+            tock(&t_total, &mut t_ddot);
+        }
+
+        if config.should_print(k) {
+            println!("Iteration = {k} , Residual = {normr:+.5e}");
+        }
+        let flow = config.notify(IterationInfo {
+            iteration: k,
+            residual: normr,
+            alpha: Some(alpha),
+            beta: Some(beta),
+            elapsed: mytimer() - t_begin,
+        });
+        reason = criteria.check(k, normr, flow);
+        iteration = k;
+        // A step which broke down or diverged is undone, so the last good iterate is returned
+        if reason.is_some_and(ConvergenceReason::is_failure) {
+            (result, normr, iteration) = (previous, previous_normr, k - 1);
+        }
+    }
+
+    let reason = reason.unwrap_or(ConvergenceReason::MaxIterations);
+
+    SolveReport {
+        solution: result,
+        iterations: iteration,
+        final_residual: normr,
+        reason,
+        residual_history: config.take_residual_history(),
+        times: Timings {
+            total: mytimer() - t_begin,
+            ddot: t_ddot,
+            waxpby: t_waxpby,
+            sparsemv: t_sparsemv,
+            precondition: 0.0,
+        },
+    }
+}
+
+#[test]
+fn test_bicgstab() {
+    let stencil = super::StencilConfig::convection_diffusion(1.5);
+    let (matrix, guess, rhs, exact) = SparseMatrix::generate_matrix_with_stencil(6, 6, 6, stencil);
+    // The convection makes the matrix non-symmetric, so the interior point `1, 1, 1` is coupled
+    // differently to its neighbours before and after it in the x dimension
+    let start = matrix.row_start_inds[36 + 6 + 1];
+    assert_eq!(matrix.list_of_inds[start + 2..start + 5], [42, 43, 44]);
+    assert_eq!(
+        matrix.list_of_vals[start + 2..start + 5],
+        [-1.0 - 0.75 / 3f64.sqrt(), 6.0, -1.0 + 0.75 / 3f64.sqrt()]
+    );
+
+    let mut residuals = vec![];
+    let mut config = SolverConfig::new()
+        .max_iterations(200)
+        .tolerance(1e-10)
+        .verbosity(Verbosity::Quiet)
+        .callback(|_, normr| residuals.push(normr));
+    let report = bicgstab(&matrix, &rhs, &guess, &mut config);
+    drop(config);
+    assert_eq!(report.reason, ConvergenceReason::Converged);
+    assert!(report.final_residual <= 1e-10);
+    assert_eq!(residuals.len(), report.iterations as usize + 1);
+    for (actual, expected) in report.solution.iter().zip(exact) {
+        assert!((expected - actual).abs() < 1e-8);
+    }
+
+    // The half step is exact for a multiple of the identity, which leaves `omega` undefined
+    let stencil = super::StencilConfig::new(super::Stencil::SevenPoint).weights(2.0, 0.0);
+    let (matrix, guess, rhs, exact) = SparseMatrix::generate_matrix_with_stencil(3, 3, 3, stencil);
+    let mut config = SolverConfig::new().verbosity(Verbosity::Quiet);
+    let report = bicgstab(&matrix, &rhs, &guess, &mut config);
+    assert_eq!(report.reason, ConvergenceReason::Converged);
+    assert_eq!(report.iterations, 1);
+    assert_eq!(report.final_residual, 0.0);
+    assert_eq!(report.solution, exact);
+
+    // With a zero diagonal, the first search direction is orthogonal to the shadow residual
+    let stencil = super::StencilConfig::new(super::Stencil::SevenPoint).weights(0.0, 1.0);
+    let (matrix, guess, mut rhs, _) = SparseMatrix::generate_matrix_with_stencil(3, 3, 3, stencil);
+    rhs.fill(0.0);
+    rhs[0] = 1.0;
+    let report = bicgstab(&matrix, &rhs, &guess, &mut config);
+    assert_eq!(report.reason, ConvergenceReason::Breakdown);
+    assert_eq!(report.iterations, 0);
+    assert_eq!(report.solution, guess);
+}
@@ -0,0 +1,164 @@
+use rayon::prelude::*;
+
+use super::{Preconditioner, SparseMatrix};
+
+impl SparseMatrix {
+    /// Greedily colours the rows of the matrix so that no two rows of the same colour are coupled,
+    /// by giving each row in turn the lowest colour which none of its earlier neighbours has. The
+    /// 27 point stencil of `generate_matrix` needs 8 colours, one for each parity of the point's
+    /// coordinates.
+    ///
+    /// # Return values
+    ///  * `colours` - The rows of each colour, in ascending order.
+    pub fn multicolour_ordering(&self) -> Vec<Vec<usize>> {
+        let mut row_colours: Vec<usize> = Vec::with_capacity(self.local_nrow);
+        let mut colours: Vec<Vec<usize>> = vec![];
+        for row in 0..self.local_nrow {
+            let start = self.row_start_inds[row];
+            let neighbour_colours: Vec<usize> = self.list_of_inds
+                [start..start + self.nnz_in_row[row]]
+                .iter()
+                .filter(|&&col| col < row)
+                .map(|&col| row_colours[col])
+                .collect();
+            let colour = (0..)
+                .find(|colour| !neighbour_colours.contains(colour))
+                .unwrap();
+            if colour == colours.len() {
+                colours.push(vec![]);
+            }
+            colours[colour].push(row);
+            row_colours.push(colour);
+        }
+        colours
+    }
+}
+
+/// The symmetric Gauss-Seidel preconditioner, which is a forward and then a backward Gauss-Seidel
+/// sweep from a zero initial guess, and is also the smoother of multigrid. The rows are visited in
+/// a multicolour ordering, so that the rows of each colour are independent of each other and can
+/// be updated in parallel.
+///
+/// # Fields
+/// * `matrix` - A copy of the matrix being preconditioned.
+/// * `diagonal` - The diagonal entry of each row.
+/// * `colours` - The rows of each colour, which are swept in order.
+pub struct SymmetricGaussSeidel {
+    pub matrix: SparseMatrix,
+    pub diagonal: Vec<f64>,
+    pub colours: Vec<Vec<usize>>,
+}
+
+impl SymmetricGaussSeidel {
+    /// Create the symmetric Gauss-Seidel preconditioner of a matrix, which must have a non-zero
+    /// diagonal.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix to precondition.
+    pub fn new(matrix: &SparseMatrix) -> Self {
+        SymmetricGaussSeidel {
+            matrix: matrix.clone(),
+            diagonal: matrix.diagonal(),
+            colours: matrix.multicolour_ordering(),
+        }
+    }
+
+    /// Improve an approximate solution `z` of `Az = r` with a forward and then a backward sweep,
+    /// as the smoother of multigrid.
+    ///
+    /// # Arguments
+    /// * `r` - The right hand side vector.
+    /// * `z` - The approximate solution.
+    pub fn smooth(&self, r: &[f64], z: &mut [f64]) {
+        for rows in self.colours.iter() {
+            self.sweep_colour(rows, r, z);
+        }
+        for rows in self.colours.iter().rev() {
+            self.sweep_colour(rows, r, z);
+        }
+    }
+
+    /// Update the rows of one colour in parallel, solving each for its own entry of `z` with the
+    /// current values of its neighbours.
+    fn sweep_colour(&self, rows: &[usize], r: &[f64], z: &mut [f64]) {
+        let (matrix, diagonal) = (&self.matrix, &self.diagonal);
+        let updates: Vec<f64> = rows
+            .par_iter()
+            .map(|&row| {
+                let start = matrix.row_start_inds[row];
+                let mut sum = r[row];
+                for ind in start..start + matrix.nnz_in_row[row] {
+                    let col = matrix.list_of_inds[ind];
+                    if col != row {
+                        sum -= matrix.list_of_vals[ind] * z[col];
+                    }
+                }
+                sum / diagonal[row]
+            })
+            .collect();
+        for (&row, update) in rows.iter().zip(updates) {
+            z[row] = update;
+        }
+    }
+}
+
+impl Preconditioner for SymmetricGaussSeidel {
+    fn apply(&self, r: &[f64]) -> Vec<f64> {
+        let mut z = vec![0.0; self.matrix.local_nrow];
+        self.smooth(r, &mut z);
+        z
+    }
+}
+
+#[test]
+fn test_multicolour_ordering() {
+    let (nx, ny, nz) = (4, 3, 5);
+    let (matrix, _, _, _) = SparseMatrix::generate_matrix(nx, ny, nz);
+    let colours = matrix.multicolour_ordering();
+    assert_eq!(colours.len(), 8);
+    assert_eq!(colours[0][..3], [0, 2, nx * 2]);
+    assert_eq!(
+        colours.iter().map(Vec::len).sum::<usize>(),
+        matrix.local_nrow
+    );
+    for rows in colours.iter() {
+        for &row in rows {
+            let start = matrix.row_start_inds[row];
+            let cols = &matrix.list_of_inds[start..start + matrix.nnz_in_row[row]];
+            assert!(cols.iter().all(|&col| col == row || !rows.contains(&col)));
+        }
+    }
+
+    let stencil = super::StencilConfig::new(super::Stencil::SevenPoint);
+    let (matrix, _, _, _) = SparseMatrix::generate_matrix_with_stencil(nx, ny, nz, stencil);
+    assert_eq!(matrix.multicolour_ordering().len(), 2);
+}
+
+#[test]
+fn test_symmetric_gauss_seidel() {
+    let (matrix, _, _, _) = SparseMatrix::generate_matrix(4, 4, 4);
+    let symgs = SymmetricGaussSeidel::new(&matrix);
+    // The preconditioner is symmetric, so `x·M⁻¹y = y·M⁻¹x`
+    let x: Vec<f64> = (0..64).map(|i| (i as f64).sin()).collect();
+    let y: Vec<f64> = (0..64).map(|i| (i as f64).cos()).collect();
+    let xy: f64 = x.iter().zip(symgs.apply(&y)).map(|(a, b)| a * b).sum();
+    let yx: f64 = y.iter().zip(symgs.apply(&x)).map(|(a, b)| a * b).sum();
+    assert!((xy - yx).abs() < 1e-12);
+
+    // The Laplacian has a constant diagonal, so Jacobi is no better than no preconditioner
+    let stencil = super::StencilConfig::new(super::Stencil::SevenPoint).weights(6.0, -1.0);
+    let (matrix, guess, rhs, exact) =
+        SparseMatrix::generate_matrix_with_stencil(16, 16, 16, stencil);
+    let mut config = super::SolverConfig::new()
+        .tolerance(1e-10)
+        .verbosity(super::Verbosity::Quiet);
+    let symgs = SymmetricGaussSeidel::new(&matrix);
+    let report = super::pcg(&matrix, &rhs, &guess, &symgs, &mut config);
+    let jacobi = super::Jacobi::new(&matrix);
+    let jacobi_report = super::pcg(&matrix, &rhs, &guess, &jacobi, &mut config);
+    assert_eq!(report.reason, super::ConvergenceReason::Converged);
+    assert!(report.iterations < jacobi_report.iterations);
+    for (actual, expected) in report.solution.iter().zip(exact) {
+        assert!((expected - actual).abs() < 1e-8);
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::SparseMatrix;
+
+/// Whether a coordinate Matrix Market file stores every entry, or only the lower triangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Symmetry {
+    General,
+    Symmetric,
+}
+
+impl std::fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Symmetry::General => write!(f, "general"),
+            Symmetry::Symmetric => write!(f, "symmetric"),
+        }
+    }
+}
+
+/// Build an error pointing at a (1-based) line of a Matrix Market file.
+fn line_error(line_no: usize, message: impl Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Matrix Market line {line_no}: {message}"),
+    )
+}
+
+/// Parse the banner on the first line of a Matrix Market file.
+///
+/// # Arguments
+/// * `contents` - The contents of the Matrix Market file.
+/// * `format` - The storage format the caller expects, either `coordinate` or `array`.
+///
+/// # Return values
+/// * `symmetry` - The symmetry of the stored entries.
+fn parse_banner(contents: &str, format: &str) -> io::Result<Symmetry> {
+    let banner = contents.lines().next().unwrap_or_default().to_lowercase();
+    let fields: Vec<&str> = banner.split_whitespace().collect();
+    match fields[..] {
+        ["%%matrixmarket", "matrix", file_format, field, symmetry] => {
+            if file_format != format {
+                return Err(line_error(
+                    1,
+                    format!("Expected `{format}` format, found `{file_format}`"),
+                ));
+            }
+            if field != "real" && field != "integer" {
+                return Err(line_error(1, format!("Unsupported field type `{field}`")));
+            }
+            match symmetry {
+                "general" => Ok(Symmetry::General),
+                "symmetric" if format == "coordinate" => Ok(Symmetry::Symmetric),
+                _ => Err(line_error(1, format!("Unsupported symmetry `{symmetry}`"))),
+            }
+        }
+        _ => Err(line_error(1, "Missing `%%MatrixMarket matrix` banner")),
+    }
+}
+
+/// The non-comment, non-blank lines after the banner, paired with their (1-based) line numbers.
+fn data_lines(contents: &str) -> impl Iterator<Item = (usize, &str)> {
+    contents
+        .lines()
+        .enumerate()
+        .skip(1)
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('%'))
+}
+
+/// Parse a single value on a line of a Matrix Market file.
+fn parse_token<T: FromStr>(line_no: usize, token: &str) -> io::Result<T> {
+    token
+        .parse()
+        .map_err(|_| line_error(line_no, format!("Failed to parse `{token}`")))
+}
+
+/// Parse a line of a Matrix Market file which should contain exactly `count` values.
+fn parse_fields<T: FromStr>(line_no: usize, line: &str, count: usize) -> io::Result<Vec<T>> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() != count {
+        return Err(line_error(
+            line_no,
+            format!("Expected {count} values, found {}", tokens.len()),
+        ));
+    }
+    tokens
+        .iter()
+        .map(|token| parse_token(line_no, token))
+        .collect()
+}
+
+/// Read the entries of a square coordinate Matrix Market file.
+///
+/// Symmetric files are expanded to store both triangles, and the entries are sorted into row
+/// major order, with any duplicate entries summed.
+///
+/// # Arguments
+/// * `contents` - The contents of the Matrix Market file.
+///
+/// # Return values
+/// * `nrow` - The number of rows (and columns) of the matrix.
+/// * `entries` - The 0-based `(row, col, val)` entries of the matrix.
+#[allow(clippy::type_complexity)]
+fn read_coordinate(contents: &str) -> io::Result<(usize, Vec<(usize, usize, f64)>)> {
+    let symmetry = parse_banner(contents, "coordinate")?;
+    let mut lines = data_lines(contents);
+
+    let (line_no, line) = lines
+        .next()
+        .ok_or_else(|| line_error(contents.lines().count(), "Missing size line"))?;
+    let sizes: Vec<usize> = parse_fields(line_no, line, 3)?;
+    let (nrow, ncol, nnz) = (sizes[0], sizes[1], sizes[2]);
+    if nrow == 0 || nrow != ncol {
+        return Err(line_error(
+            line_no,
+            format!("Matrix must be square and non-empty, found {nrow}x{ncol}"),
+        ));
+    }
+
+    let mut entries = Vec::with_capacity(nnz);
+    let mut num_read = 0;
+    for (line_no, line) in lines {
+        if num_read == nnz {
+            return Err(line_error(
+                line_no,
+                format!("More entries than the {nnz} declared"),
+            ));
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.len() != 3 {
+            return Err(line_error(
+                line_no,
+                format!("Expected `row col value`, found {} values", tokens.len()),
+            ));
+        }
+        let row: usize = parse_token(line_no, tokens[0])?;
+        let col: usize = parse_token(line_no, tokens[1])?;
+        let val: f64 = parse_token(line_no, tokens[2])?;
+        if row == 0 || row > nrow || col == 0 || col > ncol {
+            return Err(line_error(
+                line_no,
+                format!("Entry ({row}, {col}) is outside of the {nrow}x{ncol} matrix"),
+            ));
+        }
+        entries.push((row - 1, col - 1, val));
+        if symmetry == Symmetry::Symmetric && row != col {
+            entries.push((col - 1, row - 1, val));
+        }
+        num_read += 1;
+    }
+    if num_read != nnz {
+        return Err(line_error(
+            contents.lines().count(),
+            format!("Found {num_read} entries, but {nnz} were declared"),
+        ));
+    }
+
+    entries.sort_by_key(|&(row, col, _)| (row, col));
+    entries.dedup_by(|next, prev| {
+        let duplicate = (next.0, next.1) == (prev.0, prev.1);
+        if duplicate {
+            prev.2 += next.2;
+        }
+        duplicate
+    });
+    Ok((nrow, entries))
+}
+
+impl SparseMatrix {
+    /// Reads a sparse matrix from a coordinate format Matrix Market file.
+    ///
+    /// Both `general` and `symmetric` real matrices are supported. Malformed files are reported
+    /// as `InvalidData` errors naming the offending line.
+    ///
+    /// # Arguments
+    ///  * `path` - Path to the `.mtx` file.
+    ///
+    /// # Return values
+    ///  * `matrix` - Sparse matrix read from the file.
+    pub fn read_matrix_market(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let (total_nrow, entries) = read_coordinate(&contents)?;
+
+        // In non-mpi mode, the local rows are all the rows in the file
+        let (local_nrow, local_ncol) = (total_nrow, total_nrow);
+        let start_row = 0;
+        let stop_row = local_nrow - 1;
+        let (local_nnz, total_nnz) = (entries.len(), entries.len());
+
+        let mut nnz_in_row = vec![0; local_nrow];
+        let mut list_of_vals: Vec<f64> = Vec::with_capacity(local_nnz);
+        let mut list_of_inds: Vec<usize> = Vec::with_capacity(local_nnz);
+        for (row, col, val) in entries {
+            nnz_in_row[row] += 1;
+            list_of_vals.push(val);
+            list_of_inds.push(col);
+        }
+
+        // The index of the start of each row into `list_of_vals` and `list_of_inds`
+        let row_start_inds: Vec<usize> = nnz_in_row
+            .iter()
+            .scan(0, |curvalind, &nnz| {
+                let start_ind = *curvalind;
+                *curvalind += nnz;
+                Some(start_ind)
+            })
+            .collect();
+
+        Ok(SparseMatrix {
+            start_row,
+            stop_row,
+            local_nrow,
+            total_nnz,
+            total_nrow,
+            local_ncol,
+            local_nnz,
+            nnz_in_row,
+            row_start_inds,
+            list_of_vals,
+            list_of_inds,
+            geometry: None,
+        })
+    }
+
+    /// Writes the sparse matrix to a `coordinate real` Matrix Market file.
+    ///
+    /// A `symmetric` file only stores the lower triangle, so the matrix must be symmetric for it
+    /// to be read back the same.
+    ///
+    /// # Arguments
+    ///  * `path` - Path to the `.mtx` file to create.
+    ///  * `symmetry` - Whether to write every entry, or only the lower triangle.
+    pub fn write_matrix_market(
+        &self,
+        path: impl AsRef<Path>,
+        symmetry: Symmetry,
+    ) -> io::Result<()> {
+        let entries: Vec<(usize, usize, f64)> = self
+            .row_start_inds
+            .iter()
+            .zip(self.nnz_in_row.iter())
+            .enumerate()
+            .flat_map(|(row, (&start_ind, &cur_nnz))| {
+                (start_ind..start_ind + cur_nnz).map(move |j| {
+                    (
+                        self.start_row + row,
+                        self.list_of_inds[j],
+                        self.list_of_vals[j],
+                    )
+                })
+            })
+            .filter(|&(row, col, _)| symmetry == Symmetry::General || col <= row)
+            .collect();
+
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "%%MatrixMarket matrix coordinate real {symmetry}")?;
+        let nnz = entries.len();
+        writeln!(file, "{} {} {nnz}", self.total_nrow, self.total_nrow)?;
+        for (row, col, val) in entries {
+            writeln!(file, "{} {} {val:e}", row + 1, col + 1)?;
+        }
+        file.flush()
+    }
+}
+
+/// Reads a vector, such as the right hand side or exact solution, from an `array real general`
+/// Matrix Market file.
+///
+/// # Arguments
+/// * `path` - Path to the `.mtx` file.
+///
+/// # Return values
+/// * `vector` - The values of the single column stored in the file.
+pub fn read_matrix_market_vector(path: impl AsRef<Path>) -> io::Result<Vec<f64>> {
+    let contents = fs::read_to_string(path)?;
+    parse_banner(&contents, "array")?;
+    let mut lines = data_lines(&contents);
+
+    let (line_no, line) = lines
+        .next()
+        .ok_or_else(|| line_error(contents.lines().count(), "Missing size line"))?;
+    let sizes: Vec<usize> = parse_fields(line_no, line, 2)?;
+    let (nrow, ncol) = (sizes[0], sizes[1]);
+    if ncol != 1 {
+        return Err(line_error(
+            line_no,
+            format!("Expected a single column vector, found {ncol} columns"),
+        ));
+    }
+
+    let mut vector = Vec::with_capacity(nrow);
+    for (line_no, line) in lines {
+        if vector.len() == nrow {
+            return Err(line_error(
+                line_no,
+                format!("More values than the {nrow} declared"),
+            ));
+        }
+        vector.push(parse_token(line_no, line)?);
+    }
+    if vector.len() != nrow {
+        return Err(line_error(
+            contents.lines().count(),
+            format!("Found {} values, but {nrow} were declared", vector.len()),
+        ));
+    }
+    Ok(vector)
+}
+
+/// Writes a vector to an `array real general` Matrix Market file.
+///
+/// # Arguments
+/// * `path` - Path to the `.mtx` file to create.
+/// * `vector` - The vector to write as a single column.
+pub fn write_matrix_market_vector(path: impl AsRef<Path>, vector: &[f64]) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "%%MatrixMarket matrix array real general")?;
+    writeln!(file, "{} 1", vector.len())?;
+    for val in vector {
+        writeln!(file, "{val:e}")?;
+    }
+    file.flush()
+}
+
+#[test]
+fn test_matrix_market() {
+    let dir = std::env::temp_dir();
+    let matrix_file = dir.join("hpccg_test_matrix_market.mtx");
+    let vector_file = dir.join("hpccg_test_matrix_market_vector.mtx");
+
+    // A symmetric file only stores the lower triangle
+    fs::write(
+        &matrix_file,
+        "%%MatrixMarket matrix coordinate real symmetric\n\
+         % A 3x3 tridiagonal matrix\n\
+         3 3 5\n1 1 4.0\n2 1 -1.0\n2 2 4.0\n3 2 -1.0\n3 3 4.0\n",
+    )
+    .unwrap();
+    let matrix = SparseMatrix::read_matrix_market(&matrix_file).unwrap();
+    assert_eq!(matrix.total_nrow, 3);
+    assert_eq!(matrix.local_nnz, 7);
+    assert_eq!(matrix.nnz_in_row, vec![2, 3, 2]);
+    assert_eq!(matrix.row_start_inds, vec![0, 2, 5]);
+    assert_eq!(
+        matrix.list_of_vals,
+        vec![4.0, -1.0, -1.0, 4.0, -1.0, -1.0, 4.0]
+    );
+    assert_eq!(matrix.list_of_inds, vec![0, 1, 0, 1, 2, 1, 2]);
+
+    // Round trip through both formats
+    for symmetry in [Symmetry::General, Symmetry::Symmetric] {
+        matrix.write_matrix_market(&matrix_file, symmetry).unwrap();
+        let reread = SparseMatrix::read_matrix_market(&matrix_file).unwrap();
+        assert_eq!(reread.row_start_inds, matrix.row_start_inds);
+        assert_eq!(reread.list_of_vals, matrix.list_of_vals);
+        assert_eq!(reread.list_of_inds, matrix.list_of_inds);
+    }
+    let written = fs::read_to_string(&matrix_file).unwrap();
+    assert!(written.starts_with("%%MatrixMarket matrix coordinate real symmetric\n3 3 5\n"));
+
+    let rhs = vec![3.0, 2.0, 3.0];
+    write_matrix_market_vector(&vector_file, &rhs).unwrap();
+    assert_eq!(read_matrix_market_vector(&vector_file).unwrap(), rhs);
+
+    // Errors name the offending line
+    fs::write(
+        &matrix_file,
+        "%%MatrixMarket matrix coordinate real general\n2 2 2\n1 1 1.0\n2 x 1.0\n",
+    )
+    .unwrap();
+    let err = SparseMatrix::read_matrix_market(&matrix_file).unwrap_err();
+    assert!(err.to_string().contains("line 4"));
+    fs::write(
+        &matrix_file,
+        "%%MatrixMarket matrix coordinate real general\n2 2 1\n3 1 1.0\n",
+    )
+    .unwrap();
+    let err = SparseMatrix::read_matrix_market(&matrix_file).unwrap_err();
+    assert!(err.to_string().contains("line 3"));
+    assert!(read_matrix_market_vector(&matrix_file).is_err());
+
+    fs::remove_file(&matrix_file).unwrap();
+    fs::remove_file(&vector_file).unwrap();
+}
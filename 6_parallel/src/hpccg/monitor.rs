@@ -0,0 +1,144 @@
+use std::ops::ControlFlow;
+
+/// The state of a solver when it computes the residual.
+///
+/// # Fields
+/// * `iteration` - The current iteration number, which is zero for the initial residual.
+/// * `residual` - The norm of the residual at the current iteration.
+/// * `alpha` - The latest step length along the search direction, if the method has one.
+/// * `beta` - The latest coefficient of the previous search direction in the next one, if the
+///   method has one.
+/// * `elapsed` - The time since the solver started, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterationInfo {
+    pub iteration: i32,
+    pub residual: f64,
+    pub alpha: Option<f64>,
+    pub beta: Option<f64>,
+    pub elapsed: f64,
+}
+
+/// Something which watches the progress of a solver, such as by recording or plotting its
+/// residuals, and can stop it early.
+pub trait Monitor {
+    /// Look at the state of the solver whenever it computes the residual, starting with the
+    /// initial residual at iteration zero and then once for each step it takes.
+    ///
+    /// # Arguments
+    /// * `info` - The state of the solver.
+    ///
+    /// # Return values
+    /// * `flow` - Whether the solver should keep iterating, or stop with the solution whose
+    ///   residual is in `info`.
+    fn monitor(&mut self, info: &IterationInfo) -> ControlFlow<()>;
+
+    /// The residuals the monitor recorded, which are moved into the report of the solver.
+    fn residual_history(&mut self) -> Option<Vec<f64>> {
+        None
+    }
+}
+
+/// A monitor which records the residual at every iteration, so that the convergence of the solver
+/// can be plotted from the `residual_history` of its report.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResidualHistory {
+    pub residuals: Vec<f64>,
+}
+
+impl Monitor for ResidualHistory {
+    fn monitor(&mut self, info: &IterationInfo) -> ControlFlow<()> {
+        self.residuals.push(info.residual);
+        ControlFlow::Continue(())
+    }
+
+    fn residual_history(&mut self) -> Option<Vec<f64>> {
+        Some(std::mem::take(&mut self.residuals))
+    }
+}
+
+/// A borrowed monitor, whose state can be looked at once the solver has finished.
+impl<M: Monitor + ?Sized> Monitor for &mut M {
+    fn monitor(&mut self, info: &IterationInfo) -> ControlFlow<()> {
+        (**self).monitor(info)
+    }
+
+    fn residual_history(&mut self) -> Option<Vec<f64>> {
+        (**self).residual_history()
+    }
+}
+
+/// A monitor which calls a function with the iteration number and residual, and never stops the
+/// solver.
+pub(crate) struct Callback<F: FnMut(i32, f64)>(pub(crate) F);
+
+impl<F: FnMut(i32, f64)> Monitor for Callback<F> {
+    fn monitor(&mut self, info: &IterationInfo) -> ControlFlow<()> {
+        (self.0)(info.iteration, info.residual);
+        ControlFlow::Continue(())
+    }
+}
+
+#[test]
+fn test_monitor() {
+    /// A monitor which stops the solver at an iteration, and keeps the state it is given.
+    struct StopAt(i32, Vec<IterationInfo>);
+
+    impl Monitor for StopAt {
+        fn monitor(&mut self, info: &IterationInfo) -> ControlFlow<()> {
+            self.1.push(*info);
+            if info.iteration >= self.0 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+    }
+
+    let (matrix, guess, rhs, _) = super::SparseMatrix::generate_matrix(5, 5, 5);
+    let mut residuals = vec![];
+    let mut stop_at = StopAt(5, vec![]);
+    let mut config = super::SolverConfig::new()
+        .verbosity(super::Verbosity::Quiet)
+        .residual_history()
+        .monitor(&mut stop_at)
+        .callback(|_, normr| residuals.push(normr));
+    let report = super::solver(&matrix, &rhs, &guess, &mut config);
+    drop(config);
+    assert_eq!(report.reason, super::ConvergenceReason::Stopped);
+    assert_eq!(report.iterations, 5);
+    assert_eq!(report.residual_history, residuals);
+    let history = &report.residual_history;
+    assert_eq!(history.len(), report.iterations as usize + 1);
+    assert!(history.windows(2).all(|pair| pair[0] != pair[1]));
+
+    // The solution is the iterate whose residual the monitor was given
+    let nrow = matrix.local_nrow;
+    let product = super::sparsemv(&matrix, &report.solution);
+    let residual = super::waxpby(nrow, 1.0, &rhs, -1.0, &product);
+    let normr = super::ddot(nrow, &residual, &residual).sqrt();
+    assert!((normr - report.final_residual).abs() < 1e-8 * normr);
+    assert_eq!(stop_at.1.last().unwrap().residual, report.final_residual);
+
+    // The iterations are the steps taken, which are as many as in a run limited to them
+    let mut config = super::SolverConfig::new()
+        .max_iterations(6)
+        .verbosity(super::Verbosity::Quiet);
+    let limited = super::solver(&matrix, &rhs, &guess, &mut config);
+    assert_eq!(limited.iterations, 5);
+    assert_eq!(limited.solution, report.solution);
+
+    let infos = stop_at.1;
+    assert!((0..).zip(&infos).all(|(k, info)| info.iteration == k));
+    assert_eq!((infos[0].alpha, infos[0].beta), (None, None));
+    assert!(infos[2].alpha.unwrap() > 0.0 && infos[2].beta.unwrap() > 0.0);
+    assert!(infos
+        .windows(2)
+        .all(|pair| pair[0].elapsed <= pair[1].elapsed));
+
+    // Nothing is recorded without a residual history monitor
+    let mut config = super::SolverConfig::new()
+        .max_iterations(3)
+        .verbosity(super::Verbosity::Quiet);
+    let report = super::solver(&matrix, &rhs, &guess, &mut config);
+    assert!(report.residual_history.is_empty());
+}
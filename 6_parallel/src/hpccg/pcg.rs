@@ -0,0 +1,188 @@
+use super::{
+    ddot, mytimer, sparsemv, tick, tock, waxpby, ConvergenceReason, IterationInfo, Preconditioner,
+    SolveReport, SolverConfig, SparseMatrix, StoppingCriteria, Timings, Verbosity,
+};
+
+/// A method to compute the approximate solution to `Ax = b` with the preconditioned conjugate
+/// gradient method, where the search directions are built from the preconditioned residual
+/// `z = M⁻¹ r` instead of the residual itself.
+///
+/// # Arguments
+/// * `A` - The input sparse matrix.
+/// * `b` - The known right hand side vector.
+/// * `x` - The current approximate solution, which starts as the initial guess.
+/// * `preconditioner` - The preconditioner `M`, which must be symmetric positive definite.
+/// * `config` - The settings of the solver.
+///
+/// # Return values
+/// * `report` - The approximate result, and how the solver got there.
+#[allow(non_snake_case)]
+pub fn pcg(
+    A: &SparseMatrix,
+    b: &[f64],
+    x: &[f64],
+    preconditioner: &dyn Preconditioner,
+    config: &mut SolverConfig,
+) -> SolveReport {
+    let t_begin: f64 = mytimer();
+    let mut t_total: f64 = 0.0;
+    let mut t_ddot: f64 = 0.0;
+    let mut t_waxpby: f64 = 0.0;
+    let mut t_sparsemv: f64 = 0.0;
+    let mut t_precondition: f64 = 0.0;
+
+    let nrow = A.local_nrow;
+
+    let mut result = x.to_owned();
+    let mut iteration = 0;
+    let mut p = vec![0.0; nrow];
+    let mut rtrans: f64 = 0.0;
+
+    let max_iterations = config.max_iterations;
+
+    tick(&mut t_total);
+    let Ax = sparsemv(A, &result);
+    tock(&t_total, &mut t_sparsemv);
+
+    tick(&mut t_total);
+    let mut r = waxpby(nrow, 1.0, b, -1.0, &Ax);
+    tock(&t_total, &mut t_waxpby);
+
+    tick(&mut t_total);
+    let mut normr = ddot(nrow, &r, &r).sqrt();
+    tock(&t_total, &mut t_ddot);
+
+    if config.verbosity > Verbosity::Quiet {
+        println!("Initial Residual = {normr:+.5e}");
+    }
+    let flow = config.notify(IterationInfo {
+        iteration: 0,
+        residual: normr,
+        alpha: None,
+        beta: None,
+        elapsed: mytimer() - t_begin,
+    });
+
+    tick(&mut t_total);
+    let mut criteria = StoppingCriteria::new(config, normr, || ddot(nrow, b, b).sqrt());
+    tock(&t_total, &mut t_ddot);
+    let mut reason = criteria.check(0, normr, flow);
+
+    for k in 1..max_iterations {
+        if reason.is_some() {
+            break;
+        }
+
+        tick(&mut t_total);
+        let z = preconditioner.apply(&r);
+        tock(&t_total, &mut t_precondition);
+
+        let oldrtrans = rtrans;
+        tick(&mut t_total);
+        rtrans = ddot(nrow, &r, &z);
+        tock(&t_total, &mut t_ddot);
+
+        tick(&mut t_total);
+        p = if k == 1 {
+            z
+        } else {
+            waxpby(nrow, 1.0, &z, rtrans / oldrtrans, &p)
+        };
+        tock(&t_total, &mut t_waxpby);
+
+        tick(&mut t_total);
+        let Ap = sparsemv(A, &p);
+        tock(&t_total, &mut t_sparsemv);
+
+        tick(&mut t_total);
+        let pAp = ddot(nrow, &p, &Ap);
+        tock(&t_total, &mut t_ddot);
+
+        // The method cannot take a step along a direction without positive curvature
+        if pAp.is_nan() || pAp <= 0.0 {
+            reason = Some(ConvergenceReason::Breakdown);
+            break;
+        }
+        let alpha = rtrans / pAp;
+
+        tick(&mut t_total);
+        let next = waxpby(nrow, 1.0, &result, alpha, &p);
+        let previous = std::mem::replace(&mut result, next);
+        r = waxpby(nrow, 1.0, &r, -alpha, &Ap);
+        tock(&t_total, &mut t_waxpby);
+
+        // Unlike the unpreconditioned method, `r·z` is not the squared residual, so it is found
+        // separately to check for convergence
+        let previous_normr = normr;
+        tick(&mut t_total);
+        normr = ddot(nrow, &r, &r).sqrt();
+        tock(&t_total, &mut t_ddot);
+        iteration = k;
+
+        if config.should_print(k) {
+            println!("Iteration = {k} , Residual = {normr:+.5e}");
+        }
+        let flow = config.notify(IterationInfo {
+            iteration: k,
+            residual: normr,
+            alpha: Some(alpha),
+            beta: (k > 1).then(|| rtrans / oldrtrans),
+            elapsed: mytimer() - t_begin,
+        });
+        reason = criteria.check(k, normr, flow);
+        // A step which broke down or diverged is undone, so the last good iterate is returned
+        if reason.is_some_and(ConvergenceReason::is_failure) {
+            (result, normr, iteration) = (previous, previous_normr, k - 1);
+        }
+    }
+
+    let reason = reason.unwrap_or(ConvergenceReason::MaxIterations);
+
+    SolveReport {
+        solution: result,
+        iterations: iteration,
+        final_residual: normr,
+        reason,
+        residual_history: config.take_residual_history(),
+        times: Timings {
+            total: mytimer() - t_begin,
+            ddot: t_ddot,
+            waxpby: t_waxpby,
+            sparsemv: t_sparsemv,
+            precondition: t_precondition,
+        },
+    }
+}
+
+#[test]
+fn test_pcg() {
+    let diffusion = super::DiffusionConfig::new(super::CoefficientField::Layered).contrast(1e4);
+    let (matrix, guess, rhs, exact) = SparseMatrix::generate_diffusion_matrix(8, 8, 8, diffusion);
+    let jacobi = super::Jacobi::new(&matrix);
+
+    let mut residuals = vec![];
+    let mut config = SolverConfig::new()
+        .max_iterations(500)
+        .tolerance(1e-10)
+        .verbosity(Verbosity::Quiet)
+        .callback(|_, normr| residuals.push(normr));
+    let report = pcg(&matrix, &rhs, &guess, &jacobi, &mut config);
+    drop(config);
+    assert_eq!(report.reason, ConvergenceReason::Converged);
+    assert!(report.final_residual <= 1e-10);
+    assert_eq!(residuals.len(), report.iterations as usize + 1);
+    assert!(report.times.precondition > 0.0);
+    for (actual, expected) in report.solution.iter().zip(exact.iter()) {
+        assert!((expected - actual).abs() < 1e-6);
+    }
+
+    // Scaling by the diagonal evens out the jumps in the coefficients, so fewer iterations are
+    // needed than without a preconditioner
+    let mut config = SolverConfig::new()
+        .max_iterations(500)
+        .tolerance(1e-10)
+        .verbosity(Verbosity::Quiet);
+    let unpreconditioned = super::solver(&matrix, &rhs, &guess, &mut config);
+    assert!(report.iterations < unpreconditioned.iterations);
+    assert_eq!(unpreconditioned.times.precondition, 0.0);
+}
@@ -0,0 +1,145 @@
+use std::str::FromStr;
+
+use super::{
+    AlgebraicMultigrid, Chebyshev, IncompleteCholesky, Multigrid, SmootherKind, SparseMatrix,
+    SymmetricGaussSeidel,
+};
+
+/// An approximation `M` of a matrix which is cheap to invert, used by the preconditioned conjugate
+/// gradient solver to reduce the number of iterations it needs. It must be symmetric positive
+/// definite for the solver to converge.
+pub trait Preconditioner {
+    /// Applies the inverse of the preconditioner to a residual.
+    ///
+    /// # Arguments
+    /// * `r` - The residual, with one entry for each local row of the matrix.
+    ///
+    /// # Return values
+    /// * `z` - The preconditioned residual, `z = M⁻¹ r`.
+    fn apply(&self, r: &[f64]) -> Vec<f64>;
+}
+
+/// The preconditioners which can be chosen for the preconditioned conjugate gradient solver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreconditionerKind {
+    /// The diagonal of the matrix.
+    Jacobi,
+    /// A forward and a backward Gauss-Seidel sweep in a multicolour ordering.
+    SymmetricGaussSeidel,
+    /// The incomplete Cholesky factorisation with zero fill-in.
+    IncompleteCholesky,
+    /// A few steps of the Chebyshev iteration, which needs no dot products.
+    Chebyshev,
+    /// A geometric multigrid V-cycle, for matrices generated from a stencil.
+    Multigrid,
+    /// A smoothed aggregation algebraic multigrid V-cycle, which needs no mesh.
+    AlgebraicMultigrid,
+}
+
+impl FromStr for PreconditionerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jacobi" => Ok(PreconditionerKind::Jacobi),
+            "symgs" => Ok(PreconditionerKind::SymmetricGaussSeidel),
+            "ic0" => Ok(PreconditionerKind::IncompleteCholesky),
+            "chebyshev" => Ok(PreconditionerKind::Chebyshev),
+            "mg" => Ok(PreconditionerKind::Multigrid),
+            "amg" => Ok(PreconditionerKind::AlgebraicMultigrid),
+            _ => Err(format!(
+                "Unknown preconditioner `{s}`, expected `jacobi`, `symgs`, `ic0`, `chebyshev`, \
+                 `mg` or `amg`"
+            )),
+        }
+    }
+}
+
+impl PreconditionerKind {
+    /// Build the preconditioner of a matrix, with the four levels of HPCG for geometric multigrid.
+    /// Algebraic multigrid coarsens for up to ten levels, with its default strength threshold, and
+    /// Chebyshev takes four steps.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix to precondition.
+    /// * `smoother` - The smoother of the levels of geometric multigrid.
+    ///
+    /// # Return values
+    /// * `preconditioner` - The preconditioner, or an error if it cannot be built for the matrix.
+    pub fn build(
+        &self,
+        matrix: &SparseMatrix,
+        smoother: SmootherKind,
+    ) -> Result<Box<dyn Preconditioner>, String> {
+        Ok(match self {
+            PreconditionerKind::Jacobi => Box::new(Jacobi::new(matrix)),
+            PreconditionerKind::SymmetricGaussSeidel => Box::new(SymmetricGaussSeidel::new(matrix)),
+            PreconditionerKind::IncompleteCholesky => Box::new(IncompleteCholesky::new(matrix)?),
+            PreconditionerKind::Chebyshev => Box::new(Chebyshev::new(matrix, 4)),
+            PreconditionerKind::Multigrid => Box::new(Multigrid::new(matrix, 4, smoother)?),
+            PreconditionerKind::AlgebraicMultigrid => {
+                let threshold = AlgebraicMultigrid::DEFAULT_STRENGTH_THRESHOLD;
+                Box::new(AlgebraicMultigrid::new(matrix, 10, threshold)?)
+            }
+        })
+    }
+}
+
+impl std::fmt::Display for PreconditionerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PreconditionerKind::Jacobi => write!(f, "jacobi"),
+            PreconditionerKind::SymmetricGaussSeidel => write!(f, "symgs"),
+            PreconditionerKind::IncompleteCholesky => write!(f, "ic0"),
+            PreconditionerKind::Chebyshev => write!(f, "chebyshev"),
+            PreconditionerKind::Multigrid => write!(f, "mg"),
+            PreconditionerKind::AlgebraicMultigrid => write!(f, "amg"),
+        }
+    }
+}
+
+/// The Jacobi preconditioner, which is the diagonal of the matrix.
+///
+/// # Fields
+/// * `inverse_diagonal` - The reciprocal of the diagonal entry of each row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jacobi {
+    pub inverse_diagonal: Vec<f64>,
+}
+
+impl Jacobi {
+    /// Create the Jacobi preconditioner of a matrix, which must have a non-zero diagonal.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix to precondition.
+    pub fn new(matrix: &SparseMatrix) -> Self {
+        let inverse_diagonal = matrix.diagonal().iter().map(|d| 1.0 / d).collect();
+        Jacobi { inverse_diagonal }
+    }
+}
+
+impl Preconditioner for Jacobi {
+    fn apply(&self, r: &[f64]) -> Vec<f64> {
+        r.iter()
+            .zip(self.inverse_diagonal.iter())
+            .map(|(r, d)| r * d)
+            .collect()
+    }
+}
+
+#[test]
+fn test_jacobi() {
+    let (matrix, _, _, _) = SparseMatrix::generate_matrix(2, 2, 2);
+    let jacobi = Jacobi::new(&matrix);
+    assert_eq!(jacobi.inverse_diagonal, vec![1.0 / 27.0; 8]);
+    assert_eq!(jacobi.apply(&[27.0, 54.0]), vec![1.0, 2.0]);
+
+    assert_eq!("jacobi".parse(), Ok(PreconditionerKind::Jacobi));
+    assert_eq!(
+        "symgs".parse(),
+        Ok(PreconditionerKind::SymmetricGaussSeidel)
+    );
+    assert_eq!("ic0".parse(), Ok(PreconditionerKind::IncompleteCholesky));
+    assert_eq!(PreconditionerKind::Multigrid.to_string(), "mg");
+    assert!("ilu".parse::<PreconditionerKind>().is_err());
+}
@@ -0,0 +1,149 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::{FromStr, SplitWhitespace};
+
+use super::SparseMatrix;
+
+/// Parse the next whitespace separated value from the contents of an HPC data file.
+fn next_value<T: FromStr>(tokens: &mut SplitWhitespace) -> io::Result<T> {
+    let token = tokens.next().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::UnexpectedEof, "Unexpected end of HPC data file")
+    })?;
+    token.parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Failed to parse `{token}` in HPC data file"),
+        )
+    })
+}
+
+impl SparseMatrix {
+    /// Reads a sparse matrix and its associated vectors from an HPC data file.
+    ///
+    /// The file is laid out as whitespace separated values, in the same format as read by
+    /// `read_HPC_row` in the reference implementation:
+    ///  * The total number of rows, and the total number of non-zeroes.
+    ///  * The number of non-zeroes in each row.
+    ///  * For each row, its number of non-zeroes followed by a `value index` pair per non-zero.
+    ///  * For each row, a `guess rhs exact` triple.
+    ///
+    /// # Arguments
+    ///  * `data_file` - Path to the HPC data file.
+    ///
+    /// # Return values
+    ///  * `matrix` - Sparse matrix read from the file.
+    ///  * `guess` - Inital guess for the mesh.
+    ///  * `rhs` - Right hand side.
+    ///  * `exact` - Exact solution (as computed by a direct solver).
+    #[allow(clippy::type_complexity)]
+    pub fn read_hpc_row(
+        data_file: impl AsRef<Path>,
+    ) -> io::Result<(Self, Vec<f64>, Vec<f64>, Vec<f64>)> {
+        let contents = fs::read_to_string(data_file)?;
+        let mut tokens = contents.split_whitespace();
+
+        let total_nrow: usize = next_value(&mut tokens)?;
+        let total_nnz: usize = next_value(&mut tokens)?;
+        if total_nrow == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "HPC data file must contain at least one row",
+            ));
+        }
+
+        // In non-mpi mode, the local rows are all the rows in the file
+        let (local_nrow, local_ncol) = (total_nrow, total_nrow);
+        let start_row = 0;
+        let stop_row = local_nrow - 1;
+
+        // The number of non-zero numbers in each row
+        let mut nnz_in_row = Vec::with_capacity(local_nrow);
+        for _ in 0..total_nrow {
+            nnz_in_row.push(next_value::<usize>(&mut tokens)?);
+        }
+        let local_nnz = nnz_in_row.iter().sum();
+
+        // The index of the start of each row into `list_of_vals` and `list_of_inds`
+        let mut row_start_inds: Vec<usize> = Vec::with_capacity(local_nrow);
+        let mut list_of_vals: Vec<f64> = Vec::with_capacity(local_nnz);
+        let mut list_of_inds: Vec<usize> = Vec::with_capacity(local_nnz);
+
+        for &expected_nnz in nnz_in_row.iter() {
+            let cur_nnz: usize = next_value(&mut tokens)?;
+            if cur_nnz != expected_nnz {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Row has {cur_nnz} non-zeroes, but {expected_nnz} were declared"),
+                ));
+            }
+            row_start_inds.push(list_of_vals.len());
+            for _ in 0..cur_nnz {
+                list_of_vals.push(next_value(&mut tokens)?);
+                let ind: usize = next_value(&mut tokens)?;
+                if ind >= local_ncol {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Column index {ind} is out of bounds for {local_ncol} columns"),
+                    ));
+                }
+                list_of_inds.push(ind);
+            }
+        }
+
+        // Output data other than the sparse matrix
+        let mut guess: Vec<f64> = Vec::with_capacity(local_nrow);
+        let mut rhs: Vec<f64> = Vec::with_capacity(local_nrow);
+        let mut exact: Vec<f64> = Vec::with_capacity(local_nrow);
+        for _ in 0..total_nrow {
+            guess.push(next_value(&mut tokens)?);
+            rhs.push(next_value(&mut tokens)?);
+            exact.push(next_value(&mut tokens)?);
+        }
+
+        let matrix = SparseMatrix {
+            start_row,
+            stop_row,
+            local_nrow,
+            total_nnz,
+            total_nrow,
+            local_ncol,
+            local_nnz,
+            nnz_in_row,
+            row_start_inds,
+            list_of_vals,
+            list_of_inds,
+            geometry: None,
+        };
+        Ok((matrix, guess, rhs, exact))
+    }
+}
+
+#[test]
+fn test_read_hpc_row() {
+    let data_file = std::env::temp_dir().join("hpccg_test_read_hpc_row.txt");
+    fs::write(
+        &data_file,
+        "3 7\n2 3 2\n2 4.0 0 -1.0 1\n3 -1.0 0 4.0 1 -1.0 2\n2 -1.0 1 4.0 2\n\
+         0.0 3.0 1.0\n0.0 2.0 1.0\n0.0 3.0 1.0\n",
+    )
+    .unwrap();
+    let (matrix, guess, rhs, exact) = SparseMatrix::read_hpc_row(&data_file).unwrap();
+    fs::remove_file(&data_file).unwrap();
+
+    assert_eq!(matrix.total_nrow, 3);
+    assert_eq!(matrix.total_nnz, 7);
+    assert_eq!(matrix.local_nnz, 7);
+    assert_eq!(matrix.nnz_in_row, vec![2, 3, 2]);
+    assert_eq!(matrix.row_start_inds, vec![0, 2, 5]);
+    assert_eq!(
+        matrix.list_of_vals,
+        vec![4.0, -1.0, -1.0, 4.0, -1.0, -1.0, 4.0]
+    );
+    assert_eq!(matrix.list_of_inds, vec![0, 1, 0, 1, 2, 1, 2]);
+    assert_eq!(guess, vec![0.0; 3]);
+    assert_eq!(rhs, vec![3.0, 2.0, 3.0]);
+    assert_eq!(exact, vec![1.0; 3]);
+
+    assert!(SparseMatrix::read_hpc_row(&data_file).is_err());
+}
@@ -0,0 +1,79 @@
+/// Why the solver stopped iterating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvergenceReason {
+    /// The residual fell below the tolerance.
+    Converged,
+    /// The maximum number of iterations was reached before the residual fell below the tolerance.
+    MaxIterations,
+    /// The method could not take another step, such as when the search direction is not a
+    /// direction of descent, or the residual is not a number.
+    Breakdown,
+    /// The residual grew above the divergence tolerance.
+    Diverged,
+    /// The residual stopped getting smaller for the stagnation window.
+    Stagnated,
+    /// A monitor asked the solver to stop.
+    Stopped,
+}
+
+impl ConvergenceReason {
+    /// Whether the last step of the solver failed, so that it returns the iterate before it.
+    pub(crate) fn is_failure(self) -> bool {
+        matches!(
+            self,
+            ConvergenceReason::Breakdown | ConvergenceReason::Diverged
+        )
+    }
+}
+
+impl std::fmt::Display for ConvergenceReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConvergenceReason::Converged => write!(f, "converged"),
+            ConvergenceReason::MaxIterations => write!(f, "maximum iterations reached"),
+            ConvergenceReason::Breakdown => write!(f, "breakdown"),
+            ConvergenceReason::Diverged => write!(f, "diverged"),
+            ConvergenceReason::Stagnated => write!(f, "stagnated"),
+            ConvergenceReason::Stopped => write!(f, "stopped by monitor"),
+        }
+    }
+}
+
+/// The time spent in each operation of the solver, in seconds.
+///
+/// # Fields
+/// * `total` - Time spent in the whole solver.
+/// * `ddot` - Time spent in dot products.
+/// * `waxpby` - Time spent in vector updates.
+/// * `sparsemv` - Time spent in sparse matrix-vector multiplications.
+/// * `precondition` - Time spent applying the preconditioner, including any boundary values it
+///   exchanges.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Timings {
+    pub total: f64,
+    pub ddot: f64,
+    pub waxpby: f64,
+    pub sparsemv: f64,
+    pub precondition: f64,
+}
+
+/// The outcome of a run of the solver.
+///
+/// # Fields
+/// * `solution` - The approximate result at the end of the solver loop, which is the last one
+///   before the failed step if the method broke down or diverged.
+/// * `iterations` - The number of steps the solver took to reach the solution.
+/// * `final_residual` - The norm of the residual `b - Ax` at the end of the solver loop.
+/// * `reason` - Why the solver stopped iterating.
+/// * `residual_history` - The residual at every iteration, if it was recorded by a
+///   `ResidualHistory` monitor.
+/// * `times` - The time spent in each operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveReport {
+    pub solution: Vec<f64>,
+    pub iterations: i32,
+    pub final_residual: f64,
+    pub reason: ConvergenceReason,
+    pub residual_history: Vec<f64>,
+    pub times: Timings,
+}
@@ -0,0 +1,136 @@
+use rayon::prelude::*;
+
+use super::SparseMatrix;
+
+impl SparseMatrix {
+    /// Builds a matrix from the entries of each of its rows, which need not be square, in which
+    /// case `local_ncol` is its number of columns.
+    ///
+    /// # Arguments
+    ///  * `rows` - The column and value of each entry of each row, in column order.
+    ///  * `ncol` - The number of columns.
+    ///
+    /// # Return values
+    ///  * `matrix` - The sparse matrix.
+    pub fn from_rows(rows: Vec<Vec<(usize, f64)>>, ncol: usize) -> Self {
+        let local_nrow = rows.len();
+        let nnz_in_row: Vec<usize> = rows.iter().map(Vec::len).collect();
+        let local_nnz: usize = nnz_in_row.iter().sum();
+
+        // The index of the start of each row into `list_of_vals` and `list_of_inds`
+        let row_start_inds: Vec<usize> = nnz_in_row
+            .iter()
+            .scan(0, |curvalind, &nnz| {
+                let start_ind = *curvalind;
+                *curvalind += nnz;
+                Some(start_ind)
+            })
+            .collect();
+        let (list_of_inds, list_of_vals) = rows.into_iter().flatten().unzip();
+
+        SparseMatrix {
+            start_row: 0,
+            stop_row: local_nrow.saturating_sub(1),
+            total_nrow: local_nrow,
+            total_nnz: local_nnz,
+            local_nrow,
+            local_ncol: ncol,
+            local_nnz,
+            nnz_in_row,
+            row_start_inds,
+            list_of_vals,
+            list_of_inds,
+            geometry: None,
+        }
+    }
+
+    /// The column and value of each entry of a row.
+    ///
+    /// # Arguments
+    ///  * `row` - The row of the matrix.
+    pub fn row_entries(&self, row: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let start = self.row_start_inds[row];
+        let end = start + self.nnz_in_row[row];
+        self.list_of_inds[start..end]
+            .iter()
+            .copied()
+            .zip(self.list_of_vals[start..end].iter().copied())
+    }
+
+    /// The transpose of the matrix, with the entries of each row in column order.
+    pub fn transpose(&self) -> Self {
+        let mut rows: Vec<Vec<(usize, f64)>> = vec![vec![]; self.local_ncol];
+        for row in 0..self.local_nrow {
+            for (col, value) in self.row_entries(row) {
+                rows[col].push((row, value));
+            }
+        }
+        SparseMatrix::from_rows(rows, self.local_nrow)
+    }
+
+    /// The product of the matrix with another, with the rows found in parallel, and the entries of
+    /// each row in column order.
+    ///
+    /// # Arguments
+    ///  * `other` - The matrix to multiply by on the right, with as many rows as this has columns.
+    pub fn multiply(&self, other: &SparseMatrix) -> Self {
+        assert_eq!(self.local_ncol, other.local_nrow);
+        let rows = (0..self.local_nrow)
+            .into_par_iter()
+            .map(|row| {
+                // Each entry of the row scales a row of `other`, and the products in the same
+                // column are summed
+                let mut products: Vec<(usize, f64)> = self
+                    .row_entries(row)
+                    .flat_map(|(mid, value)| {
+                        other
+                            .row_entries(mid)
+                            .map(move |(col, other_value)| (col, value * other_value))
+                    })
+                    .collect();
+                products.sort_by_key(|&(col, _)| col);
+                products.dedup_by(|next, prev| {
+                    let duplicate = next.0 == prev.0;
+                    if duplicate {
+                        prev.1 += next.1;
+                    }
+                    duplicate
+                });
+                products
+            })
+            .collect();
+        SparseMatrix::from_rows(rows, other.local_ncol)
+    }
+}
+
+#[test]
+fn test_sparse_algebra() {
+    // [1 0 2]
+    // [0 3 0]
+    let matrix = SparseMatrix::from_rows(vec![vec![(0, 1.0), (2, 2.0)], vec![(1, 3.0)]], 3);
+    assert_eq!(matrix.row_start_inds, vec![0, 2]);
+    assert_eq!(matrix.local_ncol, 3);
+
+    let transpose = matrix.transpose();
+    assert_eq!((transpose.local_nrow, transpose.local_ncol), (3, 2));
+    assert_eq!(transpose.row_entries(2).collect::<Vec<_>>(), vec![(0, 2.0)]);
+
+    // The product with its transpose is symmetric, and sums the products in each column
+    let product = matrix.multiply(&transpose);
+    assert_eq!((product.local_nrow, product.local_ncol), (2, 2));
+    assert_eq!(product.list_of_inds, vec![0, 1]);
+    assert_eq!(product.list_of_vals, vec![5.0, 9.0]);
+    let product = transpose.multiply(&matrix);
+    assert_eq!(
+        product.row_entries(0).collect::<Vec<_>>(),
+        vec![(0, 1.0), (2, 2.0)]
+    );
+    assert_eq!(product.row_entries(2).collect::<Vec<_>>()[1], (2, 4.0));
+
+    // Multiplying agrees with multiplying a vector by each matrix in turn
+    let (matrix, _, _, _) = SparseMatrix::generate_matrix(3, 3, 3);
+    let vector: Vec<f64> = (0..27).map(|i| i as f64).collect();
+    let square = matrix.multiply(&matrix);
+    let expected = super::sparsemv(&matrix, &super::sparsemv(&matrix, &vector));
+    assert_eq!(super::sparsemv(&square, &vector), expected);
+}
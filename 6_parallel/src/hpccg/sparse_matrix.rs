@@ -1,3 +1,5 @@
+use super::{Stencil, StencilConfig};
+
 /// A data structure representing a sparse matrix mesh
 ///
 /// # Fields
@@ -7,12 +9,15 @@
 /// * `total_nnz` - The total number of non-zeroes (always equal to `local_nnz` in serial mode)
 /// * `local_nrow` - The local volume of the matrix, calculated as `x*y*z` in serial mode
 /// * `local_ncol` - A variable only used in MPI mode (set to `local_nrow` in serial mode)
-/// * `local_nnz` - The local number of non-zero values, approximated as `local_nrow*27`
+/// * `local_nnz` - The local number of non-zero values, approximated as `local_nrow` times the
+///   number of points in the stencil
 /// * `nnz_in_row` - A vector containing the number of non-zeroes in each row
 /// * `row_start_inds` - A vector of pointers to values
 /// * `list_of_vals` - A vector of values stored in the matrix
 /// * `list_of_inds` - A vector of indices into the matrix
-#[derive(Debug)]
+/// * `geometry` - The structured mesh the matrix was generated on, if it was generated from a
+///   stencil
+#[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct SparseMatrix {
     pub start_row: usize,
@@ -26,6 +31,38 @@ pub struct SparseMatrix {
     pub row_start_inds: Vec<usize>,
     pub list_of_vals: Vec<f64>,
     pub list_of_inds: Vec<usize>,
+    pub geometry: Option<Geometry>,
+}
+
+/// The structured mesh a matrix was generated on, from which coarser versions of the matrix can be
+/// generated.
+///
+/// # Fields
+/// * `nx` - Size of x dimension.
+/// * `ny` - Size of y dimension.
+/// * `nz` - Size of z dimension.
+/// * `stencil` - The stencil connecting each point to its neighbours, and their weights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geometry {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    pub stencil: StencilConfig,
+}
+
+impl Geometry {
+    /// The mesh with half as many points in each dimension, if each dimension has an even size.
+    pub fn coarsen(&self) -> Option<Self> {
+        if [self.nx, self.ny, self.nz].iter().any(|n| n % 2 != 0) {
+            return None;
+        }
+        Some(Geometry {
+            nx: self.nx / 2,
+            ny: self.ny / 2,
+            nz: self.nz / 2,
+            ..*self
+        })
+    }
 }
 
 impl SparseMatrix {
@@ -46,13 +83,34 @@ impl SparseMatrix {
         ny: usize,
         nz: usize,
     ) -> (Self, Vec<f64>, Vec<f64>, Vec<f64>) {
-        let use_7pt_stencil = false;
+        Self::generate_matrix_with_stencil(nx, ny, nz, Stencil::TwentySevenPoint.into())
+    }
 
+    /// Generates the initial mesh and its associated values, using the given stencil and weights.
+    /// The right hand side is chosen so that the exact solution is all ones.
+    ///
+    /// # Arguments
+    ///  * `nx` - Size of x dimension.
+    ///  * `ny` - Size of y dimension.
+    ///  * `nz` - Size of z dimension.
+    ///  * `stencil` - The stencil connecting each point to its neighbours, and their weights.
+    ///
+    /// # Return values
+    ///  * `matrix` - Generated sparse matrix.
+    ///  * `guess` - Inital guess for the mesh.
+    ///  * `rhs` - Right hand side.
+    ///  * `exact` - Exact solution (as computed by a direct solver).
+    pub fn generate_matrix_with_stencil(
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        stencil: StencilConfig,
+    ) -> (Self, Vec<f64>, Vec<f64>, Vec<f64>) {
         // The size of our sub-block (must be non-zero)
         let local_nrow = nx * ny * nz;
         assert!(local_nrow > 0);
         // The approximate number of non-zeros per row (excluding boundary nodes)
-        let local_nnz = 27 * local_nrow;
+        let local_nnz = stencil.shape.points() * local_nrow;
         // Each processor gets a section of a chimney stack domain
         let start_row = 0;
         let stop_row = local_nrow - 1;
@@ -78,48 +136,45 @@ impl SparseMatrix {
         for iz in 0..nz {
             for iy in 0..ny {
                 for ix in 0..nx {
-                    let currow = start_row + iz * nx * ny + iy * nx + ix;
                     let mut nnzrow: usize = 0;
+                    let mut row_sum = 0.0;
                     row_start_inds.push(curvalind);
                     for sz in -1..=1 {
                         for sy in -1..=1 {
                             for sx in -1..=1 {
-                                let curcol = (currow as i32)
-                                    + sz * (nx as i32) * (ny as i32)
-                                    + sy * (nx as i32)
-                                    + sx;
-                                // Since we have a stack of nx by ny by nz domains , stacking
-                                // in the z direction, we check to see if sx and sy are
-                                // reaching outside of the domain, while the check for the
-                                // curcol being valid is sufficient to check the z values
-                                let sx_ix = (ix as i32) + sx;
-                                let sy_iy = (iy as i32) + sy;
-                                #[allow(clippy::collapsible_if)]
-                                if (sx_ix >= 0)
-                                    && (sx_ix < (nx as i32))
-                                    && (sy_iy >= 0)
-                                    && (sy_iy < (ny as i32))
-                                    && (curcol >= 0 && curcol < (local_nrow as i32))
-                                {
-                                    if !use_7pt_stencil || (sz * sz + sy * sy + sx * sx <= 1) {
-                                        // This logic will skip over point that are not part of
-                                        // a 7-pt stencil
-                                        if (curcol as usize) == currow {
-                                            list_of_vals.push(27.0);
-                                        } else {
-                                            list_of_vals.push(-1.0);
-                                        }
-                                        curvalind += 1;
-                                        list_of_inds.push(curcol as usize);
-                                        nnzrow += 1;
+                                // Skip over neighbours which are outside of the domain, unless
+                                // it wraps around in their dimension
+                                let Some([cx, cy, cz]) =
+                                    stencil.neighbour([ix, iy, iz], [sx, sy, sz], [nx, ny, nz])
+                                else {
+                                    continue;
+                                };
+                                // This logic will skip over points that are not part of
+                                // the stencil
+                                if let Some(weight) = stencil.weight(sx, sy, sz) {
+                                    let curcol = start_row + cz * nx * ny + cy * nx + cx;
+                                    row_sum += weight;
+                                    // A periodic dimension shorter than three points wraps more
+                                    // than one offset onto the same neighbour, whose weights are
+                                    // summed into a single entry
+                                    let row_start = curvalind - nnzrow;
+                                    if let Some(ind) = (row_start..curvalind)
+                                        .find(|&ind| list_of_inds[ind] == curcol)
+                                    {
+                                        list_of_vals[ind] += weight;
+                                        continue;
                                     }
+                                    list_of_vals.push(weight);
+                                    curvalind += 1;
+                                    list_of_inds.push(curcol);
+                                    nnzrow += 1;
                                 }
                             }
                         }
                     }
                     nnz_in_row.push(nnzrow);
                     guess.push(0.0);
-                    rhs.push(27.0 - ((nnzrow - 1) as f64));
+                    rhs.push(row_sum);
                     exact.push(1.0);
                 }
             }
@@ -137,9 +192,31 @@ impl SparseMatrix {
             row_start_inds,
             list_of_vals,
             list_of_inds,
+            geometry: Some(Geometry {
+                nx,
+                ny,
+                nz,
+                stencil,
+            }),
         };
         (matrix, guess, rhs, exact)
     }
+
+    /// Finds the value on the diagonal of each row of the matrix, which is zero for rows without
+    /// a diagonal entry.
+    ///
+    /// # Return values
+    ///  * `diagonal` - The diagonal entry of each row.
+    pub fn diagonal(&self) -> Vec<f64> {
+        (0..self.local_nrow)
+            .map(|row| {
+                let start = self.row_start_inds[row];
+                (start..start + self.nnz_in_row[row])
+                    .find(|&ind| self.list_of_inds[ind] == self.start_row + row)
+                    .map_or(0.0, |ind| self.list_of_vals[ind])
+            })
+            .collect()
+    }
 }
 
 #[test]
@@ -183,4 +260,68 @@ fn test_sparse_matrix() {
     assert_eq!(guess, vec![0.0; 8]);
     assert_eq!(rhs, vec![20.0; 8]);
     assert_eq!(exact, vec![1.0; 8]);
+    assert_eq!(matrix.diagonal(), vec![27.0; 8]);
+}
+
+#[test]
+fn test_sparse_matrix_7pt() {
+    let (matrix, _, rhs, _) =
+        SparseMatrix::generate_matrix_with_stencil(2, 2, 2, Stencil::SevenPoint.into());
+    assert_eq!(matrix.nnz_in_row, vec![4; 8]);
+    assert_eq!(matrix.list_of_inds[..4], [0, 1, 2, 4]);
+    assert_eq!(rhs, vec![24.0; 8]);
+    assert_eq!("7".parse(), Ok(Stencil::SevenPoint));
+    assert!("9".parse::<Stencil>().is_err());
+}
+
+#[test]
+fn test_sparse_matrix_anisotropic() {
+    let stencil = StencilConfig::new(Stencil::NineteenPoint)
+        .weights(30.0, -1.5)
+        .anisotropy(1.0, 0.5, 2.0);
+    let (matrix, _, rhs, exact) = SparseMatrix::generate_matrix_with_stencil(3, 3, 3, stencil);
+    assert_eq!(matrix.local_nnz, 19 * 27);
+    assert_eq!(matrix.nnz_in_row[13], 19);
+    assert_eq!(matrix.nnz_in_row[0], 7);
+    // The right hand side is the product of the matrix with the all ones exact solution
+    assert_eq!(super::hpccg_internals::sparsemv(&matrix, &exact), rhs);
+}
+
+#[test]
+fn test_sparse_matrix_periodic() {
+    let stencil = StencilConfig::default().periodic(true, true, true);
+    let (matrix, _, rhs, _) = SparseMatrix::generate_matrix_with_stencil(3, 4, 3, stencil);
+    assert_eq!(matrix.nnz_in_row, vec![27; 36]);
+    assert_eq!(rhs, vec![1.0; 36]);
+    // The first row wraps around to the far side of the mesh in every dimension
+    assert_eq!(matrix.list_of_inds[0], 2 * 12 + 3 * 3 + 2);
+
+    let stencil = StencilConfig::new(Stencil::SevenPoint)
+        .weights(6.0, -1.0)
+        .periodic(true, false, false)
+        .shift(0.5);
+    let (matrix, _, rhs, exact) = SparseMatrix::generate_matrix_with_stencil(3, 3, 3, stencil);
+    assert_eq!(matrix.nnz_in_row[0], 5);
+    assert_eq!(matrix.nnz_in_row[13], 7);
+    assert_eq!(rhs[13], 0.5);
+    assert_eq!(super::hpccg_internals::sparsemv(&matrix, &exact), rhs);
+
+    // A periodic dimension shorter than three points wraps both of its offsets onto one point,
+    // which has a single entry with their weights summed
+    for (nx, cols, vals) in [
+        (1, vec![0, 1, 3], vec![4.5, -1.0, -1.0]),
+        (2, vec![1, 0, 2, 6], vec![-2.0, 6.5, -1.0, -1.0]),
+    ] {
+        let (matrix, _, rhs, exact) = SparseMatrix::generate_matrix_with_stencil(nx, 3, 3, stencil);
+        assert_eq!(matrix.nnz_in_row[0], cols.len());
+        assert_eq!(matrix.list_of_inds[..cols.len()], cols);
+        assert_eq!(matrix.list_of_vals[..vals.len()], vals);
+        for (&start, &nnz) in matrix.row_start_inds.iter().zip(matrix.nnz_in_row.iter()) {
+            let mut row = matrix.list_of_inds[start..start + nnz].to_vec();
+            row.sort_unstable();
+            row.dedup();
+            assert_eq!(row.len(), nnz);
+        }
+        assert_eq!(super::hpccg_internals::sparsemv(&matrix, &exact), rhs);
+    }
 }
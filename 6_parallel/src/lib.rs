@@ -0,0 +1,20 @@
+//! The HPCCG conjugate gradient solver, for use from other codes.
+//!
+//! A problem is set up as a [`SparseMatrix`], either generated with
+//! [`SparseMatrix::generate_matrix`] or [`SparseMatrix::generate_diffusion_matrix`], or read with
+//! [`SparseMatrix::read_hpc_row`] or [`SparseMatrix::read_matrix_market`], and then solved by
+//! [`solver`], or by [`pcg`] with a [`Preconditioner`] such as [`Jacobi`],
+//! [`SymmetricGaussSeidel`], [`IncompleteCholesky`], [`Chebyshev`], [`Multigrid`] or
+//! [`AlgebraicMultigrid`], or by [`bicgstab`] or [`gmres`] if it is non-symmetric, configured by a
+//! [`SolverConfig`]. The progress of a solver can be watched with a [`Monitor`], such as a
+//! [`ResidualHistory`] which records its residuals into the [`SolveReport`].
+pub mod hpccg;
+
+pub use hpccg::hpccg_internals;
+pub use hpccg::{
+    bicgstab, compute_residual, gmres, pcg, solver, AlgebraicMultigrid, Chebyshev, CoefficientField,
+    ConvergenceReason, DiffusionConfig, Geometry, IncompleteCholesky, IterationInfo, Jacobi,
+    ManufacturedSolution, Method, Monitor, Multigrid, Preconditioner, PreconditionerKind,
+    ResidualHistory, Smoother, SmootherKind, SolveReport, SolverConfig, SparseMatrix, Stencil,
+    StencilConfig, SymmetricGaussSeidel, Symmetry, Timings, Verbosity,
+};
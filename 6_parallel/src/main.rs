@@ -1,61 +1,474 @@
-pub mod hpccg;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use hpccg_rs::hpccg;
+
+/// Run the HPCCG conjugate gradient benchmark.
+///
+/// The matrix is either generated from a stencil or a heterogeneous diffusion problem on an `NX` by
+/// `NY` by `NZ` grid, or read from an HPC data file or a Matrix Market (`.mtx`) file.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    /// Either the grid dimensions `NX NY NZ`, or the path of a data file
+    #[arg(value_name = "NX NY NZ | DATA_FILE", num_args = 0..=3)]
+    problem: Vec<String>,
+
+    /// Size of the x dimension of the grid [default: 25]
+    #[arg(long, conflicts_with = "problem")]
+    nx: Option<usize>,
+
+    /// Size of the y dimension of the grid [default: 25]
+    #[arg(long, conflicts_with = "problem")]
+    ny: Option<usize>,
+
+    /// Size of the z dimension of the grid [default: 25]
+    #[arg(long, conflicts_with = "problem")]
+    nz: Option<usize>,
+
+    /// Maximum number of iterations of the solver
+    #[arg(long, default_value_t = 150)]
+    max_iter: i32,
+
+    /// Residual at which the solver stops (zero always runs `--max-iter` iterations)
+    #[arg(long, default_value_t = 0.0)]
+    tolerance: f64,
+
+    /// Fraction of the initial residual at which the solver stops
+    #[arg(long, default_value_t = 0.0)]
+    relative_tolerance: f64,
+
+    /// Fraction of the norm of the right hand side at which the solver stops
+    #[arg(long, default_value_t = 0.0)]
+    rhs_tolerance: f64,
+
+    /// Multiple of the initial residual above which the solver has diverged
+    #[arg(long, default_value_t = f64::INFINITY)]
+    divergence_tolerance: f64,
+
+    /// Iterations without a new smallest residual after which the solver has stagnated (zero
+    /// never stagnates)
+    #[arg(long, default_value_t = 0)]
+    stagnation_window: i32,
+
+    /// Iterative method used to solve the system, one of `cg`, `pcg`, `bicgstab` or `gmres`
+    #[arg(long, default_value = "cg")]
+    solver: hpccg::Method,
+
+    /// Preconditioner used by the `pcg` solver, one of `jacobi`, `symgs`, `ic0`, `chebyshev`, `mg`
+    /// or `amg`
+    #[arg(long, default_value = "jacobi")]
+    preconditioner: hpccg::PreconditionerKind,
+
+    /// Smoother of each level of the `mg` preconditioner, one of `symgs` or `chebyshev`
+    #[arg(long, default_value = "symgs")]
+    smoother: hpccg::SmootherKind,
+
+    /// Number of iterations between restarts of GMRES
+    #[arg(long, default_value_t = 30)]
+    restart: usize,
+
+    /// Orthogonalise each new GMRES basis vector twice
+    #[arg(long)]
+    reorthogonalise: bool,
+
+    /// Stencil used to generate the matrix, one of `7`, `19` or `27`
+    #[arg(long, default_value = "27")]
+    stencil: hpccg::Stencil,
+
+    /// Weight of each point of the generated matrix
+    #[arg(long, default_value_t = 27.0, allow_negative_numbers = true)]
+    diagonal: f64,
+
+    /// Weight of each neighbour of a point of the generated matrix
+    #[arg(long, default_value_t = -1.0, allow_negative_numbers = true)]
+    off_diagonal: f64,
+
+    /// Factors scaling the weight of a neighbour offset along each of the x, y and z dimensions
+    #[arg(
+        long,
+        num_args = 3,
+        value_names = ["AX", "AY", "AZ"],
+        default_values_t = [1.0, 1.0, 1.0],
+        action = clap::ArgAction::Set
+    )]
+    anisotropy: Vec<f64>,
+
+    /// Dimensions the generated mesh wraps around in, any of `x`, `y` and `z` (for example `xz`)
+    #[arg(long, value_name = "AXES", value_parser = parse_axes)]
+    periodic: Option<[bool; 3]>,
+
+    /// Shift added to the diagonal of the generated matrix, to keep it non-singular when the
+    /// weights of each row sum to zero
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    shift: f64,
+
+    /// Cell Péclet number of a flow through the generated mesh, which makes the matrix
+    /// non-symmetric
+    #[arg(long, default_value_t = 0.0)]
+    peclet: f64,
+
+    /// Direction of the flow through the generated mesh
+    #[arg(
+        long,
+        num_args = 3,
+        value_names = ["FX", "FY", "FZ"],
+        default_values_t = [1.0, 1.0, 1.0],
+        allow_negative_numbers = true,
+        action = clap::ArgAction::Set
+    )]
+    flow: Vec<f64>,
+
+    /// Generate a heterogeneous diffusion problem instead of a stencil, with coefficients that are
+    /// one of `constant`, `random`, `layered` or `channelised`
+    #[arg(
+        long,
+        value_name = "FIELD",
+        conflicts_with_all = [
+            "stencil",
+            "diagonal",
+            "off_diagonal",
+            "anisotropy",
+            "periodic",
+            "shift",
+            "peclet",
+            "flow",
+        ]
+    )]
+    coefficients: Option<hpccg::CoefficientField>,
+
+    /// Ratio between the largest and smallest diffusion coefficients
+    #[arg(long, default_value_t = 100.0, requires = "coefficients")]
+    contrast: f64,
+
+    /// Seed of the random diffusion coefficients
+    #[arg(long, default_value_t = 0, requires = "coefficients")]
+    seed: u64,
+
+    /// Number of layers, or of channels across each of the y and z dimensions
+    #[arg(long, default_value_t = 4, requires = "coefficients")]
+    layers: usize,
+
+    /// Manufactured solution of the diffusion problem, one of `ones`, `linear` or `sine`
+    #[arg(long, default_value = "ones", requires = "coefficients")]
+    solution: hpccg::ManufacturedSolution,
+
+    /// Number of Rayon threads to use (defaults to one per core)
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// File to write the YAML report to, instead of a timestamped file in the current directory
+    #[arg(long)]
+    output_file: Option<PathBuf>,
+
+    /// File to write the residual at every iteration to, as lines of `ITERATION RESIDUAL`
+    #[arg(long)]
+    history_file: Option<PathBuf>,
+
+    /// Print the residual at every iteration
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Only print errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Where the matrix for a run comes from.
+#[derive(Debug)]
+enum Problem {
+    Generate(usize, usize, usize),
+    DataFile(String),
+}
+
+impl Cli {
+    /// Work out where the matrix comes from, from the positional arguments or size options.
+    fn problem(&self) -> Result<Problem, clap::Error> {
+        let parse_size = |size: &str| match size.parse::<usize>() {
+            Ok(size) if size > 0 => Ok(size),
+            _ => Err(Cli::command().error(
+                ErrorKind::ValueValidation,
+                format!("invalid grid size `{size}`, expected a positive integer"),
+            )),
+        };
+        match &self.problem[..] {
+            [] => {
+                let (nx, ny, nz) = (
+                    self.nx.unwrap_or(25),
+                    self.ny.unwrap_or(25),
+                    self.nz.unwrap_or(25),
+                );
+                if nx == 0 || ny == 0 || nz == 0 {
+                    return Err(Cli::command().error(
+                        ErrorKind::ValueValidation,
+                        "grid sizes must be positive integers",
+                    ));
+                }
+                Ok(Problem::Generate(nx, ny, nz))
+            }
+            [data_file] => Ok(Problem::DataFile(data_file.to_owned())),
+            [x, y, z] => Ok(Problem::Generate(
+                parse_size(x)?,
+                parse_size(y)?,
+                parse_size(z)?,
+            )),
+            _ => Err(Cli::command().error(
+                ErrorKind::WrongNumberOfValues,
+                "expected either the three grid dimensions `NX NY NZ`, or a single `DATA_FILE`",
+            )),
+        }
+    }
+
+    /// The stencil to generate the matrix with, from the `--stencil`, `--diagonal`,
+    /// `--off-diagonal`, `--anisotropy`, `--periodic`, `--shift`, `--peclet` and `--flow`
+    /// options.
+    fn stencil(&self) -> hpccg::StencilConfig {
+        let [ax, ay, az] = self.anisotropy[..] else {
+            unreachable!("`--anisotropy` takes three values")
+        };
+        let [fx, fy, fz] = self.flow[..] else {
+            unreachable!("`--flow` takes three values")
+        };
+        let [x, y, z] = self.periodic.unwrap_or_default();
+        hpccg::StencilConfig::new(self.stencil)
+            .weights(self.diagonal, self.off_diagonal)
+            .anisotropy(ax, ay, az)
+            .periodic(x, y, z)
+            .shift(self.shift)
+            .convection(self.peclet, [fx, fy, fz])
+    }
+
+    /// The heterogeneous diffusion problem to generate, if `--coefficients` is given, from it and
+    /// the `--contrast`, `--seed`, `--layers` and `--solution` options.
+    fn diffusion(&self) -> Option<hpccg::DiffusionConfig> {
+        let config = hpccg::DiffusionConfig::new(self.coefficients?)
+            .contrast(self.contrast)
+            .seed(self.seed)
+            .layers(self.layers)
+            .solution(self.solution);
+        Some(config)
+    }
+
+    /// The verbosity of the run, from the `--quiet` and `--verbose` flags.
+    fn verbosity(&self) -> hpccg::Verbosity {
+        if self.quiet {
+            hpccg::Verbosity::Quiet
+        } else if self.verbose {
+            hpccg::Verbosity::Verbose
+        } else {
+            hpccg::Verbosity::Normal
+        }
+    }
+}
+
+/// Parse a set of dimensions, such as `xz`, into whether each of the x, y and z dimensions is in
+/// the set.
+fn parse_axes(axes: &str) -> Result<[bool; 3], String> {
+    let mut periodic = [false; 3];
+    for axis in axes.chars() {
+        match axis {
+            'x' => periodic[0] = true,
+            'y' => periodic[1] = true,
+            'z' => periodic[2] = true,
+            _ => {
+                return Err(format!(
+                    "unknown dimension `{axis}`, expected `x`, `y` or `z`"
+                ))
+            }
+        }
+    }
+    Ok(periodic)
+}
 
 /// The driver code for the calculating the conjugate gradient.
 ///
 /// First,the progam generatess the matrix, right hand side vector,
-/// exact solution vector, and an initial guess. Then, it calls the
-/// HPCCG conjugate gradient solver on the matrix and associated data.
-/// Finally, it print the result of the solver, and information about
-/// the performance of the computation.
+/// exact solution vector, and an initial guess, or reads them from an
+/// HPC data file or Matrix Market (`.mtx`) file if one is given. Then,
+/// it calls the HPCCG conjugate gradient solver on the matrix and
+/// associated data. Finally, it print the result of the solver, and
+/// information about the performance of the computation, which is also
+/// saved as a report. Run with `--help` for the available options.
 #[cfg(not(tarpaulin_include))]
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    let (nx, ny, nz) = match &args.to_owned()[..] {
-        [_, x, y, z] => (
-            x.parse::<usize>().expect("Failed to parse number!"),
-            y.parse::<usize>().expect("Failed to parse number!"),
-            z.parse::<usize>().expect("Failed to parse number!"),
-        ),
-        _ =>(25, 25, 25),
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let problem = cli.problem().unwrap_or_else(|err| err.exit());
+    let verbosity = cli.verbosity();
+
+    if let Some(threads) = cli.threads {
+        if let Err(err) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+        {
+            eprintln!("Error: Failed to start {threads} Rayon threads: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let setup = match &problem {
+        Problem::DataFile(data_file) if data_file.ends_with(".mtx") => {
+            if verbosity > hpccg::Verbosity::Quiet {
+                println!("Reading Matrix Market matrix from {data_file}...");
+            }
+            hpccg::SparseMatrix::read_matrix_market(data_file)
+                .map(|matrix| {
+                    // Choose the right hand side so that the exact solution is all ones
+                    let rhs = matrix
+                        .row_start_inds
+                        .iter()
+                        .zip(matrix.nnz_in_row.iter())
+                        .map(|(&start_ind, &cur_nnz)| {
+                            matrix.list_of_vals[start_ind..start_ind + cur_nnz]
+                                .iter()
+                                .sum()
+                        })
+                        .collect();
+                    let nrow = matrix.local_nrow;
+                    (matrix, vec![0.0; nrow], rhs, vec![1.0; nrow])
+                })
+                .map_err(|err| format!("Failed to read {data_file}: {err}"))
+        }
+        Problem::DataFile(data_file) => {
+            if verbosity > hpccg::Verbosity::Quiet {
+                println!("Reading matrix info from {data_file}...");
+            }
+            hpccg::SparseMatrix::read_hpc_row(data_file)
+                .map_err(|err| format!("Failed to read {data_file}: {err}"))
+        }
+        Problem::Generate(nx, ny, nz) => Ok(match cli.diffusion() {
+            Some(diffusion) => {
+                hpccg::SparseMatrix::generate_diffusion_matrix(*nx, *ny, *nz, diffusion)
+            }
+            None => hpccg::SparseMatrix::generate_matrix_with_stencil(*nx, *ny, *nz, cli.stencil()),
+        }),
+    };
+    let (matrix, guess, rhs, exact) = match setup {
+        Ok(setup) => setup,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            return ExitCode::FAILURE;
+        }
     };
 
-    let (matrix, guess, rhs, exact) = hpccg::SparseMatrix::generate_matrix(nx, ny, nz);
-    let max_iter = 150;
-    let tolerance = 0.0;
+    let mut config = hpccg::SolverConfig::new()
+        .max_iterations(cli.max_iter)
+        .tolerance(cli.tolerance)
+        .relative_tolerance(cli.relative_tolerance)
+        .rhs_tolerance(cli.rhs_tolerance)
+        .divergence_tolerance(cli.divergence_tolerance)
+        .stagnation_window(cli.stagnation_window)
+        .verbosity(verbosity)
+        .restart(cli.restart)
+        .reorthogonalise(cli.reorthogonalise);
+    if cli.history_file.is_some() {
+        config = config.residual_history();
+    }
+    let report = match cli.solver {
+        hpccg::Method::ConjugateGradient => hpccg::solver(&matrix, &rhs, &guess, &mut config),
+        hpccg::Method::Pcg => {
+            let preconditioner = match cli.preconditioner.build(&matrix, cli.smoother) {
+                Ok(preconditioner) => preconditioner,
+                Err(err) => {
+                    eprintln!("Error: {err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            hpccg::pcg(&matrix, &rhs, &guess, preconditioner.as_ref(), &mut config)
+        }
+        hpccg::Method::BiCgStab => hpccg::bicgstab(&matrix, &rhs, &guess, &mut config),
+        hpccg::Method::Gmres => hpccg::gmres(&matrix, &rhs, &guess, &mut config),
+    };
+    let (iterations, times) = (report.iterations, report.times);
 
-    let (result, iterations, normr, times) =
-        hpccg::solver(&matrix, &rhs, &guess, max_iter, tolerance);
+    if let Some(history_file) = &cli.history_file {
+        let history: String = report
+            .residual_history
+            .iter()
+            .enumerate()
+            .map(|(k, normr)| format!("{k} {normr:e}\n"))
+            .collect();
+        if let Err(err) = std::fs::write(history_file, history) {
+            eprintln!("Error: Failed to write residual history: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
 
-    let ddot_flops = iterations as i64 * 4 * matrix.total_nrow as i64;
-    let waxpby_flops = iterations as i64 * 6 * matrix.total_nrow as i64;
-    let sparsemv_flops = iterations as i64 * 2 * matrix.total_nnz as i64;
+    let [ddot_calls, waxpby_calls, sparsemv_calls] = cli.solver.kernel_calls(&config);
+    // Each kernel does two floating point operations per entry of its vectors or matrix
+    let flops = |calls: f64, size: usize| (iterations as f64 * calls * 2.0 * size as f64) as i64;
+    let ddot_flops = flops(ddot_calls, matrix.total_nrow);
+    let waxpby_flops = flops(waxpby_calls, matrix.total_nrow);
+    let sparsemv_flops = flops(sparsemv_calls, matrix.total_nnz);
     let total_flops = ddot_flops + waxpby_flops + sparsemv_flops;
-    let residual = hpccg::compute_residual(matrix.local_nrow, &result, &exact);
-
-    println!("Mini-Application Name: hpccg-parallel");
-    println!("Mini-Application Version: 1.0");
-    println!("Parallelism:\n  MPI not enabled:\n  Rayon enabled");
-    println!("Dimensions:\n  nx: {nx}\n  ny: {ny}\n  nz: {nz}");
-    println!("Number of iterations: {iterations}");
-    println!("Final residual: {normr:.5e}");
-    println!("#********** Performance Summary (times in sec) ***********");
-    println!("Time Summary:");
-    println!("  Total: {:.4}", times[0]);
-    println!("  DDOT: {:.4}", times[1]);
-    println!("  WAXPBY: {:.4}", times[2]);
-    println!("  SPARSEMV: {:.4}", times[3]);
-    println!("FLOPS Summary:");
-    println!("  Total: {total_flops:.4}");
-    println!("  DDOT: {ddot_flops:.4}");
-    println!("  WAXPBY: {waxpby_flops:.4}");
-    println!("  SPARSEMV: {sparsemv_flops:.4}");
-    println!("MFLOPS Summary:");
-    println!("  Total: {:.4}", (total_flops as f64) / times[0] / 1.0e6);
-    println!("  DDOT: {:.4}", (ddot_flops as f64) / times[1] / 1.0e6);
-    println!("  WAXPBY: {:.4}", (waxpby_flops as f64) / times[2] / 1.0e6);
-    println!(
-        "  SPARSEMV: {:.4}",
-        (sparsemv_flops as f64) / times[3] / 1.0e6
+    let residual = hpccg::compute_residual(matrix.local_nrow, &report.solution, &exact);
+
+    let mut doc = hpccg::YamlDoc::new("hpccg-parallel", "1.0");
+    let parallelism = doc.add("Parallelism", "");
+    parallelism.add("MPI not enabled", "");
+    parallelism.add("Number of Rayon threads", rayon::current_num_threads());
+    match &problem {
+        Problem::DataFile(data_file) => {
+            doc.add("Data file", data_file.as_str());
+        }
+        Problem::Generate(nx, ny, nz) => {
+            let dimensions = doc.add("Dimensions", "");
+            dimensions.add("nx", *nx);
+            dimensions.add("ny", *ny);
+            dimensions.add("nz", *nz);
+        }
+    }
+    doc.add("Solver", cli.solver.to_string());
+    if cli.solver == hpccg::Method::Pcg {
+        doc.add("Preconditioner", cli.preconditioner.to_string());
+        if cli.preconditioner == hpccg::PreconditionerKind::Multigrid {
+            doc.add("Smoother", cli.smoother.to_string());
+        }
+    }
+    doc.add("Number of iterations", iterations);
+    doc.add("Final residual", report.final_residual);
+    doc.add("Convergence reason", report.reason.to_string());
+    doc.add(
+        "#********** Performance Summary (times in sec) ***********",
+        "",
     );
-    println!("Difference between computed and exact = {residual:.5e}.");
+
+    let time_summary = doc.add("Time Summary", "");
+    time_summary.add("Total   ", times.total);
+    time_summary.add("DDOT    ", times.ddot);
+    time_summary.add("WAXPBY  ", times.waxpby);
+    time_summary.add("SPARSEMV", times.sparsemv);
+    if cli.solver == hpccg::Method::Pcg {
+        time_summary.add("PRECOND ", times.precondition);
+    }
+
+    let flops_summary = doc.add("FLOPS Summary", "");
+    flops_summary.add("Total   ", total_flops as f64);
+    flops_summary.add("DDOT    ", ddot_flops as f64);
+    flops_summary.add("WAXPBY  ", waxpby_flops as f64);
+    flops_summary.add("SPARSEMV", sparsemv_flops as f64);
+
+    let mflops_summary = doc.add("MFLOPS Summary", "");
+    mflops_summary.add("Total   ", (total_flops as f64) / times.total / 1.0e6);
+    mflops_summary.add("DDOT    ", (ddot_flops as f64) / times.ddot / 1.0e6);
+    mflops_summary.add("WAXPBY  ", (waxpby_flops as f64) / times.waxpby / 1.0e6);
+    mflops_summary.add("SPARSEMV", (sparsemv_flops as f64) / times.sparsemv / 1.0e6);
+
+    let yaml = match &cli.output_file {
+        Some(output_file) => doc.write_yaml(output_file),
+        None => doc.generate_yaml(),
+    };
+    match yaml {
+        Ok(yaml) if verbosity > hpccg::Verbosity::Quiet => {
+            print!("{yaml}");
+            println!("Difference between computed and exact = {residual:.5e}.");
+        }
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("Error: Failed to write YAML report: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
 }
@@ -1,11 +1,34 @@
+mod bicgstab;
+mod chebyshev;
+mod chronopoulos_gear;
 pub mod compute_residual;
 mod ddot;
+mod decomposition;
+mod diffusion;
+mod dump_matlab_matrix;
 mod exchange_externals;
+mod gauss_seidel;
+mod gmres;
+mod incomplete_cholesky;
 pub mod make_local_matrix;
+mod matrix_market;
+mod matrix_powers;
+mod method;
+mod monitor;
+mod multigrid;
 pub mod mytimer;
+mod pcg;
+mod preconditioner;
+mod read_hpc_row;
+mod solve_report;
+mod solver_config;
 pub mod sparse_matrix;
 mod sparsemv;
+mod sstep_cg;
+mod stencil;
+mod stopping_criteria;
 mod waxpby;
+mod yaml_doc;
 
 pub mod hpccg_internals {
     pub use super::ddot::ddot;
@@ -15,14 +38,35 @@ pub mod hpccg_internals {
 
 use mpi::traits::*;
 
+pub use bicgstab::bicgstab;
+pub use chebyshev::Chebyshev;
+pub use chronopoulos_gear::chronopoulos_gear;
 pub use compute_residual::compute_residual;
-use ddot::ddot;
+use ddot::{ddot, fused_ddot};
+pub use decomposition::{Decomposition, ProcessGrid};
+pub use diffusion::{CoefficientField, DiffusionConfig, ManufacturedSolution};
 use exchange_externals::exchange_externals;
-pub use make_local_matrix::make_local_matrix;
+pub use gauss_seidel::SymmetricGaussSeidel;
+pub use gmres::gmres;
+pub use incomplete_cholesky::IncompleteCholesky;
+pub use make_local_matrix::{make_deep_local_matrix, make_local_matrix};
+pub use matrix_market::{read_matrix_market_vector, write_matrix_market_vector, Symmetry};
+pub use matrix_powers::MatrixPowers;
+pub use method::Method;
+pub use monitor::{IterationInfo, Monitor, ResidualHistory};
+pub use multigrid::{Multigrid, Smoother, SmootherKind};
 pub use mytimer::mytimer;
-pub use sparse_matrix::SparseMatrix;
+pub use pcg::pcg;
+pub use preconditioner::{Jacobi, Preconditioner, PreconditionerKind};
+pub use solve_report::{ConvergenceReason, SolveReport, Timings};
+pub use solver_config::{SolverConfig, Verbosity};
+pub use sparse_matrix::{Geometry, SparseMatrix};
 use sparsemv::sparsemv;
+pub use sstep_cg::{sstep_cg, KrylovBasis};
+pub use stencil::{Stencil, StencilConfig};
+use stopping_criteria::StoppingCriteria;
 use waxpby::waxpby;
+pub use yaml_doc::{YamlDoc, YamlElement, YamlValue};
 
 /// Store the start time for a code section.
 fn tick(t0: &mut f64) {
@@ -40,25 +84,18 @@ fn tock(t0: &f64, t: &mut f64) {
 /// * `A` - The input sparse matrix.
 /// * `b` - The known right hand side vector.
 /// * `x` - The current approximate solution, which starts as the initial guess.
-/// * `max_iter` - The maximum number of iterations to perform.
-/// * `tolerance` - The value the residual needs to be less than for convergence (how "good" of a
-///                 solution do we need).
+/// * `config` - The settings of the solver.
 ///
 /// # Return values
-/// * `result` - The approximate result at the end of the solver loop
-/// * `iterations` - The number of iterations for which the solver ran
-/// * `normr` - The residual difference between the current approximate solution and the exact
-///             solution.
-/// * `times` - An array of times spent for each operation (ddot/waxpby/sparse_mv/total).
+/// * `report` - The approximate result, and how the solver got there.
 #[allow(non_snake_case, unused_assignments, unused_mut)]
 pub fn solver(
     mut A: &mut SparseMatrix,
     b: &[f64],
     x: &[f64],
-    max_iterations: i32,
-    tolerance: f64,
+    config: &mut SolverConfig,
     world: &impl Communicator,
-) -> (Vec<f64>, i32, f64, Vec<f64>) {
+) -> SolveReport {
     let t_begin: f64 = mytimer();
     let mut t_total: f64 = 0.0;
     let mut t_ddot: f64 = 0.0;
@@ -79,18 +116,20 @@ pub fn solver(
     let mut normr = 0.0;
     let mut rtrans: f64 = 0.0;
     let mut oldrtrans: f64 = 0.0;
+    let mut alpha: f64 = 0.0;
+    let mut beta: f64 = 0.0;
 
     let rank = world.rank();
 
-    let print_freq = (max_iterations / 10).max(1).min(50);
+    let max_iterations = config.max_iterations;
 
     // `p` is of length `ncols`, so copy `x` to `p` for sparse matrix-vector operation
     tick(&mut t_total);
     p = waxpby(result.len(), 1.0, &result, 0.0, b);
     tock(&t_total, &mut t_waxpby);
 
-    tick(&mut t_mpi_exchange);
-    exchange_externals(&mut A, &mut p, world);
+    tick(&mut t_total);
+    exchange_externals(A, &mut p, world);
     tock(&t_total, &mut t_mpi_exchange);
 
     tick(&mut t_total);
@@ -107,12 +146,26 @@ pub fn solver(
 
     normr = rtrans.sqrt();
 
-    if rank == 0 {
+    if rank == 0 && config.verbosity > Verbosity::Quiet {
         println!("Initial Residual = {normr:+.5e}");
     }
+    let flow = config.notify(IterationInfo {
+        iteration: 0,
+        residual: normr,
+        alpha: None,
+        beta: None,
+        elapsed: mytimer() - t_begin,
+    });
+
+    tick(&mut t_total);
+    let mut criteria = StoppingCriteria::new(config, normr, || {
+        ddot(nrow, b, b, &mut t_mpi_allreduce, world).sqrt()
+    });
+    tock(&t_total, &mut t_ddot);
+    let mut reason = criteria.check(0, normr, flow);
 
     for k in 1..max_iterations {
-        if normr <= tolerance {
+        if reason.is_some() {
             break;
         }
 
@@ -121,23 +174,14 @@ pub fn solver(
             p = waxpby(nrow, 1.0, &r, 0.0, &r);
             tock(&t_total, &mut t_waxpby);
         } else {
-            oldrtrans = rtrans;
-            tick(&mut t_total);
-            rtrans = ddot(nrow, &r, &r, &mut t_mpi_allreduce, world);
-            tock(&t_total, &mut t_ddot);
-            let beta = rtrans / oldrtrans;
+            beta = rtrans / oldrtrans;
             tick(&mut t_total);
             p = waxpby(nrow, 1.0, &r, beta, &p);
             tock(&t_total, &mut t_waxpby);
         }
 
-        normr = rtrans.sqrt();
-        if rank == 0 && (k % print_freq == 0 || k + 1 == max_iterations) {
-            println!("Iteration = {k} , Residual = {normr:+.5e}");
-        }
-
-        tick(&mut t_mpi_exchange);
-        exchange_externals(&mut A, &mut p, world);
+        tick(&mut t_total);
+        exchange_externals(A, &mut p, world);
         tock(&t_total, &mut t_mpi_exchange);
 
         tick(&mut t_total);
@@ -145,28 +189,62 @@ pub fn solver(
         tock(&t_total, &mut t_sparsemv);
 
         tick(&mut t_total);
-        let alpha = ddot(r.len(), &p, &Ap, &mut t_mpi_allreduce, world);
+        let pAp = ddot(r.len(), &p, &Ap, &mut t_mpi_allreduce, world);
         tock(&t_total, &mut t_ddot);
 
-        let alpha = rtrans / alpha;
+        // The method cannot take a step along a direction without positive curvature
+        if pAp.is_nan() || pAp <= 0.0 {
+            reason = Some(ConvergenceReason::Breakdown);
+            break;
+        }
+        alpha = rtrans / pAp;
         tick(&mut t_total);
-        result = waxpby(nrow, 1.0, &result, alpha, &p);
+        let next = waxpby(nrow, 1.0, &result, alpha, &p);
+        let previous = std::mem::replace(&mut result, next);
         r = waxpby(nrow, 1.0, &r, -alpha, &Ap);
         tock(&t_total, &mut t_waxpby);
+
+        oldrtrans = rtrans;
+        tick(&mut t_total);
+        rtrans = ddot(nrow, &r, &r, &mut t_mpi_allreduce, world);
+        tock(&t_total, &mut t_ddot);
+
+        let previous_normr = normr;
+        normr = rtrans.sqrt();
         iteration = k;
+        if rank == 0 && config.should_print(k) {
+            println!("Iteration = {k} , Residual = {normr:+.5e}");
+        }
+        let flow = config.notify(IterationInfo {
+            iteration: k,
+            residual: normr,
+            alpha: Some(alpha),
+            beta: (k > 1).then_some(beta),
+            elapsed: mytimer() - t_begin,
+        });
+        reason = criteria.check(k, normr, flow);
+        // A step which broke down or diverged is undone, so the last good iterate is returned
+        if reason.is_some_and(ConvergenceReason::is_failure) {
+            (result, normr, iteration) = (previous, previous_normr, k - 1);
+        }
     }
 
-    (
-        result,
-        iteration,
-        normr,
-        vec![
-            mytimer() - t_begin,
-            t_ddot,
-            t_waxpby,
-            t_sparsemv,
-            t_mpi_allreduce,
-            t_mpi_exchange,
-        ],
-    )
+    let reason = reason.unwrap_or(ConvergenceReason::MaxIterations);
+
+    SolveReport {
+        solution: result,
+        iterations: iteration,
+        final_residual: normr,
+        reason,
+        residual_history: config.take_residual_history(),
+        times: Timings {
+            total: mytimer() - t_begin,
+            ddot: t_ddot,
+            waxpby: t_waxpby,
+            sparsemv: t_sparsemv,
+            allreduce: t_mpi_allreduce,
+            exchange: t_mpi_exchange,
+            precondition: 0.0,
+        },
+    }
 }
@@ -32,3 +32,28 @@ pub fn ddot(
     *time_allreduce += mytimer() - t0;
     global_result
 }
+
+/// A method to compute several dot products in one pass over the vectors, summed over all
+/// processors with a single reduction instead of one for each product.
+///
+/// # Arguments
+/// * `width` - The number of entries of each vector to include.
+/// * `pairs` - The pairs of vectors to compute the dot products of.
+/// * `time_allreduce` - The time spent in the reduction, which is incremented.
+/// * `world` - The MPI world to communicate over.
+pub fn fused_ddot<const N: usize>(
+    width: usize,
+    pairs: [(&[f64], &[f64]); N],
+    time_allreduce: &mut f64,
+    world: &impl Communicator,
+) -> [f64; N] {
+    let local = (0..width).fold([0.0; N], |sums, i| {
+        std::array::from_fn(|j| sums[j] + pairs[j].0[i] * pairs[j].1[i])
+    });
+
+    let t0 = mytimer();
+    let mut global = [0.0; N];
+    world.all_reduce_into(&local[..], &mut global[..], SystemOperation::sum());
+    *time_allreduce += mytimer() - t0;
+    global
+}
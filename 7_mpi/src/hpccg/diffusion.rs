@@ -0,0 +1,369 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use mpi::traits::*;
+
+use super::{Decomposition, ProcessGrid, SparseMatrix, Stencil, StencilConfig};
+
+/// The pattern of the diffusion coefficient of each cell of a heterogeneous diffusion problem.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoefficientField {
+    /// Every cell has a coefficient of one.
+    Constant,
+    /// Each cell has an independent random coefficient, log-uniformly distributed between one and
+    /// the contrast.
+    Random,
+    /// Layers stacked along the z dimension, alternating between one and the contrast.
+    Layered,
+    /// Channels running along the x dimension with a coefficient of the contrast, in a background
+    /// with a coefficient of one.
+    Channelised,
+}
+
+impl FromStr for CoefficientField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "constant" => Ok(CoefficientField::Constant),
+            "random" => Ok(CoefficientField::Random),
+            "layered" => Ok(CoefficientField::Layered),
+            "channelised" | "channelized" | "channels" => Ok(CoefficientField::Channelised),
+            _ => Err(format!(
+                "Unknown coefficient field `{s}`, expected one of `constant`, `random`, `layered` \
+                 or `channelised`"
+            )),
+        }
+    }
+}
+
+/// The manufactured solution that the right hand side of a generated problem is chosen for, where
+/// the mesh spans the unit cube and each value is taken at the centre of its cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ManufacturedSolution {
+    /// The solution is one everywhere.
+    Ones,
+    /// The solution is `x + y + z`.
+    Linear,
+    /// The solution is `sin(πx) sin(πy) sin(πz)`.
+    Sine,
+}
+
+impl FromStr for ManufacturedSolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ones" => Ok(ManufacturedSolution::Ones),
+            "linear" => Ok(ManufacturedSolution::Linear),
+            "sine" => Ok(ManufacturedSolution::Sine),
+            _ => Err(format!(
+                "Unknown manufactured solution `{s}`, expected one of `ones`, `linear` or `sine`"
+            )),
+        }
+    }
+}
+
+impl ManufacturedSolution {
+    /// The value of the solution at a cell of the mesh.
+    ///
+    /// # Arguments
+    /// * `cell` - The position of the cell in the mesh.
+    /// * `mesh` - The size of the mesh in each dimension.
+    pub fn value(&self, cell: [usize; 3], mesh: [usize; 3]) -> f64 {
+        let [x, y, z] = [0, 1, 2].map(|dim| (cell[dim] as f64 + 0.5) / mesh[dim] as f64);
+        match self {
+            ManufacturedSolution::Ones => 1.0,
+            ManufacturedSolution::Linear => x + y + z,
+            ManufacturedSolution::Sine => (PI * x).sin() * (PI * y).sin() * (PI * z).sin(),
+        }
+    }
+}
+
+/// The coefficients and manufactured solution of a heterogeneous diffusion problem, built up from
+/// the pattern of its coefficients, for example
+/// `DiffusionConfig::new(CoefficientField::Random).contrast(1e4).seed(7)`.
+///
+/// # Fields
+/// * `field` - The pattern of the coefficient of each cell.
+/// * `contrast` - The ratio between the largest and smallest coefficients.
+/// * `seed` - The seed of the random coefficients.
+/// * `layers` - The number of layers, or the number of channels across each of the y and z
+///   dimensions.
+/// * `solution` - The manufactured solution the right hand side is chosen for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffusionConfig {
+    pub field: CoefficientField,
+    pub contrast: f64,
+    pub seed: u64,
+    pub layers: usize,
+    pub solution: ManufacturedSolution,
+}
+
+impl Default for DiffusionConfig {
+    fn default() -> Self {
+        DiffusionConfig::new(CoefficientField::Random)
+    }
+}
+
+impl From<CoefficientField> for DiffusionConfig {
+    fn from(field: CoefficientField) -> Self {
+        DiffusionConfig::new(field)
+    }
+}
+
+impl DiffusionConfig {
+    /// Create a configuration with a contrast of `100`, a seed of `0`, `4` layers and a solution
+    /// of all ones.
+    ///
+    /// # Arguments
+    /// * `field` - The pattern of the coefficient of each cell.
+    pub fn new(field: CoefficientField) -> Self {
+        DiffusionConfig {
+            field,
+            contrast: 100.0,
+            seed: 0,
+            layers: 4,
+            solution: ManufacturedSolution::Ones,
+        }
+    }
+
+    /// Set the ratio between the largest and smallest coefficients.
+    pub fn contrast(mut self, contrast: f64) -> Self {
+        self.contrast = contrast;
+        self
+    }
+
+    /// Set the seed of the random coefficients.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the number of layers, or of channels across each dimension.
+    pub fn layers(mut self, layers: usize) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Set the manufactured solution the right hand side is chosen for.
+    pub fn solution(mut self, solution: ManufacturedSolution) -> Self {
+        self.solution = solution;
+        self
+    }
+
+    /// The diffusion coefficient of a cell of the mesh, which only depends on its position in the
+    /// whole mesh, so is the same however the mesh is divided up.
+    ///
+    /// # Arguments
+    /// * `cell` - The position of the cell in the mesh.
+    /// * `mesh` - The size of the mesh in each dimension.
+    pub fn coefficient(&self, cell: [usize; 3], mesh: [usize; 3]) -> f64 {
+        let [gx, gy, gz] = cell;
+        let [mx, my, mz] = mesh;
+        let high = match self.field {
+            CoefficientField::Constant => return 1.0,
+            CoefficientField::Random => {
+                let index = (gz * my + gy) * mx + gx;
+                return self.contrast.powf(unit_random(self.seed, index as u64));
+            }
+            CoefficientField::Layered => (gz * self.layers / mz) % 2 == 1,
+            CoefficientField::Channelised => {
+                // Alternate between background and channel bands across each of the y and z
+                // dimensions, so the channels are where two channel bands cross
+                let bands = 2 * self.layers + 1;
+                (gy * bands / my) % 2 == 1 && (gz * bands / mz) % 2 == 1
+            }
+        };
+        if high {
+            self.contrast
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A uniformly distributed random number in `[0, 1)`, from the SplitMix64 hash of an index.
+///
+/// # Arguments
+/// * `seed` - The seed of the random sequence.
+/// * `index` - The index into the random sequence.
+fn unit_random(seed: u64, index: u64) -> f64 {
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// The coefficient of the face between two cells, which is the harmonic mean of their
+/// coefficients, so the coupling between them is the same from either side.
+fn face_coefficient(k1: f64, k2: f64) -> f64 {
+    2.0 * k1 * k2 / (k1 + k2)
+}
+
+impl SparseMatrix {
+    /// Generates a heterogeneous diffusion problem, discretised with the 7-point finite volume
+    /// scheme and zero Dirichlet boundaries, so the matrix is symmetric positive definite for any
+    /// positive coefficients. The right hand side is chosen so that the exact solution is the
+    /// manufactured solution.
+    ///
+    /// # Arguments
+    ///  * `nx` - Size of x dimension.
+    ///  * `ny` - Size of y dimension.
+    ///  * `nz` - Size of z dimension.
+    ///  * `diffusion` - The coefficients of the cells, and the manufactured solution.
+    ///
+    /// # Return values
+    ///  * `matrix` - Generated sparse matrix.
+    ///  * `guess` - Inital guess for the mesh.
+    ///  * `rhs` - Right hand side.
+    ///  * `exact` - Exact solution (the manufactured solution).
+    pub fn generate_diffusion_matrix(
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        diffusion: DiffusionConfig,
+        world: &impl Communicator,
+    ) -> (Self, Vec<f64>, Vec<f64>, Vec<f64>) {
+        let grid = ProcessGrid::new(world.size() as usize, Decomposition::Slab);
+        Self::generate_diffusion_matrix_on_grid(nx, ny, nz, diffusion, grid, world.rank() as usize)
+    }
+
+    /// Generates one processor's part of a heterogeneous diffusion problem, where each processor
+    /// owns a brick of the mesh in a grid of processors. The coefficients only depend on the
+    /// position of each cell in the whole mesh, so the problem is the same for any grid of the
+    /// same mesh.
+    ///
+    /// # Arguments
+    ///  * `nx` - Size of x dimension of each processor's brick.
+    ///  * `ny` - Size of y dimension of each processor's brick.
+    ///  * `nz` - Size of z dimension of each processor's brick.
+    ///  * `diffusion` - The coefficients of the cells, and the manufactured solution.
+    ///  * `grid` - The arrangement of the processors.
+    ///  * `rank` - The rank of this processor.
+    ///
+    /// # Return values
+    ///  * `matrix` - Generated sparse matrix.
+    ///  * `guess` - Inital guess for the mesh.
+    ///  * `rhs` - Right hand side.
+    ///  * `exact` - Exact solution (the manufactured solution).
+    pub fn generate_diffusion_matrix_on_grid(
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        diffusion: DiffusionConfig,
+        grid: ProcessGrid,
+        rank: usize,
+    ) -> (Self, Vec<f64>, Vec<f64>, Vec<f64>) {
+        let size = grid.size();
+        assert!(rank < size);
+
+        // The size of our sub-block (must be non-zero)
+        let local_nrow = nx * ny * nz;
+        assert!(local_nrow > 0);
+        // The approximate number of non-zeros per row (excluding boundary nodes)
+        let local_nnz = 7 * local_nrow;
+        let total_nrow = local_nrow * size;
+        let total_nnz = 7 * total_nrow;
+        let local_ncol = local_nrow;
+
+        // Each processor gets a brick of the mesh, and owns a contiguous range of rows
+        let start_row = local_nrow * rank;
+        let stop_row = start_row + local_nrow - 1;
+
+        // The position of our brick in the grid, and the size of the whole mesh
+        let (rx, ry, rz) = grid.coords(rank);
+        let mesh = [grid.px * nx, grid.py * ny, grid.pz * nz];
+        // The finite volume scheme couples each cell to the cells it shares a face with
+        let stencil = StencilConfig::new(Stencil::SevenPoint);
+
+        let mut nnz_in_row = Vec::with_capacity(local_nrow);
+        let mut row_start_inds: Vec<usize> = Vec::with_capacity(local_nrow);
+
+        let mut guess: Vec<f64> = Vec::with_capacity(local_nrow);
+        let mut rhs: Vec<f64> = Vec::with_capacity(local_nrow);
+        let mut exact: Vec<f64> = Vec::with_capacity(local_nrow);
+
+        let mut list_of_vals: Vec<f64> = Vec::with_capacity(local_nnz);
+        let mut list_of_inds: Vec<i32> = Vec::with_capacity(local_nnz);
+
+        for iz in 0..nz {
+            for iy in 0..ny {
+                for ix in 0..nx {
+                    let point = [rx * nx + ix, ry * ny + iy, rz * nz + iz];
+                    let coefficient = diffusion.coefficient(point, mesh);
+                    let start_ind = list_of_vals.len();
+                    let mut diagonal_ind = start_ind;
+                    let mut diagonal = 0.0;
+                    let mut row_product = 0.0;
+                    for sz in -1..=1 {
+                        for sy in -1..=1 {
+                            for sx in -1..=1 {
+                                if !stencil.shape.contains(sx, sy, sz) {
+                                    continue;
+                                }
+                                if (sx, sy, sz) == (0, 0, 0) {
+                                    // The diagonal is filled in once all of the faces are known
+                                    diagonal_ind = list_of_vals.len();
+                                    list_of_vals.push(0.0);
+                                    list_of_inds.push(grid.global_row(point, [nx, ny, nz]) as i32);
+                                    continue;
+                                }
+                                let Some(cell) = stencil.neighbour(point, [sx, sy, sz], mesh)
+                                else {
+                                    // A boundary face, half a cell from the fixed zero boundary
+                                    diagonal += 2.0 * coefficient;
+                                    continue;
+                                };
+                                let weight = face_coefficient(
+                                    coefficient,
+                                    diffusion.coefficient(cell, mesh),
+                                );
+                                diagonal += weight;
+                                row_product -= weight * diffusion.solution.value(cell, mesh);
+                                list_of_vals.push(-weight);
+                                list_of_inds.push(grid.global_row(cell, [nx, ny, nz]) as i32);
+                            }
+                        }
+                    }
+                    list_of_vals[diagonal_ind] = diagonal;
+                    let value = diffusion.solution.value(point, mesh);
+                    row_start_inds.push(start_ind);
+                    nnz_in_row.push(list_of_vals.len() - start_ind);
+                    guess.push(0.0);
+                    rhs.push(row_product + diagonal * value);
+                    exact.push(value);
+                }
+            }
+        }
+
+        let matrix = SparseMatrix {
+            start_row,
+            stop_row,
+            local_nrow,
+            total_nnz,
+            total_nrow,
+            local_ncol,
+            local_nnz,
+            nnz_in_row,
+            row_start_inds,
+            list_of_vals,
+            list_of_inds,
+            geometry: None,
+            // ===== MPI only ===== //
+            is_local: false,
+            num_external: 0,
+            num_send_neighbors: 0,
+            external_index: vec![],
+            external_local_index: vec![],
+            total_to_be_sent: 0,
+            elements_to_send: vec![],
+            neighbors: vec![],
+            recv_length: vec![],
+            send_length: vec![],
+            send_buffer: vec![],
+        };
+        (matrix, guess, rhs, exact)
+    }
+}
@@ -0,0 +1,119 @@
+use mpi::traits::*;
+
+use super::{exchange_externals, Preconditioner, SparseMatrix};
+
+impl SparseMatrix {
+    /// Greedily colours the rows of the matrix so that no two rows of the same colour are coupled,
+    /// by giving each row in turn the lowest colour which none of its earlier neighbours has. The
+    /// 27 point stencil of `generate_matrix` needs 8 colours, one for each parity of the point's
+    /// coordinates. Only the local rows are coloured, so the column indices must already have been
+    /// made local by `make_local_matrix`, and the external columns are ignored.
+    ///
+    /// # Return values
+    ///  * `colours` - The rows of each colour, in ascending order.
+    pub fn multicolour_ordering(&self) -> Vec<Vec<usize>> {
+        let mut row_colours: Vec<usize> = Vec::with_capacity(self.local_nrow);
+        let mut colours: Vec<Vec<usize>> = vec![];
+        for row in 0..self.local_nrow {
+            let start = self.row_start_inds[row];
+            let neighbour_colours: Vec<usize> = self.list_of_inds
+                [start..start + self.nnz_in_row[row]]
+                .iter()
+                .filter(|&&col| (col as usize) < row)
+                .map(|&col| row_colours[col as usize])
+                .collect();
+            let colour = (0..)
+                .find(|colour| !neighbour_colours.contains(colour))
+                .unwrap();
+            if colour == colours.len() {
+                colours.push(vec![]);
+            }
+            colours[colour].push(row);
+            row_colours.push(colour);
+        }
+        colours
+    }
+}
+
+/// The symmetric Gauss-Seidel preconditioner, which is a forward and then a backward Gauss-Seidel
+/// sweep from a zero initial guess, and is also the smoother of multigrid. The rows are visited in
+/// a multicolour ordering, so that the rows of each colour are independent of each other and can
+/// be updated in any order.
+///
+/// Each processor sweeps over its own rows, with the values of the external rows exchanged before
+/// each sweep, so the preconditioner is a block Gauss-Seidel between the processors.
+///
+/// # Fields
+/// * `matrix` - A copy of the matrix being preconditioned, whose send buffer is used for the
+///   exchanges.
+/// * `diagonal` - The diagonal entry of each local row.
+/// * `colours` - The local rows of each colour, which are swept in order.
+/// * `world` - The MPI world to communicate over.
+pub struct SymmetricGaussSeidel<'a, C: Communicator> {
+    pub matrix: SparseMatrix,
+    pub diagonal: Vec<f64>,
+    pub colours: Vec<Vec<usize>>,
+    pub world: &'a C,
+}
+
+impl<'a, C: Communicator> SymmetricGaussSeidel<'a, C> {
+    /// Create the symmetric Gauss-Seidel preconditioner of a matrix, which must have a non-zero
+    /// diagonal, after it has been passed to `make_local_matrix`.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix to precondition.
+    /// * `world` - The MPI world to communicate over.
+    pub fn new(matrix: &SparseMatrix, world: &'a C) -> Self {
+        SymmetricGaussSeidel {
+            matrix: matrix.clone(),
+            diagonal: matrix.diagonal(),
+            colours: matrix.multicolour_ordering(),
+            world,
+        }
+    }
+
+    /// Improve an approximate solution `z` of `Az = r` with a forward and then a backward sweep,
+    /// as the smoother of multigrid.
+    ///
+    /// # Arguments
+    /// * `r` - The right hand side vector.
+    /// * `z` - The approximate solution, with one entry for each local row of the matrix.
+    pub fn smooth(&mut self, r: &[f64], z: &mut Vec<f64>) {
+        let nrow = self.matrix.local_nrow;
+        exchange_externals(&mut self.matrix, z, self.world);
+        for rows in self.colours.iter() {
+            self.sweep_colour(rows, r, z);
+        }
+        z.truncate(nrow);
+        exchange_externals(&mut self.matrix, z, self.world);
+        for rows in self.colours.iter().rev() {
+            self.sweep_colour(rows, r, z);
+        }
+        z.truncate(nrow);
+    }
+
+    /// Update the rows of one colour, solving each for its own entry of `z` with the current
+    /// values of its neighbours.
+    fn sweep_colour(&self, rows: &[usize], r: &[f64], z: &mut [f64]) {
+        let (matrix, diagonal) = (&self.matrix, &self.diagonal);
+        for &row in rows {
+            let start = matrix.row_start_inds[row];
+            let mut sum = r[row];
+            for ind in start..start + matrix.nnz_in_row[row] {
+                let col = matrix.list_of_inds[ind] as usize;
+                if col != row {
+                    sum -= matrix.list_of_vals[ind] * z[col];
+                }
+            }
+            z[row] = sum / diagonal[row];
+        }
+    }
+}
+
+impl<C: Communicator> Preconditioner for SymmetricGaussSeidel<'_, C> {
+    fn apply(&mut self, r: &[f64]) -> Vec<f64> {
+        let mut z = vec![0.0; self.matrix.local_nrow];
+        self.smooth(r, &mut z);
+        z
+    }
+}
@@ -0,0 +1,82 @@
+use std::ops::ControlFlow;
+
+/// The state of a solver when it computes the residual.
+///
+/// # Fields
+/// * `iteration` - The current iteration number, which is zero for the initial residual.
+/// * `residual` - The norm of the residual at the current iteration.
+/// * `alpha` - The latest step length along the search direction, if the method has one.
+/// * `beta` - The latest coefficient of the previous search direction in the next one, if the
+///   method has one.
+/// * `elapsed` - The time since the solver started, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterationInfo {
+    pub iteration: i32,
+    pub residual: f64,
+    pub alpha: Option<f64>,
+    pub beta: Option<f64>,
+    pub elapsed: f64,
+}
+
+/// Something which watches the progress of a solver, such as by recording or plotting its
+/// residuals, and can stop it early.
+///
+/// The monitors on each processor see the same residuals, and must all decide to stop at the
+/// same iteration, as the processors would otherwise wait for each other forever.
+pub trait Monitor {
+    /// Look at the state of the solver whenever it computes the residual, starting with the
+    /// initial residual at iteration zero and then once for each step it takes.
+    ///
+    /// # Arguments
+    /// * `info` - The state of the solver.
+    ///
+    /// # Return values
+    /// * `flow` - Whether the solver should keep iterating, or stop with the solution whose
+    ///   residual is in `info`.
+    fn monitor(&mut self, info: &IterationInfo) -> ControlFlow<()>;
+
+    /// The residuals the monitor recorded, which are moved into the report of the solver.
+    fn residual_history(&mut self) -> Option<Vec<f64>> {
+        None
+    }
+}
+
+/// A monitor which records the residual at every iteration, so that the convergence of the solver
+/// can be plotted from the `residual_history` of its report.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResidualHistory {
+    pub residuals: Vec<f64>,
+}
+
+impl Monitor for ResidualHistory {
+    fn monitor(&mut self, info: &IterationInfo) -> ControlFlow<()> {
+        self.residuals.push(info.residual);
+        ControlFlow::Continue(())
+    }
+
+    fn residual_history(&mut self) -> Option<Vec<f64>> {
+        Some(std::mem::take(&mut self.residuals))
+    }
+}
+
+/// A borrowed monitor, whose state can be looked at once the solver has finished.
+impl<M: Monitor + ?Sized> Monitor for &mut M {
+    fn monitor(&mut self, info: &IterationInfo) -> ControlFlow<()> {
+        (**self).monitor(info)
+    }
+
+    fn residual_history(&mut self) -> Option<Vec<f64>> {
+        (**self).residual_history()
+    }
+}
+
+/// A monitor which calls a function with the iteration number and residual, and never stops the
+/// solver.
+pub(crate) struct Callback<F: FnMut(i32, f64)>(pub(crate) F);
+
+impl<F: FnMut(i32, f64)> Monitor for Callback<F> {
+    fn monitor(&mut self, info: &IterationInfo) -> ControlFlow<()> {
+        (self.0)(info.iteration, info.residual);
+        ControlFlow::Continue(())
+    }
+}
@@ -0,0 +1,25 @@
+//! The HPCCG conjugate gradient solver, for use from other codes.
+//!
+//! A problem is set up as a [`SparseMatrix`], either generated with
+//! [`SparseMatrix::generate_matrix`] or [`SparseMatrix::generate_diffusion_matrix`], or read with
+//! [`SparseMatrix::read_hpc_row`] or [`SparseMatrix::read_matrix_market`], and then solved by
+//! [`solver`], [`chronopoulos_gear`] or [`sstep_cg`], or by [`pcg`] with a [`Preconditioner`] such
+//! as [`Jacobi`], [`SymmetricGaussSeidel`], [`IncompleteCholesky`], [`Chebyshev`] or [`Multigrid`],
+//! or by [`bicgstab`] or [`gmres`] if it is non-symmetric, configured by a [`SolverConfig`]. With
+//! MPI, each processor's part of the matrix must be passed to [`make_local_matrix()`] before it is
+//! solved. The progress of a solver can be watched with a [`Monitor`], such as a
+//! [`ResidualHistory`] which records its residuals into the [`SolveReport`].
+pub mod hpccg;
+
+mod tests;
+
+pub use hpccg::hpccg_internals;
+pub use hpccg::{
+    bicgstab, chronopoulos_gear, compute_residual, gmres, make_deep_local_matrix, make_local_matrix,
+    pcg, solver, sstep_cg, Chebyshev, CoefficientField, ConvergenceReason, Decomposition,
+    DiffusionConfig, Geometry, IncompleteCholesky, IterationInfo, Jacobi, KrylovBasis,
+    ManufacturedSolution, MatrixPowers, Method, Monitor, Multigrid, Preconditioner,
+    PreconditionerKind, ProcessGrid, ResidualHistory, Smoother, SmootherKind, SolveReport,
+    SolverConfig, SparseMatrix, Stencil, StencilConfig, SymmetricGaussSeidel, Symmetry, Timings,
+    Verbosity,
+};
@@ -1,118 +1,569 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use hpccg_rs::hpccg;
 use mpi::collective::SystemOperation;
 use mpi::traits::*;
 
-pub mod hpccg;
+/// Run the HPCCG conjugate gradient benchmark.
+///
+/// The matrix is either generated from a stencil on an `NX` by `NY` by `NZ` grid, or read from an
+/// HPC data file or a Matrix Market (`.mtx`) file.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    /// Either the grid dimensions `NX NY NZ`, or the path of a data file
+    #[arg(value_name = "NX NY NZ | DATA_FILE", num_args = 0..=3)]
+    problem: Vec<String>,
+
+    /// Size of the x dimension of each processor's sub-block [default: 5]
+    #[arg(long, conflicts_with = "problem")]
+    nx: Option<usize>,
+
+    /// Size of the y dimension of each processor's sub-block [default: 5]
+    #[arg(long, conflicts_with = "problem")]
+    ny: Option<usize>,
+
+    /// Size of the z dimension of each processor's sub-block [default: 5]
+    #[arg(long, conflicts_with = "problem")]
+    nz: Option<usize>,
+
+    /// Maximum number of iterations of the solver
+    #[arg(long, default_value_t = 150)]
+    max_iter: i32,
+
+    /// Residual at which the solver stops (zero always runs `--max-iter` iterations)
+    #[arg(long, default_value_t = 0.0)]
+    tolerance: f64,
+
+    /// Fraction of the initial residual at which the solver stops
+    #[arg(long, default_value_t = 0.0)]
+    relative_tolerance: f64,
+
+    /// Fraction of the norm of the right hand side at which the solver stops
+    #[arg(long, default_value_t = 0.0)]
+    rhs_tolerance: f64,
+
+    /// Multiple of the initial residual above which the solver has diverged
+    #[arg(long, default_value_t = f64::INFINITY)]
+    divergence_tolerance: f64,
+
+    /// Iterations without a new smallest residual after which the solver has stagnated (zero
+    /// never stagnates)
+    #[arg(long, default_value_t = 0)]
+    stagnation_window: i32,
+
+    /// Iterative method used to solve the system, one of `cg`, `pcg`, `bicgstab`, `gmres`,
+    /// `chrongear` or `sstep`
+    #[arg(long, default_value = "cg")]
+    solver: hpccg::Method,
+
+    /// Preconditioner used by the `pcg` solver, one of `jacobi`, `symgs`, `ic0`, `chebyshev`
+    /// or `mg`
+    #[arg(long, default_value = "jacobi")]
+    preconditioner: hpccg::PreconditionerKind,
+
+    /// Smoother of each level of the `mg` preconditioner, one of `symgs` or `chebyshev`
+    #[arg(long, default_value = "symgs")]
+    smoother: hpccg::SmootherKind,
+
+    /// Number of iterations between restarts of GMRES
+    #[arg(long, default_value_t = 30)]
+    restart: usize,
+
+    /// Orthogonalise each new GMRES basis vector twice
+    #[arg(long)]
+    reorthogonalise: bool,
+
+    /// Number of iterations of the `sstep` solver per global reduction
+    #[arg(long, default_value_t = 4)]
+    step_size: usize,
+
+    /// Krylov basis of the `sstep` solver, either `monomial` or `newton`
+    #[arg(long, default_value = "newton")]
+    basis: hpccg::KrylovBasis,
+
+    /// Stencil used to generate the matrix, one of `7`, `19` or `27`
+    #[arg(long, default_value = "27")]
+    stencil: hpccg::Stencil,
+
+    /// How the generated mesh is divided between MPI ranks, either `slab` (a stack of bricks in
+    /// the z dimension) or `grid` (a 3D grid of bricks)
+    #[arg(long, default_value = "slab")]
+    decomposition: hpccg::Decomposition,
+
+    /// Weight of each point of the generated matrix
+    #[arg(long, default_value_t = 27.0, allow_negative_numbers = true)]
+    diagonal: f64,
+
+    /// Weight of each neighbour of a point of the generated matrix
+    #[arg(long, default_value_t = -1.0, allow_negative_numbers = true)]
+    off_diagonal: f64,
+
+    /// Factors scaling the weight of a neighbour offset along each of the x, y and z dimensions
+    #[arg(
+        long,
+        num_args = 3,
+        value_names = ["AX", "AY", "AZ"],
+        default_values_t = [1.0, 1.0, 1.0],
+        action = clap::ArgAction::Set
+    )]
+    anisotropy: Vec<f64>,
+
+    /// Dimensions the generated mesh wraps around in, any of `x`, `y` and `z` (for example `xz`)
+    #[arg(long, value_name = "AXES", value_parser = parse_axes)]
+    periodic: Option<[bool; 3]>,
+
+    /// Shift added to the diagonal of the generated matrix, to keep it non-singular when the
+    /// weights of each row sum to zero
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    shift: f64,
+
+    /// Cell Péclet number of a flow through the generated mesh, which makes the matrix
+    /// non-symmetric
+    #[arg(long, default_value_t = 0.0)]
+    peclet: f64,
+
+    /// Direction of the flow through the generated mesh
+    #[arg(
+        long,
+        num_args = 3,
+        value_names = ["FX", "FY", "FZ"],
+        default_values_t = [1.0, 1.0, 1.0],
+        allow_negative_numbers = true,
+        action = clap::ArgAction::Set
+    )]
+    flow: Vec<f64>,
+
+    /// Generate a heterogeneous diffusion problem instead of a stencil, with coefficients that are
+    /// one of `constant`, `random`, `layered` or `channelised`
+    #[arg(
+        long,
+        value_name = "FIELD",
+        conflicts_with_all = [
+            "stencil",
+            "diagonal",
+            "off_diagonal",
+            "anisotropy",
+            "periodic",
+            "shift",
+            "peclet",
+            "flow",
+        ]
+    )]
+    coefficients: Option<hpccg::CoefficientField>,
+
+    /// Ratio between the largest and smallest diffusion coefficients
+    #[arg(long, default_value_t = 100.0, requires = "coefficients")]
+    contrast: f64,
+
+    /// Seed of the random diffusion coefficients
+    #[arg(long, default_value_t = 0, requires = "coefficients")]
+    seed: u64,
+
+    /// Number of layers, or of channels across each of the y and z dimensions
+    #[arg(long, default_value_t = 4, requires = "coefficients")]
+    layers: usize,
+
+    /// Manufactured solution of the diffusion problem, one of `ones`, `linear` or `sine`
+    #[arg(long, default_value = "ones", requires = "coefficients")]
+    solution: hpccg::ManufacturedSolution,
+
+    /// File to write the YAML report to, instead of a timestamped file in the current directory
+    #[arg(long)]
+    output_file: Option<PathBuf>,
+
+    /// File to write the residual at every iteration to, as lines of `ITERATION RESIDUAL`
+    #[arg(long)]
+    history_file: Option<PathBuf>,
+
+    /// Write each processor's rows of the matrix to `matRANK.dat` for Matlab. As in the
+    /// reference, this is only done on up to four processors
+    #[arg(long)]
+    dump_matrix: bool,
 
-mod tests;
+    /// Print the residual at every iteration
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Only print errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Where the matrix for a run comes from.
+#[derive(Debug)]
+enum Problem {
+    Generate(usize, usize, usize),
+    DataFile(String),
+}
+
+impl Cli {
+    /// Work out where the matrix comes from, from the positional arguments or size options.
+    fn problem(&self) -> Result<Problem, clap::Error> {
+        let parse_size = |size: &str| match size.parse::<usize>() {
+            Ok(size) if size > 0 => Ok(size),
+            _ => Err(Cli::command().error(
+                ErrorKind::ValueValidation,
+                format!("invalid grid size `{size}`, expected a positive integer"),
+            )),
+        };
+        match &self.problem[..] {
+            [] => {
+                let (nx, ny, nz) = (
+                    self.nx.unwrap_or(5),
+                    self.ny.unwrap_or(5),
+                    self.nz.unwrap_or(5),
+                );
+                if nx == 0 || ny == 0 || nz == 0 {
+                    return Err(Cli::command().error(
+                        ErrorKind::ValueValidation,
+                        "grid sizes must be positive integers",
+                    ));
+                }
+                Ok(Problem::Generate(nx, ny, nz))
+            }
+            [data_file] => Ok(Problem::DataFile(data_file.to_owned())),
+            [x, y, z] => Ok(Problem::Generate(
+                parse_size(x)?,
+                parse_size(y)?,
+                parse_size(z)?,
+            )),
+            _ => Err(Cli::command().error(
+                ErrorKind::WrongNumberOfValues,
+                "expected either the three grid dimensions `NX NY NZ`, or a single `DATA_FILE`",
+            )),
+        }
+    }
+
+    /// The stencil to generate the matrix with, from the `--stencil`, `--diagonal`,
+    /// `--off-diagonal`, `--anisotropy`, `--periodic`, `--shift`, `--peclet` and `--flow`
+    /// options.
+    fn stencil(&self) -> hpccg::StencilConfig {
+        let [ax, ay, az] = self.anisotropy[..] else {
+            unreachable!("`--anisotropy` takes three values")
+        };
+        let [fx, fy, fz] = self.flow[..] else {
+            unreachable!("`--flow` takes three values")
+        };
+        let [x, y, z] = self.periodic.unwrap_or_default();
+        hpccg::StencilConfig::new(self.stencil)
+            .weights(self.diagonal, self.off_diagonal)
+            .anisotropy(ax, ay, az)
+            .periodic(x, y, z)
+            .shift(self.shift)
+            .convection(self.peclet, [fx, fy, fz])
+    }
+
+    /// The heterogeneous diffusion problem to generate, if `--coefficients` is given, from it and
+    /// the `--contrast`, `--seed`, `--layers` and `--solution` options.
+    fn diffusion(&self) -> Option<hpccg::DiffusionConfig> {
+        let config = hpccg::DiffusionConfig::new(self.coefficients?)
+            .contrast(self.contrast)
+            .seed(self.seed)
+            .layers(self.layers)
+            .solution(self.solution);
+        Some(config)
+    }
+
+    /// The verbosity of the run, from the `--quiet` and `--verbose` flags.
+    fn verbosity(&self) -> hpccg::Verbosity {
+        if self.quiet {
+            hpccg::Verbosity::Quiet
+        } else if self.verbose {
+            hpccg::Verbosity::Verbose
+        } else {
+            hpccg::Verbosity::Normal
+        }
+    }
+}
+
+/// Parse a set of dimensions, such as `xz`, into whether each of the x, y and z dimensions is in
+/// the set.
+fn parse_axes(axes: &str) -> Result<[bool; 3], String> {
+    let mut periodic = [false; 3];
+    for axis in axes.chars() {
+        match axis {
+            'x' => periodic[0] = true,
+            'y' => periodic[1] = true,
+            'z' => periodic[2] = true,
+            _ => {
+                return Err(format!(
+                    "unknown dimension `{axis}`, expected `x`, `y` or `z`"
+                ))
+            }
+        }
+    }
+    Ok(periodic)
+}
 
 /// The driver code for the calculating the conjugate gradient.
 ///
 /// First,the progam generatess the matrix, right hand side vector,
-/// exact solution vector, and an initial guess. Then, it calls the
-/// HPCCG conjugate gradient solver on the matrix and associated data.
-/// Finally, it print the result of the solver, and information about
-/// the performance of the computation.
+/// exact solution vector, and an initial guess, or reads them from an
+/// HPC data file or Matrix Market (`.mtx`) file if one is given. Then,
+/// it calls the HPCCG conjugate gradient solver on the matrix and
+/// associated data. Finally, it print the result of the solver, and
+/// information about the performance of the computation, which is also
+/// saved as a report. Run with `--help` for the available options.
 #[cfg(not(tarpaulin_include))]
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    let (nx, ny, nz) = match &args.to_owned()[..] {
-        [_, x, y, z] => (
-            x.parse::<usize>().expect("Failed to parse number!"),
-            y.parse::<usize>().expect("Failed to parse number!"),
-            z.parse::<usize>().expect("Failed to parse number!"),
-        ),
-        _ => (5, 5, 5),
-    };
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let problem = cli.problem().unwrap_or_else(|err| err.exit());
+    let verbosity = cli.verbosity();
 
     let universe = mpi::initialize().unwrap();
     let world = universe.world();
+    let is_root = world.rank() == 0;
+    let grid = hpccg::ProcessGrid::new(world.size() as usize, cli.decomposition);
 
-    let (mut matrix, guess, rhs, exact) = hpccg::SparseMatrix::generate_matrix(nx, ny, nz, &world);
-    let max_iter = 150;
-    let tolerance = 0.0;
+    let setup = match &problem {
+        Problem::DataFile(data_file) if data_file.ends_with(".mtx") => {
+            if is_root && verbosity > hpccg::Verbosity::Quiet {
+                println!("Reading Matrix Market matrix from {data_file}...");
+            }
+            hpccg::SparseMatrix::read_matrix_market(data_file, &world)
+                .map(|matrix| {
+                    // Choose the right hand side so that the exact solution is all ones
+                    let rhs = matrix
+                        .row_start_inds
+                        .iter()
+                        .zip(matrix.nnz_in_row.iter())
+                        .map(|(&start_ind, &cur_nnz)| {
+                            matrix.list_of_vals[start_ind..start_ind + cur_nnz]
+                                .iter()
+                                .sum()
+                        })
+                        .collect();
+                    let nrow = matrix.local_nrow;
+                    (matrix, vec![0.0; nrow], rhs, vec![1.0; nrow])
+                })
+                .map_err(|err| format!("Failed to read {data_file}: {err}"))
+        }
+        Problem::DataFile(data_file) => {
+            if is_root && verbosity > hpccg::Verbosity::Quiet {
+                println!("Reading matrix info from {data_file}...");
+            }
+            hpccg::SparseMatrix::read_hpc_row(data_file, &world)
+                .map_err(|err| format!("Failed to read {data_file}: {err}"))
+        }
+        Problem::Generate(nx, ny, nz) => {
+            let rank = world.rank() as usize;
+            Ok(match cli.diffusion() {
+                Some(diffusion) => hpccg::SparseMatrix::generate_diffusion_matrix_on_grid(
+                    *nx, *ny, *nz, diffusion, grid, rank,
+                ),
+                None => hpccg::SparseMatrix::generate_matrix_on_grid(
+                    *nx,
+                    *ny,
+                    *nz,
+                    cli.stencil(),
+                    grid,
+                    rank,
+                ),
+            })
+        }
+    };
+    let (mut matrix, guess, rhs, exact) = match setup {
+        Ok(setup) => setup,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            world.abort(1);
+        }
+    };
 
     // TODO: Add timer for overhead making the matrix
     let t6 = hpccg::mytimer();
     hpccg::make_local_matrix(&mut matrix, &world);
     let t6 = hpccg::mytimer() - t6;
 
-    let (result, iterations, normr, mut times) =
-        hpccg::solver(&mut matrix, &rhs, &guess, max_iter, tolerance, &world);
+    if cli.dump_matrix {
+        if world.size() > 4 {
+            if is_root {
+                eprintln!("Warning: The matrix is only dumped on up to four processors");
+            }
+        } else if let Err(err) = matrix.dump_matlab(world.rank()) {
+            eprintln!("Error: Failed to dump the matrix: {err}");
+            world.abort(1);
+        }
+    }
+
+    let mut config = hpccg::SolverConfig::new()
+        .max_iterations(cli.max_iter)
+        .tolerance(cli.tolerance)
+        .relative_tolerance(cli.relative_tolerance)
+        .rhs_tolerance(cli.rhs_tolerance)
+        .divergence_tolerance(cli.divergence_tolerance)
+        .stagnation_window(cli.stagnation_window)
+        .verbosity(verbosity)
+        .restart(cli.restart)
+        .reorthogonalise(cli.reorthogonalise)
+        .step_size(cli.step_size)
+        .basis(cli.basis);
+    if cli.history_file.is_some() {
+        config = config.residual_history();
+    }
+    let report = match cli.solver {
+        hpccg::Method::ConjugateGradient => {
+            hpccg::solver(&mut matrix, &rhs, &guess, &mut config, &world)
+        }
+        hpccg::Method::Pcg => {
+            let mut preconditioner = match cli.preconditioner.build(&matrix, cli.smoother, &world) {
+                Ok(preconditioner) => preconditioner,
+                Err(err) => {
+                    eprintln!("Error: {err}");
+                    world.abort(1);
+                }
+            };
+            hpccg::pcg(
+                &mut matrix,
+                &rhs,
+                &guess,
+                preconditioner.as_mut(),
+                &mut config,
+                &world,
+            )
+        }
+        hpccg::Method::BiCgStab => hpccg::bicgstab(&mut matrix, &rhs, &guess, &mut config, &world),
+        hpccg::Method::Gmres => hpccg::gmres(&mut matrix, &rhs, &guess, &mut config, &world),
+        hpccg::Method::ChronopoulosGear => {
+            hpccg::chronopoulos_gear(&mut matrix, &rhs, &guess, &mut config, &world)
+        }
+        hpccg::Method::SStepCg => hpccg::sstep_cg(&mut matrix, &rhs, &guess, &mut config, &world),
+    };
+    let (iterations, times) = (report.iterations, report.times);
 
-    let ddot_flops = iterations as i64 * 4 * matrix.total_nrow as i64;
-    let waxpby_flops = iterations as i64 * 6 * matrix.total_nrow as i64;
-    let sparsemv_flops = iterations as i64 * 2 * matrix.total_nnz as i64;
+    if let Some(history_file) = cli.history_file.as_ref().filter(|_| is_root) {
+        let history: String = report
+            .residual_history
+            .iter()
+            .enumerate()
+            .map(|(k, normr)| format!("{k} {normr:e}\n"))
+            .collect();
+        if let Err(err) = std::fs::write(history_file, history) {
+            eprintln!("Error: Failed to write residual history: {err}");
+            world.abort(1);
+        }
+    }
+
+    let [ddot_calls, waxpby_calls, sparsemv_calls] = cli.solver.kernel_calls(&config);
+    // Each kernel does two floating point operations per entry of its vectors or matrix
+    let flops = |calls: f64, size: usize| (iterations as f64 * calls * 2.0 * size as f64) as i64;
+    let ddot_flops = flops(ddot_calls, matrix.total_nrow);
+    let waxpby_flops = flops(waxpby_calls, matrix.total_nrow);
+    let sparsemv_flops = flops(sparsemv_calls, matrix.total_nnz);
     let total_flops = ddot_flops + waxpby_flops + sparsemv_flops;
 
-    times.push(t6);
-    let total_sparsemv_time = times[3] + times[5] + times[6];
+    let total_sparsemv_time = times.sparsemv + times.exchange + t6;
     let mut t4min = 0.0;
     let mut t4max = 0.0;
     let mut t4avg = 0.0;
-    world.all_reduce_into(&times[4], &mut t4min, SystemOperation::min());
-    world.all_reduce_into(&times[4], &mut t4max, SystemOperation::max());
-    world.all_reduce_into(&times[4], &mut t4avg, SystemOperation::sum());
-
-    if world.rank() == 0 {
-        let residual = hpccg::compute_residual(matrix.local_nrow, &result, &exact);
-
-        println!("Mini-Application Name: hpccg");
-        println!("Mini-Application Version: 1.0");
-        println!("Parallelism:");
-        println!("  Number of MPI ranks: {}", world.size());
-        println!("  Rayon disabled");
-        println!("Dimensions:\n  nx: {nx}\n  ny: {ny}\n  nz: {nz}");
-        println!("Number of iterations: {iterations}");
-        println!("Final residual: {normr:.5e}");
-        println!("#********** Performance Summary (times in sec) ***********");
-        println!("Time Summary:");
-        println!("  Total: {:.4}", times[0]);
-        println!("  DDOT: {:.4}", times[1]);
-        println!("  WAXPBY: {:.4}", times[2]);
-        println!("  SPARSEMV: {:.4}", times[3]);
-        println!("FLOPS Summary:");
-        println!("  Total: {total_flops:.4}");
-        println!("  DDOT: {ddot_flops:.4}");
-        println!("  WAXPBY: {waxpby_flops:.4}");
-        println!("  SPARSEMV: {sparsemv_flops:.4}");
-        println!("MFLOPS Summary:");
-        println!("  Total: {:.4}", (total_flops as f64) / times[0] / 1.0e6);
-        println!("  DDOT: {:.4}", (ddot_flops as f64) / times[1] / 1.0e6);
-        println!("  WAXPBY: {:.4}", (waxpby_flops as f64) / times[2] / 1.0e6);
-        println!(
-            "  SPARSEMV: {:.4}",
-            (sparsemv_flops as f64) / times[3] / 1.0e6
-        );
-        println!("DDOT Timing Variations:");
-        println!("  Min DDOT MPI_Allreduce time: {t4min:.4}");
-        println!("  Max DDOT MPI_Allreduce time: {t4max:.4}");
-        println!("  Avg DDOT MPI_Allreduce time: {t4avg:.4}");
-        println!("SPARSEMV OVERHEADS:");
-        println!(
-            "  SPARSEMV MFLOPS W OVERHEAD: {:.4}",
-            (sparsemv_flops as f64) / total_sparsemv_time / 1.0e6
-        );
-        println!(
-            "  SPARSEMV PARALLEL OVERHEAD Time: {:.4}",
-            times[5] + times[6]
+    world.all_reduce_into(&times.allreduce, &mut t4min, SystemOperation::min());
+    world.all_reduce_into(&times.allreduce, &mut t4max, SystemOperation::max());
+    world.all_reduce_into(&times.allreduce, &mut t4avg, SystemOperation::sum());
+    t4avg /= world.size() as f64;
+
+    if is_root {
+        let residual = hpccg::compute_residual(matrix.local_nrow, &report.solution, &exact);
+
+        let mut doc = hpccg::YamlDoc::new("hpccg", "1.0");
+        let parallelism = doc.add("Parallelism", "");
+        parallelism.add("Number of MPI ranks", world.size());
+        parallelism.add("Rayon not enabled", "");
+        match &problem {
+            Problem::DataFile(data_file) => {
+                doc.add("Data file", data_file.as_str());
+            }
+            Problem::Generate(nx, ny, nz) => {
+                let process_grid = doc.get("Parallelism").unwrap().add("Process grid", "");
+                process_grid.add("px", grid.px);
+                process_grid.add("py", grid.py);
+                process_grid.add("pz", grid.pz);
+                let dimensions = doc.add("Dimensions", "");
+                dimensions.add("nx", *nx);
+                dimensions.add("ny", *ny);
+                dimensions.add("nz", *nz);
+            }
+        }
+        doc.add("Solver", cli.solver.to_string());
+        if cli.solver == hpccg::Method::Pcg {
+            doc.add("Preconditioner", cli.preconditioner.to_string());
+            if cli.preconditioner == hpccg::PreconditionerKind::Multigrid {
+                doc.add("Smoother", cli.smoother.to_string());
+            }
+        }
+        doc.add("Number of iterations", iterations);
+        doc.add("Final residual", report.final_residual);
+        doc.add("Convergence reason", report.reason.to_string());
+        doc.add(
+            "#********** Performance Summary (times in sec) ***********",
+            "",
         );
-        println!(
-            "  SPARSEMV PARALLEL OVERHEAD Pct: {:.4}",
-            ((times[5] + times[6]) / total_sparsemv_time) * 100.0
+
+        let time_summary = doc.add("Time Summary", "");
+        time_summary.add("Total   ", times.total);
+        time_summary.add("DDOT    ", times.ddot);
+        time_summary.add("WAXPBY  ", times.waxpby);
+        time_summary.add("SPARSEMV", times.sparsemv);
+        if cli.solver == hpccg::Method::Pcg {
+            time_summary.add("PRECOND ", times.precondition);
+        }
+
+        let flops_summary = doc.add("FLOPS Summary", "");
+        flops_summary.add("Total   ", total_flops as f64);
+        flops_summary.add("DDOT    ", ddot_flops as f64);
+        flops_summary.add("WAXPBY  ", waxpby_flops as f64);
+        flops_summary.add("SPARSEMV", sparsemv_flops as f64);
+
+        let mflops_summary = doc.add("MFLOPS Summary", "");
+        mflops_summary.add("Total   ", (total_flops as f64) / times.total / 1.0e6);
+        mflops_summary.add("DDOT    ", (ddot_flops as f64) / times.ddot / 1.0e6);
+        mflops_summary.add("WAXPBY  ", (waxpby_flops as f64) / times.waxpby / 1.0e6);
+        mflops_summary.add("SPARSEMV", (sparsemv_flops as f64) / times.sparsemv / 1.0e6);
+
+        let ddot_variations = doc.add("DDOT Timing Variations", "");
+        ddot_variations.add("Min DDOT MPI_Allreduce time", t4min);
+        ddot_variations.add("Max DDOT MPI_Allreduce time", t4max);
+        ddot_variations.add("Avg DDOT MPI_Allreduce time", t4avg);
+
+        let sparsemv_overheads = doc.add("SPARSEMV OVERHEADS", "");
+        sparsemv_overheads.add(
+            "SPARSEMV MFLOPS W OVERHEAD",
+            (sparsemv_flops as f64) / total_sparsemv_time / 1.0e6,
         );
-        println!("  SPARSEMV PARALLEL OVERHEAD Setup Time: {:.4}", times[6]);
-        println!(
-            "  SPARSEMV PARALLEL OVERHEAD Setup Pct: {:.4}",
-            (times[6] / total_sparsemv_time) * 100.0
+        sparsemv_overheads.add("SPARSEMV PARALLEL OVERHEAD Time", times.exchange + t6);
+        sparsemv_overheads.add(
+            "SPARSEMV PARALLEL OVERHEAD Pct",
+            ((times.exchange + t6) / total_sparsemv_time) * 100.0,
         );
-        println!(
-            "  SPARSEMV PARALLEL OVERHEAD Bdry Exch Time: {:.4}",
-            times[5]
+        sparsemv_overheads.add("SPARSEMV PARALLEL OVERHEAD Setup Time", t6);
+        sparsemv_overheads.add(
+            "SPARSEMV PARALLEL OVERHEAD Setup Pct",
+            (t6 / total_sparsemv_time) * 100.0,
         );
-        println!(
-            "  SPARSEMV PARALLEL OVERHEAD Bdry Exch Pct: {:.4}",
-            (times[5] / total_sparsemv_time) * 100.0
+        sparsemv_overheads.add("SPARSEMV PARALLEL OVERHEAD Bdry Exch Time", times.exchange);
+        sparsemv_overheads.add(
+            "SPARSEMV PARALLEL OVERHEAD Bdry Exch Pct",
+            (times.exchange / total_sparsemv_time) * 100.0,
         );
-        println!("Difference between computed and exact = {residual:.5e}.");
+
+        let yaml = match &cli.output_file {
+            Some(output_file) => doc.write_yaml(output_file),
+            None => doc.generate_yaml(),
+        };
+        match yaml {
+            Ok(yaml) if verbosity > hpccg::Verbosity::Quiet => {
+                print!("{yaml}");
+                println!("Difference between computed and exact = {residual:.5e}.");
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("Error: Failed to write YAML report: {err}");
+                world.abort(1);
+            }
+        }
     }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,112 @@
+use std::str::FromStr;
+
+/// How the mesh is divided between the MPI processes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decomposition {
+    /// Each process gets a slab of a "chimney stack" domain, stacked in the z dimension.
+    Slab,
+    /// Each process gets a brick of a 3D grid of processes, with up to 26 neighbours.
+    Grid,
+}
+
+impl FromStr for Decomposition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "slab" => Ok(Decomposition::Slab),
+            "grid" => Ok(Decomposition::Grid),
+            _ => Err(format!(
+                "Unknown decomposition `{s}`, expected `slab` or `grid`"
+            )),
+        }
+    }
+}
+
+/// The arrangement of the MPI processes into a grid, where each process owns an `nx` by `ny` by
+/// `nz` brick of the mesh. Processes are numbered with x varying fastest, then y, then z.
+///
+/// # Fields
+/// * `px` - The number of processes in the x dimension.
+/// * `py` - The number of processes in the y dimension.
+/// * `pz` - The number of processes in the z dimension.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessGrid {
+    pub px: usize,
+    pub py: usize,
+    pub pz: usize,
+}
+
+impl ProcessGrid {
+    /// Arrange a number of processes into a grid.
+    ///
+    /// The grid decomposition factors the number of processes into the grid with the smallest
+    /// surface area between processes (so the most cube-like grid), with the dimensions in
+    /// increasing order so a prime number of processes is stacked in the z dimension like the
+    /// slab decomposition.
+    ///
+    /// # Arguments
+    /// * `size` - The number of processes.
+    /// * `decomposition` - How the mesh is divided between the processes.
+    pub fn new(size: usize, decomposition: Decomposition) -> Self {
+        assert!(size > 0);
+        if decomposition == Decomposition::Slab {
+            return ProcessGrid {
+                px: 1,
+                py: 1,
+                pz: size,
+            };
+        }
+        let mut best = (1, 1, size);
+        let surface = |(px, py, pz): (usize, usize, usize)| px * py + py * pz + pz * px;
+        for px in (1..=size).filter(|&px| size.is_multiple_of(px)) {
+            for py in (px..=size / px).filter(|&py| (size / px).is_multiple_of(py)) {
+                let pz = size / px / py;
+                if pz >= py && surface((px, py, pz)) < surface(best) {
+                    best = (px, py, pz);
+                }
+            }
+        }
+        let (px, py, pz) = best;
+        ProcessGrid { px, py, pz }
+    }
+
+    /// The number of processes in the grid.
+    pub fn size(&self) -> usize {
+        self.px * self.py * self.pz
+    }
+
+    /// The position of a process in the grid.
+    ///
+    /// # Arguments
+    /// * `rank` - The rank of the process.
+    pub fn coords(&self, rank: usize) -> (usize, usize, usize) {
+        (
+            rank % self.px,
+            (rank / self.px) % self.py,
+            rank / (self.px * self.py),
+        )
+    }
+
+    /// The rank of the process at a position in the grid.
+    ///
+    /// # Arguments
+    /// * `rx` - The position of the process in the x dimension.
+    /// * `ry` - The position of the process in the y dimension.
+    /// * `rz` - The position of the process in the z dimension.
+    pub fn rank(&self, rx: usize, ry: usize, rz: usize) -> usize {
+        rz * self.px * self.py + ry * self.px + rx
+    }
+    /// The global row of a point of the mesh, where each process owns a contiguous range of rows
+    /// for the points in its brick.
+    ///
+    /// # Arguments
+    /// * `point` - The position of the point in the whole mesh.
+    /// * `brick` - The size of each process's brick in each dimension.
+    pub fn global_row(&self, point: [usize; 3], brick: [usize; 3]) -> usize {
+        let [gx, gy, gz] = point;
+        let [nx, ny, nz] = brick;
+        let owner = self.rank(gx / nx, gy / ny, gz / nz);
+        owner * nx * ny * nz + (gz % nz) * nx * ny + (gy % ny) * nx + gx % nx
+    }
+}
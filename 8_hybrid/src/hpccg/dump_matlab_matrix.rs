@@ -0,0 +1,58 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use super::SparseMatrix;
+
+/// Format a value like the C `%22.16e` conversion, with a signed exponent of at least two digits.
+fn format_c_exp(val: f64) -> String {
+    let formatted = format!("{val:.16e}");
+    let formatted = match formatted.split_once('e') {
+        Some((mantissa, exponent)) => {
+            let exponent: i32 = exponent.parse().unwrap_or_default();
+            let sign = if exponent < 0 { '-' } else { '+' };
+            format!("{mantissa}e{sign}{:02}", exponent.abs())
+        }
+        None => formatted.to_lowercase(),
+    };
+    format!("{formatted:>22}")
+}
+
+impl SparseMatrix {
+    /// Dumps this processor's rows of the matrix in (row, col, val) format for analysis with
+    /// Matlab, matching the output of `dump_matlab_matrix` in the reference implementation.
+    ///
+    /// This writes the data out to a file called `mat{rank}.dat`, which can then be read into
+    /// Matlab using `load mat0.dat` and `A = spconvert(mat0)`. The row and column indices are
+    /// written as 1-based global indices, whether or not the matrix has been through
+    /// `make_local_matrix`.
+    ///
+    /// Unlike the reference, which only dumps the matrix on up to four processors, this writes a
+    /// file for every rank it is called on, so the driver checks the number of processors before
+    /// calling it for `--dump-matrix`.
+    ///
+    /// # Arguments
+    ///  * `rank` - The rank of this processor, used as the suffix of the output file name.
+    pub fn dump_matlab(&self, rank: i32) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(format!("mat{rank}.dat"))?);
+        let global_inds = self.global_col_inds();
+        for (row, (&start_ind, &cur_nnz)) in self
+            .row_start_inds
+            .iter()
+            .zip(self.nnz_in_row.iter())
+            .enumerate()
+        {
+            let cur_inds = &global_inds[start_ind..start_ind + cur_nnz];
+            let cur_vals = &self.list_of_vals[start_ind..start_ind + cur_nnz];
+            for (&ind, &val) in cur_inds.iter().zip(cur_vals.iter()) {
+                writeln!(
+                    file,
+                    " {} {} {}",
+                    self.start_row + row + 1,
+                    ind + 1,
+                    format_c_exp(val)
+                )?;
+            }
+        }
+        file.flush()
+    }
+}
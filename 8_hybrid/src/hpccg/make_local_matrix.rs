@@ -1,8 +1,12 @@
-use super::SparseMatrix;
+// The loops in this module mirror the index-based loops of the C++ `make_local_matrix`
+#![allow(clippy::needless_range_loop)]
+
+use super::{MatrixPowers, SparseMatrix};
 
 use mpi::collective::SystemOperation;
 use mpi::point_to_point::ReceiveFuture;
 use mpi::traits::*;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
 // const MAX_EXTERNAL: usize = 100000;
@@ -11,6 +15,10 @@ const MAX_EXTERNAL: usize = 2147483647;
 const MAX_NUM_MESSAGES: usize = 10000;
 const MAX_NUM_NEIGHBORS: usize = MAX_NUM_MESSAGES;
 
+// The tags of the messages setting up the ghost region of `make_deep_local_matrix`
+const MPI_REQUEST_TAG: i32 = 199;
+const MPI_ROW_TAG: i32 = 200;
+
 const DEBUG: bool = false;
 const DEBUG_DETAILS: bool = false;
 
@@ -71,6 +79,263 @@ pub fn make_local_matrix(matrix: &mut SparseMatrix, world: &impl Communicator) {
     // println!("{:?}", matrix);
 }
 
+/// Extend the local part of a matrix, which must already have been passed to `make_local_matrix`,
+/// with a deeper ghost region for the matrix-powers kernel. The region holds the rows of other
+/// processors which are fewer than `depth` steps from the local rows in the graph of the matrix,
+/// and the columns which are at most `depth` steps away, so that after one exchange of the values
+/// of a vector in the region, `depth` products with the matrix can be computed without any more
+/// communication.
+///
+/// The region is found one level at a time, requesting the rows of the columns first reached at
+/// each level from the processors which own them.
+///
+/// # Arguments
+/// * `matrix` - The local part of the matrix.
+/// * `depth` - The number of products with the matrix the ghost region must allow.
+/// * `world` - The MPI world to communicate over.
+///
+/// # Return values
+/// * `powers` - The matrix extended with its ghost region, and how to exchange the region.
+pub fn make_deep_local_matrix(
+    matrix: &SparseMatrix,
+    depth: usize,
+    world: &impl Communicator,
+) -> MatrixPowers {
+    let size = world.size() as usize;
+    let rank = world.rank() as usize;
+
+    // Each processor owns the rows from its start row to the start row of the next
+    let mut tmp_buffer: Vec<usize> = vec![0; size];
+    let mut global_index_offsets: Vec<usize> = vec![0; size];
+    tmp_buffer[rank] = matrix.start_row;
+    world.all_reduce_into(
+        &tmp_buffer,
+        &mut global_index_offsets,
+        SystemOperation::sum(),
+    );
+    let owner = |global_ind: usize| {
+        global_index_offsets
+            .iter()
+            .rposition(|&offset| offset <= global_ind)
+            .unwrap()
+    };
+
+    // The rows of the region with their global column indices, starting with the local rows
+    let global_inds = matrix.global_col_inds();
+    let mut rows: Vec<Vec<(usize, f64)>> = (0..matrix.local_nrow)
+        .map(|row| {
+            let start = matrix.row_start_inds[row];
+            let end = start + matrix.nnz_in_row[row];
+            global_inds[start..end]
+                .iter()
+                .copied()
+                .zip(matrix.list_of_vals[start..end].iter().copied())
+                .collect()
+        })
+        .collect();
+    let mut local_inds: HashMap<usize, usize> = (0..matrix.local_nrow)
+        .map(|row| (matrix.start_row + row, row))
+        .collect();
+    let mut ghost_index = vec![];
+    let mut level_ends = vec![matrix.local_nrow];
+
+    for level in 1..=depth {
+        // The columns reached from the rows of the previous level for the first time
+        let level_start = if level == 1 { 0 } else { level_ends[level - 2] };
+        let mut reached: Vec<usize> = rows[level_start..]
+            .iter()
+            .flatten()
+            .map(|&(col, _)| col)
+            .filter(|col| !local_inds.contains_key(col))
+            .collect();
+        reached.sort_unstable();
+        reached.dedup();
+        for &global_ind in &reached {
+            local_inds.insert(global_ind, local_inds.len());
+        }
+        ghost_index.extend_from_slice(&reached);
+        level_ends.push(local_inds.len());
+
+        // The rows of the deepest level are never multiplied, so are not needed
+        if level < depth {
+            let requests = group_by_owner(&reached, owner);
+            let fetched = fetch_rows(&requests, &rows[..matrix.local_nrow], matrix, world);
+            rows.extend(fetched);
+        }
+    }
+
+    let nnz_in_row: Vec<usize> = rows.iter().map(Vec::len).collect();
+    let row_start_inds = nnz_in_row
+        .iter()
+        .scan(0, |curvalind, &nnz| {
+            let start_ind = *curvalind;
+            *curvalind += nnz;
+            Some(start_ind)
+        })
+        .collect();
+    let (list_of_inds, list_of_vals) = rows
+        .into_iter()
+        .flatten()
+        .map(|(col, value)| (local_inds[&col], value))
+        .unzip();
+
+    // Ask the owner of each column of the region for its value in every exchange
+    let ghost_inds: Vec<usize> = (matrix.local_nrow..level_ends[depth]).collect();
+    let mut by_owner: Vec<(usize, usize)> = ghost_index.iter().copied().zip(ghost_inds).collect();
+    by_owner.sort_by_key(|&(global_ind, _)| owner(global_ind));
+    let requested: Vec<usize> = by_owner.iter().map(|&(global_ind, _)| global_ind).collect();
+    let requests = group_by_owner(&requested, owner);
+    let incoming = send_lists(&requests, MPI_REQUEST_TAG, world);
+
+    MatrixPowers {
+        depth,
+        level_ends,
+        nnz_in_row,
+        row_start_inds,
+        list_of_vals,
+        list_of_inds,
+        neighbors: requests.iter().map(|(neighbor, _)| *neighbor).collect(),
+        recv_length: requests.iter().map(|(_, list)| list.len()).collect(),
+        recv_inds: by_owner.iter().map(|&(_, ind)| ind).collect(),
+        send_neighbors: incoming.iter().map(|(neighbor, _)| *neighbor).collect(),
+        send_length: incoming.iter().map(|(_, list)| list.len()).collect(),
+        elements_to_send: incoming
+            .iter()
+            .flat_map(|(_, list)| list.iter().map(|&ind| ind as usize - matrix.start_row))
+            .collect(),
+    }
+}
+
+/// Group global indices, which must be in order of the processors which own them, into a list
+/// for each of those processors.
+fn group_by_owner(
+    global_inds: &[usize],
+    owner: impl Fn(usize) -> usize,
+) -> Vec<(usize, Vec<i32>)> {
+    let mut groups: Vec<(usize, Vec<i32>)> = vec![];
+    for &global_ind in global_inds {
+        let ind_owner = owner(global_ind);
+        match groups.last_mut() {
+            Some((last_owner, group)) if *last_owner == ind_owner => group.push(global_ind as i32),
+            _ => groups.push((ind_owner, vec![global_ind as i32])),
+        }
+    }
+    groups
+}
+
+/// Fetch rows of the matrix from the processors which own them, while sending the local rows
+/// which other processors request.
+///
+/// # Arguments
+/// * `requests` - The global indices of the rows to fetch from each processor.
+/// * `local_rows` - The local rows, with global column indices.
+/// * `matrix` - The local part of the matrix.
+/// * `world` - The MPI world to communicate over.
+///
+/// # Return values
+/// * `rows` - The requested rows, with global column indices, in the order they were requested.
+fn fetch_rows(
+    requests: &[(usize, Vec<i32>)],
+    local_rows: &[Vec<(usize, f64)>],
+    matrix: &SparseMatrix,
+    world: &impl Communicator,
+) -> Vec<Vec<(usize, f64)>> {
+    let incoming = send_lists(requests, MPI_REQUEST_TAG, world);
+
+    let mut nnz_replies = vec![];
+    let mut ind_replies = vec![];
+    let mut val_replies = vec![];
+    for (requester, global_inds) in incoming {
+        let requested_rows = global_inds
+            .iter()
+            .map(|&global_ind| &local_rows[global_ind as usize - matrix.start_row]);
+        nnz_replies.push((
+            requester,
+            requested_rows.clone().map(|row| row.len() as i32).collect(),
+        ));
+        ind_replies.push((
+            requester,
+            requested_rows
+                .clone()
+                .flatten()
+                .map(|&(col, _)| col as i32)
+                .collect(),
+        ));
+        val_replies.push((
+            requester,
+            requested_rows.flatten().map(|&(_, value)| value).collect(),
+        ));
+    }
+    let nnz_in_rows = send_lists(&nnz_replies, MPI_ROW_TAG, world);
+    let inds = send_lists(&ind_replies, MPI_ROW_TAG + 1, world);
+    let vals = send_lists(&val_replies, MPI_ROW_TAG + 2, world);
+
+    // The replies are in order of the processors, as are the requests
+    let mut rows = vec![];
+    for ((nnz_in_row, inds), vals) in nnz_in_rows.iter().zip(inds.iter()).zip(vals.iter()) {
+        let mut start = 0;
+        for &nnz in &nnz_in_row.1 {
+            let end = start + nnz as usize;
+            rows.push(
+                inds.1[start..end]
+                    .iter()
+                    .map(|&col| col as usize)
+                    .zip(vals.1[start..end].iter().copied())
+                    .collect(),
+            );
+            start = end;
+        }
+    }
+    rows
+}
+
+/// Send a list of values to each of some processors, and receive the lists which other
+/// processors send to this one, without knowing in advance which processors they are.
+///
+/// # Arguments
+/// * `lists` - The processor to send each list to, and the list.
+/// * `tag` - The tag of the messages.
+/// * `world` - The MPI world to communicate over.
+///
+/// # Return values
+/// * `incoming` - The processor which sent each list received, and the list, in order of the
+///   processors.
+fn send_lists<T: Equivalence>(
+    lists: &[(usize, Vec<T>)],
+    tag: i32,
+    world: &impl Communicator,
+) -> Vec<(usize, Vec<T>)> {
+    let size = world.size() as usize;
+    let rank = world.rank() as usize;
+
+    // The number of lists each processor will receive
+    let mut tmp_buffer: Vec<usize> = vec![0; size];
+    let mut tmp_neighbors: Vec<usize> = vec![0; size];
+    for (dest, _) in lists {
+        tmp_neighbors[*dest] += 1;
+    }
+    world.all_reduce_into(&tmp_neighbors, &mut tmp_buffer, SystemOperation::sum());
+
+    let mut incoming = vec![];
+    mpi::request::multiple_scope(lists.len(), |scope, coll| {
+        for (dest, list) in lists {
+            let sreq = world
+                .process_at_rank(*dest as i32)
+                .immediate_send_with_tag(scope, &list[..], tag);
+            coll.add(sreq);
+        }
+        for _ in 0..tmp_buffer[rank] {
+            let (list, status) = world.any_process().receive_vec_with_tag(tag);
+            incoming.push((status.source_rank() as usize, list));
+        }
+        while coll.incomplete() > 0 {
+            coll.wait_any().expect("MPI_Wait error");
+        }
+    });
+    incoming.sort_by_key(|&(source, _)| source);
+    incoming
+}
+
 /// Scan the indices and transform to local
 pub fn scan_and_transform_local(
     matrix: &mut SparseMatrix,
@@ -93,13 +358,9 @@ pub fn scan_and_transform_local(
                 matrix.list_of_inds[row_start_ind + j] -= matrix.start_row as i32;
             } else {
                 // Must find out if we have already set up this point
-                if externals.contains_key(&cur_ind) {
-                    // Mark index as external by adding 1 and negating it
-                    matrix.list_of_inds[row_start_ind + j] =
-                        -(matrix.list_of_inds[row_start_ind + j] + 1);
-                } else {
-                    externals.insert(cur_ind, num_external);
-                    num_external = num_external + 1;
+                if let Entry::Vacant(e) = externals.entry(cur_ind) {
+                    e.insert(num_external);
+                    num_external += 1;
                     if num_external <= MAX_EXTERNAL {
                         matrix.external_index.push(cur_ind);
                         // Mark index as external by adding 1 and negating it
@@ -108,10 +369,16 @@ pub fn scan_and_transform_local(
                     } else {
                         panic!("Must increase `MAX_EXTERNAL` from {MAX_EXTERNAL}");
                     }
+                } else {
+                    // Mark index as external by adding 1 and negating it
+                    matrix.list_of_inds[row_start_ind + j] =
+                        -(matrix.list_of_inds[row_start_ind + j] + 1);
                 }
             }
         }
     }
+    // The local columns are now offset from zero, and the external ones negated
+    matrix.is_local = true;
 
     // TODO: Add debug timer
     if DEBUG {
@@ -238,7 +505,7 @@ fn sift_external_elements(
 ///
 fn count_num_neighbors(
     matrix: &mut SparseMatrix,
-    new_external_processor: &Vec<usize>,
+    new_external_processor: &[usize],
     world: &impl Communicator,
 ) -> (usize, usize, usize) {
     let size = world.size() as usize;
@@ -295,7 +562,7 @@ fn count_num_neighbors(
 /// external elements (in the order that we will receive this information).
 fn make_list_of_neighbors(
     matrix: &mut SparseMatrix,
-    new_external_processor: &Vec<usize>,
+    new_external_processor: &[usize],
     num_recv_neighbors: usize,
     num_send_neighbors: usize,
     world: &impl Communicator,
@@ -329,7 +596,7 @@ fn make_list_of_neighbors(
     }
 
     for i in 0..num_recv_neighbors {
-        let _ = world
+        world
             .process_at_rank(recv_list[i] as i32)
             .send_with_tag(&placeholder_data, mpi_my_tag);
     }
@@ -350,8 +617,8 @@ fn make_list_of_neighbors(
 ///  However, if they are not then add new entries to the recv list
 ///  that are in the send list (but not already in the recv list).
 fn compare_send_recv_lists(
-    recv_list: &mut Vec<usize>,
-    send_list: &Vec<usize>,
+    recv_list: &mut [usize],
+    send_list: &[usize],
     num_recv_neighbors: usize,
     num_send_neighbors: usize,
     world: &impl Communicator,
@@ -416,10 +683,10 @@ fn create_ordered_new_external(matrix: &mut SparseMatrix) -> Vec<usize> {
 fn send_processor_global_index(
     matrix: &mut SparseMatrix,
     mpi_my_tag: i32,
-    recv_list: &Vec<usize>,
+    recv_list: &[usize],
     num_recv_neighbors: usize,
     num_send_neighbors: usize,
-    new_external_processor: &Vec<usize>,
+    new_external_processor: &[usize],
     world: &impl Communicator,
 ) -> i32 {
     let mpi_my_tag = mpi_my_tag + 1;
@@ -458,7 +725,7 @@ fn send_processor_global_index(
         matrix.neighbors.push(recv_list[i]);
 
         let length = (j - start) as i32;
-        let _ = world
+        world
             .process_at_rank(recv_list[i] as i32)
             .send_with_tag(&length, mpi_my_tag);
     }
@@ -488,10 +755,10 @@ fn send_processor_global_index(
 fn build_elements_to_send_list(
     matrix: &mut SparseMatrix,
     mpi_my_tag: i32,
-    recv_list: &Vec<usize>,
+    recv_list: &[usize],
     num_recv_neighbors: usize,
     new_external: Vec<usize>,
-    new_external_processor: &Vec<usize>,
+    new_external_processor: &[usize],
     world: &impl Communicator,
 ) -> i32 {
     let mpi_my_tag = mpi_my_tag + 1;
@@ -574,7 +841,7 @@ fn build_elements_to_send_list(
     // replace global indices by local indices
     for slice in result_slices.iter() {
         for &item in slice {
-            let lhs = item as i32;
+            let lhs = item;
             let rhs = matrix.start_row as i32;
             matrix.elements_to_send.push(lhs - rhs);
         }
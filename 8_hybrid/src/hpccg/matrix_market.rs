@@ -0,0 +1,430 @@
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use mpi::collective::SystemOperation;
+use mpi::traits::*;
+
+use super::SparseMatrix;
+
+/// Whether a coordinate Matrix Market file stores every entry, or only the lower triangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Symmetry {
+    General,
+    Symmetric,
+}
+
+impl std::fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Symmetry::General => write!(f, "general"),
+            Symmetry::Symmetric => write!(f, "symmetric"),
+        }
+    }
+}
+
+/// Build an error pointing at a (1-based) line of a Matrix Market file.
+fn line_error(line_no: usize, message: impl Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Matrix Market line {line_no}: {message}"),
+    )
+}
+
+/// Parse the banner on the first line of a Matrix Market file.
+///
+/// # Arguments
+/// * `contents` - The contents of the Matrix Market file.
+/// * `format` - The storage format the caller expects, either `coordinate` or `array`.
+///
+/// # Return values
+/// * `symmetry` - The symmetry of the stored entries.
+fn parse_banner(contents: &str, format: &str) -> io::Result<Symmetry> {
+    let banner = contents.lines().next().unwrap_or_default().to_lowercase();
+    let fields: Vec<&str> = banner.split_whitespace().collect();
+    match fields[..] {
+        ["%%matrixmarket", "matrix", file_format, field, symmetry] => {
+            if file_format != format {
+                return Err(line_error(
+                    1,
+                    format!("Expected `{format}` format, found `{file_format}`"),
+                ));
+            }
+            if field != "real" && field != "integer" {
+                return Err(line_error(1, format!("Unsupported field type `{field}`")));
+            }
+            match symmetry {
+                "general" => Ok(Symmetry::General),
+                "symmetric" if format == "coordinate" => Ok(Symmetry::Symmetric),
+                _ => Err(line_error(1, format!("Unsupported symmetry `{symmetry}`"))),
+            }
+        }
+        _ => Err(line_error(1, "Missing `%%MatrixMarket matrix` banner")),
+    }
+}
+
+/// The non-comment, non-blank lines after the banner, paired with their (1-based) line numbers.
+fn data_lines(contents: &str) -> impl Iterator<Item = (usize, &str)> {
+    contents
+        .lines()
+        .enumerate()
+        .skip(1)
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('%'))
+}
+
+/// Parse a single value on a line of a Matrix Market file.
+fn parse_token<T: FromStr>(line_no: usize, token: &str) -> io::Result<T> {
+    token
+        .parse()
+        .map_err(|_| line_error(line_no, format!("Failed to parse `{token}`")))
+}
+
+/// Parse a line of a Matrix Market file which should contain exactly `count` values.
+fn parse_fields<T: FromStr>(line_no: usize, line: &str, count: usize) -> io::Result<Vec<T>> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() != count {
+        return Err(line_error(
+            line_no,
+            format!("Expected {count} values, found {}", tokens.len()),
+        ));
+    }
+    tokens
+        .iter()
+        .map(|token| parse_token(line_no, token))
+        .collect()
+}
+
+/// Read the entries of a square coordinate Matrix Market file.
+///
+/// Symmetric files are expanded to store both triangles, and the entries are sorted into row
+/// major order, with any duplicate entries summed.
+///
+/// # Arguments
+/// * `contents` - The contents of the Matrix Market file.
+///
+/// # Return values
+/// * `nrow` - The number of rows (and columns) of the matrix.
+/// * `entries` - The 0-based `(row, col, val)` entries of the matrix.
+#[allow(clippy::type_complexity)]
+fn read_coordinate(contents: &str) -> io::Result<(usize, Vec<(usize, usize, f64)>)> {
+    let symmetry = parse_banner(contents, "coordinate")?;
+    let mut lines = data_lines(contents);
+
+    let (line_no, line) = lines
+        .next()
+        .ok_or_else(|| line_error(contents.lines().count(), "Missing size line"))?;
+    let sizes: Vec<usize> = parse_fields(line_no, line, 3)?;
+    let (nrow, ncol, nnz) = (sizes[0], sizes[1], sizes[2]);
+    if nrow == 0 || nrow != ncol {
+        return Err(line_error(
+            line_no,
+            format!("Matrix must be square and non-empty, found {nrow}x{ncol}"),
+        ));
+    }
+
+    let mut entries = Vec::with_capacity(nnz);
+    let mut num_read = 0;
+    for (line_no, line) in lines {
+        if num_read == nnz {
+            return Err(line_error(
+                line_no,
+                format!("More entries than the {nnz} declared"),
+            ));
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.len() != 3 {
+            return Err(line_error(
+                line_no,
+                format!("Expected `row col value`, found {} values", tokens.len()),
+            ));
+        }
+        let row: usize = parse_token(line_no, tokens[0])?;
+        let col: usize = parse_token(line_no, tokens[1])?;
+        let val: f64 = parse_token(line_no, tokens[2])?;
+        if row == 0 || row > nrow || col == 0 || col > ncol {
+            return Err(line_error(
+                line_no,
+                format!("Entry ({row}, {col}) is outside of the {nrow}x{ncol} matrix"),
+            ));
+        }
+        entries.push((row - 1, col - 1, val));
+        if symmetry == Symmetry::Symmetric && row != col {
+            entries.push((col - 1, row - 1, val));
+        }
+        num_read += 1;
+    }
+    if num_read != nnz {
+        return Err(line_error(
+            contents.lines().count(),
+            format!("Found {num_read} entries, but {nnz} were declared"),
+        ));
+    }
+
+    entries.sort_by_key(|&(row, col, _)| (row, col));
+    entries.dedup_by(|next, prev| {
+        let duplicate = (next.0, next.1) == (prev.0, prev.1);
+        if duplicate {
+            prev.2 += next.2;
+        }
+        duplicate
+    });
+    Ok((nrow, entries))
+}
+
+/// Find the block of rows owned by this processor, matching the split used by `read_hpc_row`.
+///
+/// # Return values
+/// * `start_row` - The first row owned by this processor.
+/// * `local_nrow` - The number of rows owned by this processor.
+fn local_rows(total_nrow: usize, world: &impl Communicator) -> io::Result<(usize, usize)> {
+    let size = world.size() as usize;
+    let rank = world.rank() as usize;
+    let chunksize = total_nrow / size;
+    let remainder = total_nrow % size;
+    let local_nrow = if rank < remainder {
+        chunksize + 1
+    } else {
+        chunksize
+    };
+    if local_nrow == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Matrix Market file has too few rows to give one to each of {size} processors"),
+        ));
+    }
+    Ok((rank * chunksize + rank.min(remainder), local_nrow))
+}
+
+/// Let each processor in turn append to a file, with the lowest rank creating it.
+///
+/// # Arguments
+/// * `path` - Path to the file to write.
+/// * `world` - The MPI world to take turns over.
+/// * `write` - Writes this processor's part of the file, given whether it is the first.
+fn write_in_turn(
+    path: &Path,
+    world: &impl Communicator,
+    mut write: impl FnMut(&mut BufWriter<File>, bool) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut result = Ok(());
+    for rank in 0..world.size() {
+        if rank == world.rank() {
+            result = (|| {
+                let file = if rank == 0 {
+                    File::create(path)?
+                } else {
+                    OpenOptions::new().append(true).open(path)?
+                };
+                let mut file = BufWriter::new(file);
+                write(&mut file, rank == 0)?;
+                file.flush()
+            })();
+        }
+        world.barrier();
+    }
+    result
+}
+
+impl SparseMatrix {
+    /// Reads this processor's rows of a sparse matrix from a coordinate format Matrix Market file.
+    ///
+    /// Both `general` and `symmetric` real matrices are supported. Malformed files are reported
+    /// as `InvalidData` errors naming the offending line. Every processor reads the whole file,
+    /// and keeps the same block of rows as `read_hpc_row` would. The column indices are left as
+    /// global indices, to be transformed by `make_local_matrix`.
+    ///
+    /// # Arguments
+    ///  * `path` - Path to the `.mtx` file.
+    ///  * `world` - The MPI world to partition the rows over.
+    ///
+    /// # Return values
+    ///  * `matrix` - Sparse matrix read from the file.
+    pub fn read_matrix_market(
+        path: impl AsRef<Path>,
+        world: &impl Communicator,
+    ) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let (total_nrow, entries) = read_coordinate(&contents)?;
+        let total_nnz = entries.len();
+
+        let (start_row, local_nrow) = local_rows(total_nrow, world)?;
+        let stop_row = start_row + local_nrow - 1;
+        let local_ncol = local_nrow;
+
+        let mut nnz_in_row = vec![0; local_nrow];
+        let mut list_of_vals: Vec<f64> = vec![];
+        let mut list_of_inds: Vec<i32> = vec![];
+        for (row, col, val) in entries {
+            if start_row <= row && row <= stop_row {
+                nnz_in_row[row - start_row] += 1;
+                list_of_vals.push(val);
+                list_of_inds.push(col as i32);
+            }
+        }
+        let local_nnz = list_of_vals.len();
+
+        // The index of the start of each row into `list_of_vals` and `list_of_inds`
+        let row_start_inds: Vec<usize> = nnz_in_row
+            .iter()
+            .scan(0, |curvalind, &nnz| {
+                let start_ind = *curvalind;
+                *curvalind += nnz;
+                Some(start_ind)
+            })
+            .collect();
+
+        Ok(SparseMatrix {
+            start_row,
+            stop_row,
+            local_nrow,
+            total_nnz,
+            total_nrow,
+            local_ncol,
+            local_nnz,
+            nnz_in_row,
+            row_start_inds,
+            list_of_vals,
+            list_of_inds,
+            geometry: None,
+            // ===== MPI only ===== //
+            is_local: false,
+            num_external: 0,
+            num_send_neighbors: 0,
+            external_index: vec![],
+            external_local_index: vec![],
+            total_to_be_sent: 0,
+            elements_to_send: vec![],
+            neighbors: vec![],
+            recv_length: vec![],
+            send_length: vec![],
+            send_buffer: vec![],
+        })
+    }
+
+    /// Writes the sparse matrix to a `coordinate real` Matrix Market file.
+    ///
+    /// The column indices are mapped back to global ones with `global_col_inds`, whether or not
+    /// the matrix has been through `make_local_matrix`. Each processor appends its own rows in
+    /// turn. A `symmetric` file only stores the lower triangle, so the matrix must be symmetric
+    /// for it to be read back the same.
+    ///
+    /// # Arguments
+    ///  * `path` - Path to the `.mtx` file to create.
+    ///  * `symmetry` - Whether to write every entry, or only the lower triangle.
+    ///  * `world` - The MPI world the matrix is distributed over.
+    pub fn write_matrix_market(
+        &self,
+        path: impl AsRef<Path>,
+        symmetry: Symmetry,
+        world: &impl Communicator,
+    ) -> io::Result<()> {
+        let global_inds = &self.global_col_inds();
+        let entries: Vec<(usize, usize, f64)> = self
+            .row_start_inds
+            .iter()
+            .zip(self.nnz_in_row.iter())
+            .enumerate()
+            .flat_map(|(row, (&start_ind, &cur_nnz))| {
+                (start_ind..start_ind + cur_nnz)
+                    .map(move |j| (self.start_row + row, global_inds[j], self.list_of_vals[j]))
+            })
+            .filter(|&(row, col, _)| symmetry == Symmetry::General || col <= row)
+            .collect();
+        let mut nnz = 0;
+        world.all_reduce_into(&entries.len(), &mut nnz, SystemOperation::sum());
+
+        write_in_turn(path.as_ref(), world, |file, is_first| {
+            if is_first {
+                writeln!(file, "%%MatrixMarket matrix coordinate real {symmetry}")?;
+                writeln!(file, "{} {} {nnz}", self.total_nrow, self.total_nrow)?;
+            }
+            for &(row, col, val) in &entries {
+                writeln!(file, "{} {} {val:e}", row + 1, col + 1)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Reads this processor's block of a vector, such as the right hand side or exact solution, from
+/// an `array real general` Matrix Market file.
+///
+/// # Arguments
+/// * `path` - Path to the `.mtx` file.
+/// * `world` - The MPI world to partition the rows over.
+///
+/// # Return values
+/// * `vector` - The values of the single column stored in the file for this processor's rows.
+pub fn read_matrix_market_vector(
+    path: impl AsRef<Path>,
+    world: &impl Communicator,
+) -> io::Result<Vec<f64>> {
+    let contents = fs::read_to_string(path)?;
+    parse_banner(&contents, "array")?;
+    let mut lines = data_lines(&contents);
+
+    let (line_no, line) = lines
+        .next()
+        .ok_or_else(|| line_error(contents.lines().count(), "Missing size line"))?;
+    let sizes: Vec<usize> = parse_fields(line_no, line, 2)?;
+    let (nrow, ncol) = (sizes[0], sizes[1]);
+    if ncol != 1 {
+        return Err(line_error(
+            line_no,
+            format!("Expected a single column vector, found {ncol} columns"),
+        ));
+    }
+    let (start_row, local_nrow) = local_rows(nrow, world)?;
+
+    let mut vector = Vec::with_capacity(local_nrow);
+    let mut num_read = 0;
+    for (line_no, line) in lines {
+        if num_read == nrow {
+            return Err(line_error(
+                line_no,
+                format!("More values than the {nrow} declared"),
+            ));
+        }
+        let val: f64 = parse_token(line_no, line)?;
+        if start_row <= num_read && num_read < start_row + local_nrow {
+            vector.push(val);
+        }
+        num_read += 1;
+    }
+    if num_read != nrow {
+        return Err(line_error(
+            contents.lines().count(),
+            format!("Found {num_read} values, but {nrow} were declared"),
+        ));
+    }
+    Ok(vector)
+}
+
+/// Writes a distributed vector to an `array real general` Matrix Market file.
+///
+/// # Arguments
+/// * `path` - Path to the `.mtx` file to create.
+/// * `vector` - This processor's block of the vector to write as a single column.
+/// * `world` - The MPI world the vector is distributed over.
+pub fn write_matrix_market_vector(
+    path: impl AsRef<Path>,
+    vector: &[f64],
+    world: &impl Communicator,
+) -> io::Result<()> {
+    let mut nrow = 0;
+    world.all_reduce_into(&vector.len(), &mut nrow, SystemOperation::sum());
+
+    write_in_turn(path.as_ref(), world, |file, is_first| {
+        if is_first {
+            writeln!(file, "%%MatrixMarket matrix array real general")?;
+            writeln!(file, "{nrow} 1")?;
+        }
+        for val in vector {
+            writeln!(file, "{val:e}")?;
+        }
+        Ok(())
+    })
+}
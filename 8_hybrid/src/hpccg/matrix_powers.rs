@@ -0,0 +1,143 @@
+use mpi::traits::*;
+use rayon::prelude::*;
+
+/// The matrix-powers kernel, which computes the vectors `v, p₁(A)v, ..., pₖ(A)v` of a Krylov
+/// basis with a single exchange of boundary values, instead of one exchange per product with the
+/// matrix.
+///
+/// The local rows are extended with a ghost region of the rows of other processors, made by
+/// `make_deep_local_matrix`. The region is in levels, by the number of steps from the local rows
+/// in the graph of the matrix, and each product is computed redundantly on one level fewer than
+/// the last, so that the extra work grows with the depth of the region.
+///
+/// # Fields
+/// * `depth` - The largest number of products which can be computed after one exchange.
+/// * `level_ends` - The number of rows and columns of the region up to and including each level,
+///   starting with the local rows as level zero.
+/// * `nnz_in_row` - The number of non-zeroes in each row of the local rows and ghost region.
+/// * `row_start_inds` - The index of the start of each row into `list_of_vals` and `list_of_inds`.
+/// * `list_of_vals` - The values of the non-zeroes.
+/// * `list_of_inds` - The column of each non-zero, in the local rows followed by the ghost region.
+/// * `neighbors` - The processors which own the columns of the ghost region.
+/// * `recv_length` - The number of values received from each of `neighbors`.
+/// * `recv_inds` - The column of each value received, in the order they are received.
+/// * `send_neighbors` - The processors whose ghost regions hold local rows.
+/// * `send_length` - The number of values sent to each of `send_neighbors`.
+/// * `elements_to_send` - The local row of each value sent, in the order they are sent.
+#[derive(Debug, Clone)]
+pub struct MatrixPowers {
+    pub depth: usize,
+    pub level_ends: Vec<usize>,
+    pub nnz_in_row: Vec<usize>,
+    pub row_start_inds: Vec<usize>,
+    pub list_of_vals: Vec<f64>,
+    pub list_of_inds: Vec<usize>,
+    pub neighbors: Vec<usize>,
+    pub recv_length: Vec<usize>,
+    pub recv_inds: Vec<usize>,
+    pub send_neighbors: Vec<usize>,
+    pub send_length: Vec<usize>,
+    pub elements_to_send: Vec<usize>,
+}
+
+impl MatrixPowers {
+    /// Extend vectors of local values with their values in the ghost region, exchanging them all
+    /// in one message with each neighbouring processor.
+    ///
+    /// # Arguments
+    /// * `vectors` - The local values of each vector.
+    /// * `world` - The MPI world to communicate over.
+    pub fn exchange(&self, vectors: &[&[f64]], world: &impl Communicator) -> Vec<Vec<f64>> {
+        let mpi_my_tag = 201;
+        let nvec = vectors.len();
+
+        let mut send_buffer = Vec::with_capacity(self.elements_to_send.len() * nvec);
+        for &row in &self.elements_to_send {
+            send_buffer.extend(vectors.iter().map(|vector| vector[row]));
+        }
+        let mut recv_buffers: Vec<Vec<f64>> = self
+            .recv_length
+            .iter()
+            .map(|&length| vec![0.0; length * nvec])
+            .collect();
+
+        mpi::request::multiple_scope(self.neighbors.len(), |scope, coll| {
+            // Post receives first
+            for (i, recv_buffer) in recv_buffers.iter_mut().enumerate() {
+                let rreq = world
+                    .process_at_rank(self.neighbors[i] as i32)
+                    .immediate_receive_into_with_tag(scope, &mut recv_buffer[..], mpi_my_tag);
+                coll.add(rreq);
+            }
+
+            let mut start = 0;
+            for (i, &send_neighbor) in self.send_neighbors.iter().enumerate() {
+                let end = start + self.send_length[i] * nvec;
+                world
+                    .process_at_rank(send_neighbor as i32)
+                    .send_with_tag(&send_buffer[start..end], mpi_my_tag);
+                start = end;
+            }
+
+            while coll.incomplete() > 0 {
+                coll.wait_any().expect("MPI_Wait error");
+            }
+        });
+
+        let mut extended: Vec<Vec<f64>> = vectors
+            .iter()
+            .map(|vector| {
+                let mut extended = vector.to_vec();
+                extended.resize(self.level_ends[self.depth], 0.0);
+                extended
+            })
+            .collect();
+        let received = recv_buffers.iter().flat_map(|buffer| buffer.chunks(nvec));
+        for (&col, values) in self.recv_inds.iter().zip(received) {
+            for (extended, &value) in extended.iter_mut().zip(values) {
+                extended[col] = value;
+            }
+        }
+        extended
+    }
+
+    /// The vectors of a Krylov basis built from a vector with the recurrence
+    /// `vⱼ₊₁ = (A - θⱼI)vⱼ / σ`, which is the monomial basis when all of the shifts are zero and
+    /// the scale is one.
+    ///
+    /// # Arguments
+    /// * `vector` - The first vector of the basis, extended with its values in the ghost region.
+    /// * `shifts` - The shift `θⱼ` of each product, of which there can be at most `depth`.
+    /// * `scale` - The scale `σ` of each product.
+    ///
+    /// # Return values
+    /// * `basis` - The local values of each vector of the basis, starting with `vector`.
+    pub fn powers(&self, vector: &[f64], shifts: &[f64], scale: f64) -> Vec<Vec<f64>> {
+        assert!(shifts.len() <= self.depth);
+        let nrow = self.level_ends[0];
+        let mut basis = vec![vector.to_vec()];
+        for (step, &shift) in shifts.iter().enumerate() {
+            // Each product is valid on one level fewer than the vector it multiplies
+            let rows = self.level_ends[self.depth - step - 1];
+            let previous = basis.last().unwrap();
+            let next = (0..rows)
+                .into_par_iter()
+                .map(|row| {
+                    let start = self.row_start_inds[row];
+                    let end = start + self.nnz_in_row[row];
+                    let product: f64 = self.list_of_vals[start..end]
+                        .iter()
+                        .zip(self.list_of_inds[start..end].iter())
+                        .map(|(value, &col)| value * previous[col])
+                        .sum();
+                    (product - shift * previous[row]) / scale
+                })
+                .collect();
+            basis.push(next);
+        }
+        for vector in basis.iter_mut() {
+            vector.truncate(nrow);
+        }
+        basis
+    }
+}
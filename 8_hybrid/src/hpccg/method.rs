@@ -0,0 +1,107 @@
+use std::str::FromStr;
+
+use super::SolverConfig;
+
+/// The iterative method used to solve the system.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    /// The conjugate gradient method, for symmetric positive definite matrices.
+    ConjugateGradient,
+    /// The preconditioned conjugate gradient method, for symmetric positive definite matrices.
+    Pcg,
+    /// The biconjugate gradient stabilised method, for non-symmetric matrices.
+    BiCgStab,
+    /// The restarted generalised minimal residual method, for non-symmetric matrices.
+    Gmres,
+    /// The Chronopoulos-Gear conjugate gradient method, which needs one global reduction per
+    /// iteration instead of two.
+    ChronopoulosGear,
+    /// The s-step conjugate gradient method, which does several iterations for each exchange of
+    /// boundary values and global reduction.
+    SStepCg,
+    /// The pipelined conjugate gradient method of Ghysels and Vanroose, which overlaps its global
+    /// reduction with the sparse matrix-vector multiplication.
+    PipelinedCg,
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cg" => Ok(Method::ConjugateGradient),
+            "pcg" => Ok(Method::Pcg),
+            "bicgstab" => Ok(Method::BiCgStab),
+            "gmres" => Ok(Method::Gmres),
+            "chrongear" => Ok(Method::ChronopoulosGear),
+            "sstep" => Ok(Method::SStepCg),
+            "pipecg" => Ok(Method::PipelinedCg),
+            _ => Err(format!(
+                "Unknown solver `{s}`, expected one of `cg`, `pcg`, `bicgstab`, `gmres`, \
+                 `pipecg`, `chrongear` or `sstep`"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Method::ConjugateGradient => write!(f, "cg"),
+            Method::Pcg => write!(f, "pcg"),
+            Method::BiCgStab => write!(f, "bicgstab"),
+            Method::Gmres => write!(f, "gmres"),
+            Method::ChronopoulosGear => write!(f, "chrongear"),
+            Method::SStepCg => write!(f, "sstep"),
+            Method::PipelinedCg => write!(f, "pipecg"),
+        }
+    }
+}
+
+impl Method {
+    /// The number of calls to each of the `ddot`, `waxpby` and `sparsemv` kernels in an iteration
+    /// of the method, which the floating point operations of a run are counted from. For GMRES,
+    /// this is averaged over a whole restart cycle, as the cost of orthogonalisation grows with
+    /// each iteration of the cycle.
+    ///
+    /// # Arguments
+    /// * `config` - The settings of the solver.
+    pub fn kernel_calls(&self, config: &SolverConfig) -> [f64; 3] {
+        match self {
+            Method::ConjugateGradient => [2.0, 3.0, 1.0],
+            // The residual norm is found with a separate `ddot`, as `r·z` is not its square
+            Method::Pcg => [3.0, 3.0, 1.0],
+            // Both dot products are computed in one pass, and `Ap` is updated with a recurrence
+            Method::ChronopoulosGear => [2.0, 4.0, 1.0],
+            // `s·s` is also found, to check whether the half step has converged
+            Method::BiCgStab => [6.0, 6.0, 2.0],
+            Method::SStepCg => {
+                let s = config.step_size as f64;
+                // Per outer iteration of `s` iterations, the Gram matrix of the `2s + 1` basis
+                // vectors, `2s - 1` shifted products to build the basis, and recovering `x`, `r`
+                // and `p` from it, not counting the redundant products in the ghost region
+                let m = 2.0 * s + 1.0;
+                [
+                    m * (m + 1.0) / 2.0 / s,
+                    (3.0 * m + 2.0 * s - 1.0) / s,
+                    (2.0 * s - 1.0) / s,
+                ]
+            }
+            Method::Gmres => {
+                let restart = config.restart as f64;
+                let passes = if config.reorthogonalise { 2.0 } else { 1.0 };
+                // Gram-Schmidt against each vector of the basis so far, normalising each new
+                // vector, and finding the true residual and updating the solution on restart
+                let orthogonalisation = passes * (restart + 1.0) / 2.0;
+                [
+                    orthogonalisation + 1.0 + 1.0 / restart,
+                    orthogonalisation + 2.0 + 2.0 / restart,
+                    1.0 + 1.0 / restart,
+                ]
+            }
+            // Both dot products are fused into one reduction, and the recurrences for `Ar`, `Ap`
+            // and `As` take three more vector updates
+            Method::PipelinedCg => [2.0, 6.0, 1.0],
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::str::FromStr;
+
+use mpi::traits::*;
+
+use super::{
+    exchange_externals, make_local_matrix, sparsemv, waxpby, Chebyshev, Geometry, Preconditioner,
+    SparseMatrix, SymmetricGaussSeidel,
+};
+
+/// The number of steps of the Chebyshev iteration when it is the smoother of a level.
+const CHEBYSHEV_STEPS: usize = 4;
+
+/// The smoothers which can be chosen for the levels of multigrid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmootherKind {
+    /// A forward and a backward Gauss-Seidel sweep in a multicolour ordering.
+    SymmetricGaussSeidel,
+    /// A few steps of the Chebyshev iteration, which only exchanges external values.
+    Chebyshev,
+}
+
+impl FromStr for SmootherKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "symgs" => Ok(SmootherKind::SymmetricGaussSeidel),
+            "chebyshev" => Ok(SmootherKind::Chebyshev),
+            _ => Err(format!(
+                "Unknown smoother `{s}`, expected `symgs` or `chebyshev`"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for SmootherKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SmootherKind::SymmetricGaussSeidel => write!(f, "symgs"),
+            SmootherKind::Chebyshev => write!(f, "chebyshev"),
+        }
+    }
+}
+
+/// The smoother of one level of multigrid, which holds a copy of the matrix of its level.
+pub enum Smoother<'a, C: Communicator> {
+    SymmetricGaussSeidel(SymmetricGaussSeidel<'a, C>),
+    Chebyshev(Chebyshev<'a, C>),
+}
+
+impl<'a, C: Communicator> Smoother<'a, C> {
+    /// Create a smoother of a matrix, after it has been passed to `make_local_matrix`.
+    ///
+    /// # Arguments
+    /// * `kind` - The smoother to create.
+    /// * `matrix` - The matrix of the level.
+    /// * `world` - The MPI world to communicate over.
+    pub fn new(kind: SmootherKind, matrix: &SparseMatrix, world: &'a C) -> Self {
+        match kind {
+            SmootherKind::SymmetricGaussSeidel => {
+                Smoother::SymmetricGaussSeidel(SymmetricGaussSeidel::new(matrix, world))
+            }
+            SmootherKind::Chebyshev => {
+                Smoother::Chebyshev(Chebyshev::smoother(matrix, CHEBYSHEV_STEPS, world))
+            }
+        }
+    }
+
+    /// The matrix of the level.
+    pub fn matrix(&self) -> &SparseMatrix {
+        match self {
+            Smoother::SymmetricGaussSeidel(symgs) => &symgs.matrix,
+            Smoother::Chebyshev(chebyshev) => &chebyshev.matrix,
+        }
+    }
+
+    /// Improve an approximate solution `z` of `Az = r` on the level.
+    ///
+    /// # Arguments
+    /// * `r` - The right hand side vector.
+    /// * `z` - The approximate solution, with one entry for each local row of the matrix.
+    pub fn smooth(&mut self, r: &[f64], z: &mut Vec<f64>) {
+        match self {
+            Smoother::SymmetricGaussSeidel(symgs) => symgs.smooth(r, z),
+            Smoother::Chebyshev(chebyshev) => chebyshev.smooth(r, z),
+        }
+    }
+
+    /// Exchange the external values of a vector on the level, with the send buffer of its matrix.
+    fn exchange(&mut self, vector: &mut Vec<f64>) {
+        match self {
+            Smoother::SymmetricGaussSeidel(symgs) => {
+                exchange_externals(&mut symgs.matrix, vector, symgs.world)
+            }
+            Smoother::Chebyshev(chebyshev) => {
+                exchange_externals(&mut chebyshev.matrix, vector, chebyshev.world)
+            }
+        }
+    }
+}
+
+/// The geometric multigrid preconditioner of HPCG, which is one V-cycle over a hierarchy of
+/// meshes, each with half as many points in each dimension as the one before it.
+///
+/// The coarse matrices are generated from the same stencil as the matrix, on the coarser meshes.
+/// The residual is restricted to a coarser mesh by injection, taking the values at the fine points
+/// which are also coarse points, and the correction is prolongated back to the same points.
+/// Each level is smoothed with a symmetric Gauss-Seidel sweep or a few Chebyshev steps before and
+/// after the correction, which keeps the preconditioner symmetric.
+///
+/// Each processor coarsens its own brick of the mesh, so every level has the same grid of
+/// processors, and the matrix of each coarse level has its own exchange of external values.
+///
+/// # Fields
+/// * `levels` - The smoother of each level, from the finest to the coarsest, which holds a copy of
+///   the matrix of its level.
+/// * `injections` - The fine row of each coarse row, between each level and the next.
+pub struct Multigrid<'a, C: Communicator> {
+    pub levels: Vec<Smoother<'a, C>>,
+    pub injections: Vec<Vec<usize>>,
+}
+
+impl<'a, C: Communicator> Multigrid<'a, C> {
+    /// Create the multigrid hierarchy of a matrix generated from a stencil, after it has been
+    /// passed to `make_local_matrix`, coarsening until there are `levels` levels or a dimension of
+    /// the local mesh has an odd size.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix to precondition.
+    /// * `levels` - The maximum number of levels, including the matrix itself.
+    /// * `smoother` - The smoother of every level.
+    /// * `world` - The MPI world to communicate over.
+    ///
+    /// # Return values
+    /// * `multigrid` - The preconditioner, or an error if the matrix has no mesh to coarsen.
+    pub fn new(
+        matrix: &SparseMatrix,
+        levels: usize,
+        smoother: SmootherKind,
+        world: &'a C,
+    ) -> Result<Self, String> {
+        let Some(mut fine) = matrix.geometry else {
+            return Err("Multigrid needs a matrix generated from a stencil".to_string());
+        };
+        let mut smoothers = vec![Smoother::new(smoother, matrix, world)];
+        let mut injections = vec![];
+        while smoothers.len() < levels {
+            let Some(coarse) = fine.coarsen() else {
+                break;
+            };
+            let (mut coarse_matrix, _, _, _) = SparseMatrix::generate_matrix_on_grid(
+                coarse.nx,
+                coarse.ny,
+                coarse.nz,
+                coarse.stencil,
+                coarse.grid,
+                coarse.rank,
+            );
+            make_local_matrix(&mut coarse_matrix, world);
+            smoothers.push(Smoother::new(smoother, &coarse_matrix, world));
+            injections.push(injection(&fine, &coarse));
+            fine = coarse;
+        }
+        Ok(Multigrid {
+            levels: smoothers,
+            injections,
+        })
+    }
+}
+
+impl<C: Communicator> Preconditioner for Multigrid<'_, C> {
+    fn apply(&mut self, r: &[f64]) -> Vec<f64> {
+        v_cycle(&mut self.levels, &self.injections, r)
+    }
+}
+
+/// The local fine row of each point of a coarse brick, which is the point with twice its
+/// coordinates.
+fn injection(fine: &Geometry, coarse: &Geometry) -> Vec<usize> {
+    let mut rows = Vec::with_capacity(coarse.nx * coarse.ny * coarse.nz);
+    for iz in 0..coarse.nz {
+        for iy in 0..coarse.ny {
+            for ix in 0..coarse.nx {
+                rows.push(2 * iz * fine.nx * fine.ny + 2 * iy * fine.nx + 2 * ix);
+            }
+        }
+    }
+    rows
+}
+
+/// Approximately solve `Az = r` on the first of the levels with a V-cycle: smooth, correct with
+/// the approximate solution of the restricted residual on the coarser levels, and smooth again.
+///
+/// # Arguments
+/// * `levels` - The smoothers of this level and the coarser ones.
+/// * `injections` - The fine row of each coarse row, between each level and the next.
+/// * `r` - The right hand side vector on this level.
+fn v_cycle<C: Communicator>(
+    levels: &mut [Smoother<'_, C>],
+    injections: &[Vec<usize>],
+    r: &[f64],
+) -> Vec<f64> {
+    let (smoother, coarser) = levels.split_first_mut().unwrap();
+    let nrow = smoother.matrix().local_nrow;
+    let mut z = vec![0.0; nrow];
+    smoother.smooth(r, &mut z);
+    // The coarsest level is only smoothed
+    let Some((injection, coarser_injections)) = injections.split_first() else {
+        return z;
+    };
+
+    smoother.exchange(&mut z);
+    let residual = waxpby(nrow, 1.0, r, -1.0, &sparsemv(smoother.matrix(), &z));
+    z.truncate(nrow);
+    let coarse_r: Vec<f64> = injection.iter().map(|&row| residual[row]).collect();
+    let correction = v_cycle(coarser, coarser_injections, &coarse_r);
+    for (&row, value) in injection.iter().zip(correction) {
+        z[row] += value;
+    }
+    smoother.smooth(r, &mut z);
+    z
+}
@@ -0,0 +1,183 @@
+use mpi::traits::*;
+
+use super::{
+    ddot, exchange_externals, mytimer, sparsemv, tick, tock, waxpby, ConvergenceReason,
+    IterationInfo, Preconditioner, SolveReport, SolverConfig, SparseMatrix, StoppingCriteria,
+    Timings, Verbosity,
+};
+
+/// A method to compute the approximate solution to `Ax = b` with the preconditioned conjugate
+/// gradient method, where the search directions are built from the preconditioned residual
+/// `z = M⁻¹ r` instead of the residual itself.
+///
+/// # Arguments
+/// * `A` - The input sparse matrix.
+/// * `b` - The known right hand side vector.
+/// * `x` - The current approximate solution, which starts as the initial guess.
+/// * `preconditioner` - The preconditioner `M`, which must be symmetric positive definite.
+/// * `config` - The settings of the solver.
+/// * `world` - The MPI world to communicate over.
+///
+/// # Return values
+/// * `report` - The approximate result, and how the solver got there.
+#[allow(non_snake_case)]
+pub fn pcg(
+    A: &mut SparseMatrix,
+    b: &[f64],
+    x: &[f64],
+    preconditioner: &mut dyn Preconditioner,
+    config: &mut SolverConfig,
+    world: &impl Communicator,
+) -> SolveReport {
+    let t_begin: f64 = mytimer();
+    let mut t_total: f64 = 0.0;
+    let mut t_ddot: f64 = 0.0;
+    let mut t_waxpby: f64 = 0.0;
+    let mut t_sparsemv: f64 = 0.0;
+    let mut t_precondition: f64 = 0.0;
+    let mut t_mpi_allreduce: f64 = 0.0;
+    let mut t_mpi_exchange: f64 = 0.0;
+
+    let nrow = A.local_nrow;
+
+    let mut result = x.to_owned();
+    let mut iteration = 0;
+    let mut p = vec![0.0; nrow];
+    let mut rtrans: f64 = 0.0;
+
+    let rank = world.rank();
+
+    let max_iterations = config.max_iterations;
+
+    // `x` is of length `nrow`, so copy it to a vector which can be extended with the external
+    // values for sparse matrix-vector operation
+    tick(&mut t_total);
+    let mut x_ext = waxpby(nrow, 1.0, &result, 0.0, b);
+    tock(&t_total, &mut t_waxpby);
+
+    tick(&mut t_total);
+    exchange_externals(A, &mut x_ext, world);
+    tock(&t_total, &mut t_mpi_exchange);
+
+    tick(&mut t_total);
+    let Ax = sparsemv(A, &x_ext);
+    tock(&t_total, &mut t_sparsemv);
+
+    tick(&mut t_total);
+    let mut r = waxpby(nrow, 1.0, b, -1.0, &Ax);
+    tock(&t_total, &mut t_waxpby);
+
+    tick(&mut t_total);
+    let mut normr = ddot(nrow, &r, &r, &mut t_mpi_allreduce, world).sqrt();
+    tock(&t_total, &mut t_ddot);
+
+    if rank == 0 && config.verbosity > Verbosity::Quiet {
+        println!("Initial Residual = {normr:+.5e}");
+    }
+    let flow = config.notify(IterationInfo {
+        iteration: 0,
+        residual: normr,
+        alpha: None,
+        beta: None,
+        elapsed: mytimer() - t_begin,
+    });
+
+    tick(&mut t_total);
+    let mut criteria = StoppingCriteria::new(config, normr, || {
+        ddot(nrow, b, b, &mut t_mpi_allreduce, world).sqrt()
+    });
+    tock(&t_total, &mut t_ddot);
+    let mut reason = criteria.check(0, normr, flow);
+
+    for k in 1..max_iterations {
+        if reason.is_some() {
+            break;
+        }
+
+        tick(&mut t_total);
+        let z = preconditioner.apply(&r);
+        tock(&t_total, &mut t_precondition);
+
+        let oldrtrans = rtrans;
+        tick(&mut t_total);
+        rtrans = ddot(nrow, &r, &z, &mut t_mpi_allreduce, world);
+        tock(&t_total, &mut t_ddot);
+
+        tick(&mut t_total);
+        p = if k == 1 {
+            z
+        } else {
+            waxpby(nrow, 1.0, &z, rtrans / oldrtrans, &p)
+        };
+        tock(&t_total, &mut t_waxpby);
+
+        tick(&mut t_total);
+        exchange_externals(A, &mut p, world);
+        tock(&t_total, &mut t_mpi_exchange);
+
+        tick(&mut t_total);
+        let Ap = sparsemv(A, &p);
+        tock(&t_total, &mut t_sparsemv);
+
+        tick(&mut t_total);
+        let pAp = ddot(nrow, &p, &Ap, &mut t_mpi_allreduce, world);
+        tock(&t_total, &mut t_ddot);
+
+        // The method cannot take a step along a direction without positive curvature
+        if pAp.is_nan() || pAp <= 0.0 {
+            reason = Some(ConvergenceReason::Breakdown);
+            break;
+        }
+        let alpha = rtrans / pAp;
+
+        tick(&mut t_total);
+        let next = waxpby(nrow, 1.0, &result, alpha, &p);
+        let previous = std::mem::replace(&mut result, next);
+        r = waxpby(nrow, 1.0, &r, -alpha, &Ap);
+        tock(&t_total, &mut t_waxpby);
+
+        // Unlike the unpreconditioned method, `r·z` is not the squared residual, so it is found
+        // separately to check for convergence
+        let previous_normr = normr;
+        tick(&mut t_total);
+        normr = ddot(nrow, &r, &r, &mut t_mpi_allreduce, world).sqrt();
+        tock(&t_total, &mut t_ddot);
+        iteration = k;
+
+        if rank == 0 && config.should_print(k) {
+            println!("Iteration = {k} , Residual = {normr:+.5e}");
+        }
+        let flow = config.notify(IterationInfo {
+            iteration: k,
+            residual: normr,
+            alpha: Some(alpha),
+            beta: (k > 1).then(|| rtrans / oldrtrans),
+            elapsed: mytimer() - t_begin,
+        });
+        reason = criteria.check(k, normr, flow);
+        // A step which broke down or diverged is undone, so the last good iterate is returned
+        if reason.is_some_and(ConvergenceReason::is_failure) {
+            (result, normr, iteration) = (previous, previous_normr, k - 1);
+        }
+    }
+
+    let reason = reason.unwrap_or(ConvergenceReason::MaxIterations);
+
+    SolveReport {
+        solution: result,
+        iterations: iteration,
+        final_residual: normr,
+        reason,
+        residual_history: config.take_residual_history(),
+        times: Timings {
+            total: mytimer() - t_begin,
+            ddot: t_ddot,
+            waxpby: t_waxpby,
+            sparsemv: t_sparsemv,
+            allreduce: t_mpi_allreduce,
+            exchange: t_mpi_exchange,
+            reduction_wait: 0.0,
+            precondition: t_precondition,
+        },
+    }
+}
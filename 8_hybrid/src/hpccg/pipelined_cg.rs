@@ -0,0 +1,226 @@
+use mpi::collective::SystemOperation;
+use mpi::traits::*;
+use rayon::prelude::*;
+
+use super::{
+    ddot, exchange_externals, mytimer, sparsemv, tick, tock, waxpby, ConvergenceReason,
+    IterationInfo, SolveReport, SolverConfig, SparseMatrix, StoppingCriteria, Timings, Verbosity,
+};
+
+/// A method to compute the approximate solution to `Ax = b` with the pipelined conjugate gradient
+/// method of Ghysels and Vanroose, which is mathematically equivalent to the conjugate gradient
+/// solver, but hides the latency of its global reductions.
+///
+/// The two dot products of each iteration are merged into a single non-blocking `MPI_Iallreduce`,
+/// which is in flight while the boundary exchange and sparse matrix-vector multiplication of the
+/// next search direction run. The recurrences for `w = Ar`, `s = Ap` and `z = As` replace the
+/// matrix-vector products of the conjugate gradient solver, at the cost of three more vector
+/// updates per iteration and a residual which can drift further from `b - Ax` in floating point.
+///
+/// # Arguments
+/// * `A` - The input sparse matrix.
+/// * `b` - The known right hand side vector.
+/// * `x` - The current approximate solution, which starts as the initial guess.
+/// * `config` - The settings of the solver.
+/// * `world` - The MPI world to communicate over.
+///
+/// # Return values
+/// * `report` - The approximate result, and how the solver got there.
+#[allow(non_snake_case)]
+pub fn pipelined_cg(
+    A: &mut SparseMatrix,
+    b: &[f64],
+    x: &[f64],
+    config: &mut SolverConfig,
+    world: &impl Communicator,
+) -> SolveReport {
+    let t_begin: f64 = mytimer();
+    let mut t_total: f64 = 0.0;
+    let mut t_ddot: f64 = 0.0;
+    let mut t_waxpby: f64 = 0.0;
+    let mut t_sparsemv: f64 = 0.0;
+    let mut t_mpi_allreduce: f64 = 0.0;
+    let mut t_mpi_exchange: f64 = 0.0;
+    let mut t_reduction_wait: f64 = 0.0;
+
+    let nrow = A.local_nrow;
+
+    let mut result = x.to_owned();
+    let mut iteration = 0;
+
+    let rank = world.rank();
+
+    let max_iterations = config.max_iterations;
+
+    // `x` is of length `nrow`, so copy it to a vector which can be extended with the external
+    // values for sparse matrix-vector operation
+    tick(&mut t_total);
+    let mut x_ext = waxpby(nrow, 1.0, &result, 0.0, b);
+    tock(&t_total, &mut t_waxpby);
+
+    tick(&mut t_total);
+    exchange_externals(A, &mut x_ext, world);
+    tock(&t_total, &mut t_mpi_exchange);
+
+    tick(&mut t_total);
+    let Ax = sparsemv(A, &x_ext);
+    tock(&t_total, &mut t_sparsemv);
+
+    tick(&mut t_total);
+    let mut r = waxpby(nrow, 1.0, b, -1.0, &Ax);
+    tock(&t_total, &mut t_waxpby);
+
+    tick(&mut t_total);
+    let mut r_ext = waxpby(nrow, 1.0, &r, 0.0, &r);
+    tock(&t_total, &mut t_waxpby);
+
+    tick(&mut t_total);
+    exchange_externals(A, &mut r_ext, world);
+    tock(&t_total, &mut t_mpi_exchange);
+
+    tick(&mut t_total);
+    let mut w = sparsemv(A, &r_ext);
+    tock(&t_total, &mut t_sparsemv);
+
+    tick(&mut t_total);
+    let mut normr = ddot(nrow, &r, &r, &mut t_mpi_allreduce, world).sqrt();
+    tock(&t_total, &mut t_ddot);
+
+    if rank == 0 && config.verbosity > Verbosity::Quiet {
+        println!("Initial Residual = {normr:+.5e}");
+    }
+    let flow = config.notify(IterationInfo {
+        iteration: 0,
+        residual: normr,
+        alpha: None,
+        beta: None,
+        elapsed: mytimer() - t_begin,
+    });
+
+    tick(&mut t_total);
+    let mut criteria = StoppingCriteria::new(config, normr, || {
+        ddot(nrow, b, b, &mut t_mpi_allreduce, world).sqrt()
+    });
+    tock(&t_total, &mut t_ddot);
+    let mut reason = criteria.check(0, normr, flow);
+
+    let mut p = vec![0.0; nrow];
+    let mut s = vec![0.0; nrow];
+    let mut z = vec![0.0; nrow];
+    let (mut old_gamma, mut old_alpha, mut old_beta) = (0.0, 0.0, None);
+    let (mut previous, mut previous_normr) = (result.clone(), normr);
+
+    // The reduction of each pass finds the residual of the step before it, so there is one more
+    // pass than there are steps, which only checks the residual of the last step
+    for k in 1..=max_iterations {
+        if reason.is_some() {
+            break;
+        }
+
+        // Both of the dot products `r·r` and `w·r` are reduced at once
+        tick(&mut t_total);
+        let local: [f64; 2] = r
+            .par_iter()
+            .zip(w.par_iter())
+            .map(|(r, w)| [r * r, w * r])
+            .reduce(|| [0.0, 0.0], |a, b| [a[0] + b[0], a[1] + b[1]]);
+        tock(&t_total, &mut t_ddot);
+
+        let mut global: [f64; 2] = [0.0; 2];
+        let mut w_ext = w.clone();
+        let q = mpi::request::scope(|scope| {
+            tick(&mut t_total);
+            let reduction =
+                world.immediate_all_reduce_into(scope, &local, &mut global, SystemOperation::sum());
+            tock(&t_total, &mut t_mpi_allreduce);
+
+            // The next search direction is multiplied while the reduction is in flight
+            tick(&mut t_total);
+            exchange_externals(A, &mut w_ext, world);
+            tock(&t_total, &mut t_mpi_exchange);
+
+            tick(&mut t_total);
+            let q = sparsemv(A, &w_ext);
+            tock(&t_total, &mut t_sparsemv);
+
+            tick(&mut t_total);
+            reduction.wait();
+            tock(&t_total, &mut t_reduction_wait);
+            q
+        });
+        let [gamma, delta] = global;
+
+        // The first pass finds the initial residual, which has already been checked
+        if k > 1 {
+            normr = gamma.sqrt();
+            if rank == 0 && config.should_print(iteration) {
+                println!("Iteration = {iteration} , Residual = {normr:+.5e}");
+            }
+            let flow = config.notify(IterationInfo {
+                iteration,
+                residual: normr,
+                alpha: Some(old_alpha),
+                beta: old_beta,
+                elapsed: mytimer() - t_begin,
+            });
+            reason = criteria.check(iteration, normr, flow);
+            // A step which broke down or diverged is undone, so the last good iterate is returned
+            if reason.is_some_and(ConvergenceReason::is_failure) {
+                (result, normr, iteration) = (previous, previous_normr, iteration - 1);
+                break;
+            }
+        }
+        if reason.is_some() || k == max_iterations {
+            break;
+        }
+
+        // `p·Ap`, which follows from `Ar·r` without another reduction
+        let (pAp, beta) = if k == 1 {
+            (delta, 0.0)
+        } else {
+            let beta = gamma / old_gamma;
+            (delta - beta * gamma / old_alpha, beta)
+        };
+        // The method cannot take a step along a direction without positive curvature
+        if pAp.is_nan() || pAp <= 0.0 {
+            reason = Some(ConvergenceReason::Breakdown);
+            break;
+        }
+        let alpha = gamma / pAp;
+
+        tick(&mut t_total);
+        z = waxpby(nrow, 1.0, &q, beta, &z);
+        s = waxpby(nrow, 1.0, &w, beta, &s);
+        p = waxpby(nrow, 1.0, &r, beta, &p);
+        let next = waxpby(nrow, 1.0, &result, alpha, &p);
+        previous = std::mem::replace(&mut result, next);
+        r = waxpby(nrow, 1.0, &r, -alpha, &s);
+        w = waxpby(nrow, 1.0, &w, -alpha, &z);
+        tock(&t_total, &mut t_waxpby);
+
+        previous_normr = normr;
+        (old_gamma, old_alpha, old_beta) = (gamma, alpha, (k > 1).then_some(beta));
+        iteration = k;
+    }
+
+    let reason = reason.unwrap_or(ConvergenceReason::MaxIterations);
+
+    SolveReport {
+        solution: result,
+        iterations: iteration,
+        final_residual: normr,
+        reason,
+        residual_history: config.take_residual_history(),
+        times: Timings {
+            total: mytimer() - t_begin,
+            ddot: t_ddot,
+            waxpby: t_waxpby,
+            sparsemv: t_sparsemv,
+            // Waiting for the non-blocking reduction is also time spent in the allreduce
+            allreduce: t_mpi_allreduce + t_reduction_wait,
+            exchange: t_mpi_exchange,
+            reduction_wait: t_reduction_wait,
+            precondition: 0.0,
+        },
+    }
+}
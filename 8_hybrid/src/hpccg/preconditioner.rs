@@ -0,0 +1,125 @@
+use std::str::FromStr;
+
+use mpi::traits::*;
+
+use super::{
+    Chebyshev, IncompleteCholesky, Multigrid, SmootherKind, SparseMatrix, SymmetricGaussSeidel,
+};
+
+/// An approximation `M` of a matrix which is cheap to invert, used by the preconditioned conjugate
+/// gradient solver to reduce the number of iterations it needs. It must be symmetric positive
+/// definite for the solver to converge. Applying it may communicate with the other processors, so
+/// it must be applied by all of them together.
+pub trait Preconditioner {
+    /// Applies the inverse of the preconditioner to a residual.
+    ///
+    /// # Arguments
+    /// * `r` - The residual, with one entry for each local row of the matrix.
+    ///
+    /// # Return values
+    /// * `z` - The preconditioned residual, `z = M⁻¹ r`.
+    fn apply(&mut self, r: &[f64]) -> Vec<f64>;
+}
+
+/// The preconditioners which can be chosen for the preconditioned conjugate gradient solver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreconditionerKind {
+    /// The diagonal of the matrix.
+    Jacobi,
+    /// A forward and a backward Gauss-Seidel sweep in a multicolour ordering.
+    SymmetricGaussSeidel,
+    /// The incomplete Cholesky factorisation with zero fill-in.
+    IncompleteCholesky,
+    /// A few steps of the Chebyshev iteration, which needs no dot products.
+    Chebyshev,
+    /// A geometric multigrid V-cycle, for matrices generated from a stencil.
+    Multigrid,
+}
+
+impl FromStr for PreconditionerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jacobi" => Ok(PreconditionerKind::Jacobi),
+            "symgs" => Ok(PreconditionerKind::SymmetricGaussSeidel),
+            "ic0" => Ok(PreconditionerKind::IncompleteCholesky),
+            "chebyshev" => Ok(PreconditionerKind::Chebyshev),
+            "mg" => Ok(PreconditionerKind::Multigrid),
+            _ => Err(format!(
+                "Unknown preconditioner `{s}`, expected `jacobi`, `symgs`, `ic0`, `chebyshev` \
+                 or `mg`"
+            )),
+        }
+    }
+}
+
+impl PreconditionerKind {
+    /// Build the preconditioner of a matrix, with the four levels of HPCG for multigrid, and four
+    /// steps of the Chebyshev iteration.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix to precondition.
+    /// * `smoother` - The smoother of the levels of multigrid.
+    /// * `world` - The MPI world to communicate over.
+    ///
+    /// # Return values
+    /// * `preconditioner` - The preconditioner, or an error if it cannot be built for the matrix.
+    pub fn build<'a>(
+        &self,
+        matrix: &SparseMatrix,
+        smoother: SmootherKind,
+        world: &'a impl Communicator,
+    ) -> Result<Box<dyn Preconditioner + 'a>, String> {
+        Ok(match self {
+            PreconditionerKind::Jacobi => Box::new(Jacobi::new(matrix)),
+            PreconditionerKind::SymmetricGaussSeidel => {
+                Box::new(SymmetricGaussSeidel::new(matrix, world))
+            }
+            PreconditionerKind::IncompleteCholesky => Box::new(IncompleteCholesky::new(matrix)?),
+            PreconditionerKind::Chebyshev => Box::new(Chebyshev::new(matrix, 4, world)),
+            PreconditionerKind::Multigrid => Box::new(Multigrid::new(matrix, 4, smoother, world)?),
+        })
+    }
+}
+
+impl std::fmt::Display for PreconditionerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PreconditionerKind::Jacobi => write!(f, "jacobi"),
+            PreconditionerKind::SymmetricGaussSeidel => write!(f, "symgs"),
+            PreconditionerKind::IncompleteCholesky => write!(f, "ic0"),
+            PreconditionerKind::Chebyshev => write!(f, "chebyshev"),
+            PreconditionerKind::Multigrid => write!(f, "mg"),
+        }
+    }
+}
+
+/// The Jacobi preconditioner, which is the diagonal of the matrix.
+///
+/// # Fields
+/// * `inverse_diagonal` - The reciprocal of the diagonal entry of each row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jacobi {
+    pub inverse_diagonal: Vec<f64>,
+}
+
+impl Jacobi {
+    /// Create the Jacobi preconditioner of a matrix, which must have a non-zero diagonal.
+    ///
+    /// # Arguments
+    /// * `matrix` - The matrix to precondition.
+    pub fn new(matrix: &SparseMatrix) -> Self {
+        let inverse_diagonal = matrix.diagonal().iter().map(|d| 1.0 / d).collect();
+        Jacobi { inverse_diagonal }
+    }
+}
+
+impl Preconditioner for Jacobi {
+    fn apply(&mut self, r: &[f64]) -> Vec<f64> {
+        r.iter()
+            .zip(self.inverse_diagonal.iter())
+            .map(|(r, d)| r * d)
+            .collect()
+    }
+}
@@ -0,0 +1,164 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::{FromStr, SplitWhitespace};
+
+use mpi::traits::*;
+
+use super::SparseMatrix;
+
+/// Parse the next whitespace separated value from the contents of an HPC data file.
+fn next_value<T: FromStr>(tokens: &mut SplitWhitespace) -> io::Result<T> {
+    let token = tokens.next().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::UnexpectedEof, "Unexpected end of HPC data file")
+    })?;
+    token.parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Failed to parse `{token}` in HPC data file"),
+        )
+    })
+}
+
+impl SparseMatrix {
+    /// Reads this processor's rows of a sparse matrix and its associated vectors from an HPC
+    /// data file.
+    ///
+    /// The file is laid out as whitespace separated values, in the same format as read by
+    /// `read_HPC_row` in the reference implementation:
+    ///  * The total number of rows, and the total number of non-zeroes.
+    ///  * The number of non-zeroes in each row.
+    ///  * For each row, its number of non-zeroes followed by a `value index` pair per non-zero.
+    ///  * For each row, a `guess rhs exact` triple.
+    ///
+    /// Every processor reads the whole file, and keeps a contiguous block of rows, with the
+    /// remainder rows spread one each over the lowest ranks. The column indices are left as
+    /// global indices, to be transformed by `make_local_matrix`.
+    ///
+    /// # Arguments
+    ///  * `data_file` - Path to the HPC data file.
+    ///  * `world` - The MPI world to partition the rows over.
+    ///
+    /// # Return values
+    ///  * `matrix` - Sparse matrix read from the file.
+    ///  * `guess` - Inital guess for the mesh.
+    ///  * `rhs` - Right hand side.
+    ///  * `exact` - Exact solution (as computed by a direct solver).
+    #[allow(clippy::type_complexity)]
+    pub fn read_hpc_row(
+        data_file: impl AsRef<Path>,
+        world: &impl Communicator,
+    ) -> io::Result<(Self, Vec<f64>, Vec<f64>, Vec<f64>)> {
+        let size = world.size() as usize;
+        let rank = world.rank() as usize;
+
+        let contents = fs::read_to_string(data_file)?;
+        let mut tokens = contents.split_whitespace();
+
+        let total_nrow: usize = next_value(&mut tokens)?;
+        let total_nnz: usize = next_value(&mut tokens)?;
+
+        // Each processor gets a contiguous block of rows
+        let chunksize = total_nrow / size;
+        let remainder = total_nrow % size;
+        let local_nrow = if rank < remainder { chunksize + 1 } else { chunksize };
+        if local_nrow == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("HPC data file has too few rows to give one to each of {size} processors"),
+            ));
+        }
+        let start_row = rank * chunksize + rank.min(remainder);
+        let stop_row = start_row + local_nrow - 1;
+        let local_ncol = local_nrow;
+        let is_local = |row: usize| start_row <= row && row <= stop_row;
+
+        // The number of non-zero numbers in each row
+        let mut nnz_in_row = Vec::with_capacity(local_nrow);
+        for row in 0..total_nrow {
+            let cur_nnz: usize = next_value(&mut tokens)?;
+            if is_local(row) {
+                nnz_in_row.push(cur_nnz);
+            }
+        }
+        let local_nnz = nnz_in_row.iter().sum();
+
+        // The index of the start of each row into `list_of_vals` and `list_of_inds`
+        let mut row_start_inds: Vec<usize> = Vec::with_capacity(local_nrow);
+        let mut list_of_vals: Vec<f64> = Vec::with_capacity(local_nnz);
+        let mut list_of_inds: Vec<i32> = Vec::with_capacity(local_nnz);
+
+        for row in 0..total_nrow {
+            let cur_nnz: usize = next_value(&mut tokens)?;
+            if is_local(row) {
+                let expected_nnz = nnz_in_row[row - start_row];
+                if cur_nnz != expected_nnz {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Row has {cur_nnz} non-zeroes, but {expected_nnz} were declared"),
+                    ));
+                }
+                row_start_inds.push(list_of_vals.len());
+            }
+            for _ in 0..cur_nnz {
+                let val: f64 = next_value(&mut tokens)?;
+                let ind: usize = next_value(&mut tokens)?;
+                if ind >= total_nrow {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Column index {ind} is out of bounds for {total_nrow} columns"),
+                    ));
+                }
+                if is_local(row) {
+                    list_of_vals.push(val);
+                    list_of_inds.push(ind as i32);
+                }
+            }
+        }
+
+        // Output data other than the sparse matrix
+        let mut guess: Vec<f64> = Vec::with_capacity(local_nrow);
+        let mut rhs: Vec<f64> = Vec::with_capacity(local_nrow);
+        let mut exact: Vec<f64> = Vec::with_capacity(local_nrow);
+        for row in 0..total_nrow {
+            let (cur_guess, cur_rhs, cur_exact) = (
+                next_value(&mut tokens)?,
+                next_value(&mut tokens)?,
+                next_value(&mut tokens)?,
+            );
+            if is_local(row) {
+                guess.push(cur_guess);
+                rhs.push(cur_rhs);
+                exact.push(cur_exact);
+            }
+        }
+
+        let matrix = SparseMatrix {
+            start_row,
+            stop_row,
+            local_nrow,
+            total_nnz,
+            total_nrow,
+            local_ncol,
+            local_nnz,
+            nnz_in_row,
+            row_start_inds,
+            list_of_vals,
+            list_of_inds,
+            geometry: None,
+            // ===== MPI only ===== //
+            is_local: false,
+            num_external: 0,
+            num_send_neighbors: 0,
+            external_index: vec![],
+            external_local_index: vec![],
+            total_to_be_sent: 0,
+            elements_to_send: vec![],
+            neighbors: vec![],
+            recv_length: vec![],
+            send_length: vec![],
+            send_buffer: vec![],
+        };
+        Ok((matrix, guess, rhs, exact))
+    }
+}
@@ -0,0 +1,429 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{
+    ConvergenceReason, Method, PreconditionerKind, SmootherKind, Timings, YamlDoc, YamlValue,
+};
+
+/// The formats the results of a run can be written in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Yaml,
+    Json,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "yaml" => Ok(OutputFormat::Yaml),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!(
+                "Unknown output format `{s}`, expected one of `yaml`, `json` or `csv`"
+            )),
+        }
+    }
+}
+
+/// The results of a run of the benchmark, as reported by the root processor.
+///
+/// # Fields
+/// * `num_ranks` - The number of MPI processes.
+/// * `num_threads` - The number of Rayon threads per process.
+/// * `dimensions` - The size of each processor's sub-block, if the matrix was generated.
+/// * `process_grid` - The arrangement of the processors' sub-blocks, if the matrix was generated.
+/// * `data_file` - The file the matrix was read from, if it was not generated.
+/// * `method` - The iterative method used to solve the system.
+/// * `preconditioner` - The preconditioner of the method, if it is preconditioned.
+/// * `smoother` - The smoother of the levels of multigrid, if it is the preconditioner.
+/// * `kernel_calls` - The number of calls to each of the `ddot`, `waxpby` and `sparsemv` kernels
+///   in an iteration of the method.
+/// * `total_nrow` - The total number of rows in the matrix.
+/// * `total_nnz` - The total number of non-zeroes in the matrix.
+/// * `iterations` - The number of iterations for which the solver ran.
+/// * `final_residual` - The residual at the end of the solver loop.
+/// * `reason` - Why the solver stopped iterating.
+/// * `difference` - The difference between the computed and exact solutions.
+/// * `times` - The times spent in each operation of the solver.
+/// * `make_local_matrix_time` - The time spent setting up the local matrix.
+/// * `allreduce_min` - The minimum time spent in the DDOT allreduce over all processors.
+/// * `allreduce_max` - The maximum time spent in the DDOT allreduce over all processors.
+/// * `allreduce_avg` - The average time spent in the DDOT allreduce over all processors.
+/// * `reduction_wait_min` - The minimum time spent waiting for the non-blocking allreduce over all
+///   processors.
+/// * `reduction_wait_max` - The maximum time spent waiting for the non-blocking allreduce over all
+///   processors.
+/// * `reduction_wait_avg` - The average time spent waiting for the non-blocking allreduce over all
+///   processors.
+///
+/// The time waiting for the non-blocking allreduce is also counted in the allreduce times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    pub num_ranks: i32,
+    pub num_threads: usize,
+    pub dimensions: Option<(usize, usize, usize)>,
+    pub process_grid: Option<(usize, usize, usize)>,
+    pub data_file: Option<String>,
+    pub method: Method,
+    pub preconditioner: Option<PreconditionerKind>,
+    pub smoother: Option<SmootherKind>,
+    pub kernel_calls: [f64; 3],
+    pub total_nrow: usize,
+    pub total_nnz: usize,
+    pub iterations: i32,
+    pub final_residual: f64,
+    pub reason: ConvergenceReason,
+    pub difference: f64,
+    pub times: Timings,
+    pub make_local_matrix_time: f64,
+    pub allreduce_min: f64,
+    pub allreduce_max: f64,
+    pub allreduce_avg: f64,
+    pub reduction_wait_min: f64,
+    pub reduction_wait_max: f64,
+    pub reduction_wait_avg: f64,
+}
+
+impl RunSummary {
+    /// The number of floating point operations in each kernel.
+    ///
+    /// # Return values
+    /// * `flops` - The operation counts (total/ddot/waxpby/sparsemv).
+    pub fn flops(&self) -> [f64; 4] {
+        let iterations = self.iterations as f64;
+        let [ddot_calls, waxpby_calls, sparsemv_calls] = self.kernel_calls;
+        let ddot_flops = iterations * 2.0 * ddot_calls * self.total_nrow as f64;
+        let waxpby_flops = iterations * 2.0 * waxpby_calls * self.total_nrow as f64;
+        let sparsemv_flops = iterations * 2.0 * sparsemv_calls * self.total_nnz as f64;
+        [
+            ddot_flops + waxpby_flops + sparsemv_flops,
+            ddot_flops,
+            waxpby_flops,
+            sparsemv_flops,
+        ]
+    }
+
+    /// The rate of floating point operations in each kernel, in millions per second.
+    ///
+    /// # Return values
+    /// * `mflops` - The operation rates (total/ddot/waxpby/sparsemv).
+    pub fn mflops(&self) -> [f64; 4] {
+        let flops = self.flops();
+        let times = [
+            self.times.total,
+            self.times.ddot,
+            self.times.waxpby,
+            self.times.sparsemv,
+        ];
+        std::array::from_fn(|i| flops[i] / times[i] / 1.0e6)
+    }
+
+    /// The sparse matrix-vector multiplication time including the parallel overheads of the
+    /// boundary exchange and setting up the local matrix.
+    fn total_sparsemv_time(&self) -> f64 {
+        self.times.sparsemv + self.times.exchange + self.make_local_matrix_time
+    }
+
+    /// The values of the summary as named fields, in a fixed order. New fields are only ever
+    /// added at the end, so that `append_csv` can extend CSV files written by earlier versions.
+    pub fn fields(&self) -> Vec<(&'static str, YamlValue)> {
+        let flops = self.flops();
+        let mflops = self.mflops();
+        let (nx, ny, nz) = self.dimensions.unwrap_or_default();
+        let (px, py, pz) = self.process_grid.unwrap_or_default();
+        let total_sparsemv_time = self.total_sparsemv_time();
+        vec![
+            ("mpi_ranks", self.num_ranks.into()),
+            ("rayon_threads", self.num_threads.into()),
+            ("nx", nx.into()),
+            ("ny", ny.into()),
+            ("nz", nz.into()),
+            (
+                "data_file",
+                self.data_file.clone().unwrap_or_default().into(),
+            ),
+            ("iterations", self.iterations.into()),
+            ("final_residual", self.final_residual.into()),
+            ("difference", self.difference.into()),
+            ("time_total", self.times.total.into()),
+            ("time_ddot", self.times.ddot.into()),
+            ("time_waxpby", self.times.waxpby.into()),
+            ("time_sparsemv", self.times.sparsemv.into()),
+            ("time_allreduce", self.times.allreduce.into()),
+            ("time_exchange", self.times.exchange.into()),
+            ("time_make_local_matrix", self.make_local_matrix_time.into()),
+            ("flops_total", flops[0].into()),
+            ("flops_ddot", flops[1].into()),
+            ("flops_waxpby", flops[2].into()),
+            ("flops_sparsemv", flops[3].into()),
+            ("mflops_total", mflops[0].into()),
+            ("mflops_ddot", mflops[1].into()),
+            ("mflops_waxpby", mflops[2].into()),
+            ("mflops_sparsemv", mflops[3].into()),
+            ("allreduce_min", self.allreduce_min.into()),
+            ("allreduce_max", self.allreduce_max.into()),
+            ("allreduce_avg", self.allreduce_avg.into()),
+            (
+                "sparsemv_mflops_w_overhead",
+                (flops[3] / total_sparsemv_time / 1.0e6).into(),
+            ),
+            (
+                "sparsemv_overhead_time",
+                (self.times.exchange + self.make_local_matrix_time).into(),
+            ),
+            (
+                "sparsemv_overhead_pct",
+                ((self.times.exchange + self.make_local_matrix_time) / total_sparsemv_time * 100.0)
+                    .into(),
+            ),
+            ("sparsemv_setup_time", self.make_local_matrix_time.into()),
+            (
+                "sparsemv_setup_pct",
+                (self.make_local_matrix_time / total_sparsemv_time * 100.0).into(),
+            ),
+            ("sparsemv_exchange_time", self.times.exchange.into()),
+            (
+                "sparsemv_exchange_pct",
+                (self.times.exchange / total_sparsemv_time * 100.0).into(),
+            ),
+            ("px", px.into()),
+            ("py", py.into()),
+            ("pz", pz.into()),
+            ("solver", self.method.to_string().into()),
+            (
+                "preconditioner",
+                self.preconditioner
+                    .map(|preconditioner| preconditioner.to_string())
+                    .unwrap_or_default()
+                    .into(),
+            ),
+            ("time_reduction_wait", self.times.reduction_wait.into()),
+            ("reduction_wait_min", self.reduction_wait_min.into()),
+            ("reduction_wait_max", self.reduction_wait_max.into()),
+            ("reduction_wait_avg", self.reduction_wait_avg.into()),
+            ("convergence_reason", self.reason.to_string().into()),
+            ("time_precondition", self.times.precondition.into()),
+            (
+                "smoother",
+                self.smoother
+                    .map(|smoother| smoother.to_string())
+                    .unwrap_or_default()
+                    .into(),
+            ),
+        ]
+    }
+
+    /// Build a YAML document of the summary, laid out in the same way as the reference
+    /// implementation.
+    pub fn to_yaml_doc(&self) -> YamlDoc {
+        let flops = self.flops();
+        let mflops = self.mflops();
+        let total_sparsemv_time = self.total_sparsemv_time();
+
+        let mut doc = YamlDoc::new("hpccg", "1.0");
+        let parallelism = doc.add("Parallelism", "");
+        parallelism.add("Number of MPI ranks", self.num_ranks);
+        parallelism.add("Number of Rayon threads", self.num_threads);
+        if let Some((px, py, pz)) = self.process_grid {
+            let process_grid = parallelism.add("Process grid", "");
+            process_grid.add("px", px);
+            process_grid.add("py", py);
+            process_grid.add("pz", pz);
+        }
+        match (&self.data_file, self.dimensions) {
+            (Some(data_file), _) => {
+                doc.add("Data file", data_file.as_str());
+            }
+            (None, dimensions) => {
+                let (nx, ny, nz) = dimensions.unwrap_or_default();
+                let dimensions = doc.add("Dimensions", "");
+                dimensions.add("nx", nx);
+                dimensions.add("ny", ny);
+                dimensions.add("nz", nz);
+            }
+        }
+        doc.add("Solver", self.method.to_string());
+        if let Some(preconditioner) = self.preconditioner {
+            doc.add("Preconditioner", preconditioner.to_string());
+        }
+        if let Some(smoother) = self.smoother {
+            doc.add("Smoother", smoother.to_string());
+        }
+        doc.add("Number of iterations", self.iterations);
+        doc.add("Final residual", self.final_residual);
+        doc.add("Convergence reason", self.reason.to_string());
+        doc.add("#********** Performance Summary (times in sec) ***********", "");
+
+        let time_summary = doc.add("Time Summary", "");
+        time_summary.add("Total   ", self.times.total);
+        time_summary.add("DDOT    ", self.times.ddot);
+        time_summary.add("WAXPBY  ", self.times.waxpby);
+        time_summary.add("SPARSEMV", self.times.sparsemv);
+        if self.method == Method::Pcg {
+            time_summary.add("PRECOND ", self.times.precondition);
+        }
+
+        let flops_summary = doc.add("FLOPS Summary", "");
+        flops_summary.add("Total   ", flops[0]);
+        flops_summary.add("DDOT    ", flops[1]);
+        flops_summary.add("WAXPBY  ", flops[2]);
+        flops_summary.add("SPARSEMV", flops[3]);
+
+        let mflops_summary = doc.add("MFLOPS Summary", "");
+        mflops_summary.add("Total   ", mflops[0]);
+        mflops_summary.add("DDOT    ", mflops[1]);
+        mflops_summary.add("WAXPBY  ", mflops[2]);
+        mflops_summary.add("SPARSEMV", mflops[3]);
+
+        let ddot_variations = doc.add("DDOT Timing Variations", "");
+        ddot_variations.add("Min DDOT MPI_Allreduce time", self.allreduce_min);
+        ddot_variations.add("Max DDOT MPI_Allreduce time", self.allreduce_max);
+        ddot_variations.add("Avg DDOT MPI_Allreduce time", self.allreduce_avg);
+        // Only the pipelined solver overlaps its reduction with other work
+        if self.method == Method::PipelinedCg {
+            ddot_variations.add("Min DDOT MPI_Iallreduce wait time", self.reduction_wait_min);
+            ddot_variations.add("Max DDOT MPI_Iallreduce wait time", self.reduction_wait_max);
+            ddot_variations.add("Avg DDOT MPI_Iallreduce wait time", self.reduction_wait_avg);
+        }
+
+        let sparsemv_overheads = doc.add("SPARSEMV OVERHEADS", "");
+        sparsemv_overheads.add(
+            "SPARSEMV MFLOPS W OVERHEAD",
+            flops[3] / total_sparsemv_time / 1.0e6,
+        );
+        sparsemv_overheads.add(
+            "SPARSEMV PARALLEL OVERHEAD Time",
+            self.times.exchange + self.make_local_matrix_time,
+        );
+        sparsemv_overheads.add(
+            "SPARSEMV PARALLEL OVERHEAD Pct",
+            (self.times.exchange + self.make_local_matrix_time) / total_sparsemv_time * 100.0,
+        );
+        sparsemv_overheads.add(
+            "SPARSEMV PARALLEL OVERHEAD Setup Time",
+            self.make_local_matrix_time,
+        );
+        sparsemv_overheads.add(
+            "SPARSEMV PARALLEL OVERHEAD Setup Pct",
+            self.make_local_matrix_time / total_sparsemv_time * 100.0,
+        );
+        sparsemv_overheads.add(
+            "SPARSEMV PARALLEL OVERHEAD Bdry Exch Time",
+            self.times.exchange,
+        );
+        sparsemv_overheads.add(
+            "SPARSEMV PARALLEL OVERHEAD Bdry Exch Pct",
+            self.times.exchange / total_sparsemv_time * 100.0,
+        );
+        doc
+    }
+
+    /// Format the summary as a flat JSON object, with a key for each of `fields`.
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self
+            .fields()
+            .iter()
+            .map(|(key, value)| format!("  \"{key}\": {}", json_value(value)))
+            .collect();
+        format!("{{\n{}\n}}\n", entries.join(",\n"))
+    }
+
+    /// The header line of the CSV format, naming each of `fields`.
+    pub fn csv_header(&self) -> String {
+        let keys: Vec<&str> = self.fields().iter().map(|(key, _)| *key).collect();
+        keys.join(",")
+    }
+
+    /// Format the summary as a line of CSV, in the same order as `csv_header`.
+    pub fn to_csv_row(&self) -> String {
+        let values: Vec<String> = self
+            .fields()
+            .iter()
+            .map(|(_, value)| csv_value(value))
+            .collect();
+        values.join(",")
+    }
+
+    /// Append the summary as a row of a CSV file, so the results of a sweep of runs can be
+    /// accumulated. The header is written if the file is new or empty, and otherwise must match
+    /// the existing header. A file written by an earlier version, whose header is missing the
+    /// newest fields, has its header extended and its rows padded with empty values first.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the CSV file.
+    pub fn append_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let header = self.csv_header();
+        let existing = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        match existing.lines().next() {
+            None => writeln!(file, "{header}")?,
+            Some(existing_header) if existing_header == header => {
+                if !existing.ends_with('\n') {
+                    writeln!(file)?;
+                }
+            }
+            Some(existing_header) if header.starts_with(&format!("{existing_header},")) => {
+                let padding =
+                    ",".repeat(header.matches(',').count() - existing_header.matches(',').count());
+                let rows: String = existing
+                    .lines()
+                    .skip(1)
+                    .map(|row| format!("{row}{padding}\n"))
+                    .collect();
+                file.set_len(0)?;
+                write!(file, "{header}\n{rows}")?;
+            }
+            Some(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} has a different CSV header", path.display()),
+                ))
+            }
+        }
+        writeln!(file, "{}", self.to_csv_row())
+    }
+}
+
+/// Format a value as JSON, using `null` for numbers JSON cannot represent.
+fn json_value(value: &YamlValue) -> String {
+    match value {
+        YamlValue::Int(value) => value.to_string(),
+        YamlValue::Float(value) if value.is_finite() => format!("{value:?}"),
+        YamlValue::Float(_) => "null".to_owned(),
+        YamlValue::Str(value) => {
+            let escaped: String = value
+                .chars()
+                .map(|c| match c {
+                    '"' => "\\\"".to_owned(),
+                    '\\' => "\\\\".to_owned(),
+                    c if c.is_control() => format!("\\u{:04x}", c as u32),
+                    c => c.to_string(),
+                })
+                .collect();
+            format!("\"{escaped}\"")
+        }
+    }
+}
+
+/// Format a value as a CSV field, quoting strings which contain separators or quotes.
+fn csv_value(value: &YamlValue) -> String {
+    match value {
+        YamlValue::Int(value) => value.to_string(),
+        YamlValue::Float(value) => format!("{value:?}"),
+        YamlValue::Str(value) if value.contains([',', '"', '\n']) => {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        }
+        YamlValue::Str(value) => value.clone(),
+    }
+}
@@ -1,5 +1,7 @@
 use mpi::traits::*;
 
+use super::{Decomposition, ProcessGrid, Stencil, StencilConfig};
+
 /// A data structure representing a sparse matrix mesh
 ///
 /// # Fields
@@ -9,12 +11,15 @@ use mpi::traits::*;
 /// * `total_nnz` - The total number of non-zeroes (always equal to `local_nnz` in serial mode)
 /// * `local_nrow` - The local volume of the matrix, calculated as `x*y*z` in serial mode
 /// * `local_ncol` - A variable only used in MPI mode (set to `local_nrow` in serial mode)
-/// * `local_nnz` - The local number of non-zero values, approximated as `local_nrow*27`
+/// * `local_nnz` - The local number of non-zero values, approximated as `local_nrow` times the
+///   number of points in the stencil
 /// * `nnz_in_row` - A vector containing the number of non-zeroes in each row
 /// * `row_start_inds` - A vector of pointers to values
 /// * `list_of_vals` - A vector of values stored in the matrix
 /// * `list_of_inds` - A vector of indices into the matrix
-#[derive(Debug)]
+/// * `geometry` - The structured mesh the matrix was generated on, if it was generated from a
+///   stencil
+#[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct SparseMatrix {
     pub start_row: usize,
@@ -28,7 +33,9 @@ pub struct SparseMatrix {
     pub row_start_inds: Vec<usize>,
     pub list_of_vals: Vec<f64>,
     pub list_of_inds: Vec<i32>,
+    pub geometry: Option<Geometry>,
     // MPI only
+    pub is_local: bool, // Whether `make_local_matrix` has transformed the column indices
     pub num_external: usize, // Option<usize>,
     pub num_send_neighbors: usize,
     pub external_index: Vec<usize>,
@@ -41,6 +48,41 @@ pub struct SparseMatrix {
     pub send_buffer: Vec<f64>,
 }
 
+/// The structured mesh a matrix was generated on, from which coarser versions of the matrix can be
+/// generated.
+///
+/// # Fields
+/// * `nx` - Size of x dimension of each processor's sub-block.
+/// * `ny` - Size of y dimension of each processor's sub-block.
+/// * `nz` - Size of z dimension of each processor's sub-block.
+/// * `stencil` - The stencil connecting each point to its neighbours, and their weights.
+/// * `grid` - The arrangement of the processors' sub-blocks.
+/// * `rank` - The processor whose sub-block this is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geometry {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    pub stencil: StencilConfig,
+    pub grid: ProcessGrid,
+    pub rank: usize,
+}
+
+impl Geometry {
+    /// The mesh with half as many points in each dimension, if each dimension has an even size.
+    pub fn coarsen(&self) -> Option<Self> {
+        if [self.nx, self.ny, self.nz].iter().any(|n| n % 2 != 0) {
+            return None;
+        }
+        Some(Geometry {
+            nx: self.nx / 2,
+            ny: self.ny / 2,
+            nz: self.nz / 2,
+            ..*self
+        })
+    }
+}
+
 impl SparseMatrix {
     /// Generates the initial mesh and its associated values.
     ///
@@ -60,30 +102,85 @@ impl SparseMatrix {
         nz: usize,
         world: &impl Communicator,
     ) -> (Self, Vec<f64>, Vec<f64>, Vec<f64>) {
-        let size = world.size() as usize;
-        let rank = world.rank() as usize;
+        Self::generate_matrix_with_stencil(nx, ny, nz, Stencil::TwentySevenPoint.into(), world)
+    }
 
-        let use_7pt_stencil = false;
+    /// Generates the initial mesh and its associated values, using the given stencil and weights.
+    /// The right hand side is chosen so that the exact solution is all ones.
+    ///
+    /// # Arguments
+    ///  * `nx` - Size of x dimension.
+    ///  * `ny` - Size of y dimension.
+    ///  * `nz` - Size of z dimension.
+    ///  * `stencil` - The stencil connecting each point to its neighbours, and their weights.
+    ///
+    /// # Return values
+    ///  * `matrix` - Generated sparse matrix.
+    ///  * `guess` - Inital guess for the mesh.
+    ///  * `rhs` - Right hand side.
+    ///  * `exact` - Exact solution (as computed by a direct solver).
+    pub fn generate_matrix_with_stencil(
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        stencil: StencilConfig,
+        world: &impl Communicator,
+    ) -> (Self, Vec<f64>, Vec<f64>, Vec<f64>) {
+        let grid = ProcessGrid::new(world.size() as usize, Decomposition::Slab);
+        Self::generate_matrix_on_grid(nx, ny, nz, stencil, grid, world.rank() as usize)
+    }
+
+    /// Generates one processor's part of the initial mesh and its associated values, where each
+    /// processor owns a brick of the mesh in a grid of processors. The right hand side is chosen
+    /// so that the exact solution is all ones.
+    ///
+    /// # Arguments
+    ///  * `nx` - Size of x dimension of each processor's brick.
+    ///  * `ny` - Size of y dimension of each processor's brick.
+    ///  * `nz` - Size of z dimension of each processor's brick.
+    ///  * `stencil` - The stencil connecting each point to its neighbours, and their weights.
+    ///  * `grid` - The arrangement of the processors.
+    ///  * `rank` - The rank of this processor.
+    ///
+    /// # Return values
+    ///  * `matrix` - Generated sparse matrix.
+    ///  * `guess` - Inital guess for the mesh.
+    ///  * `rhs` - Right hand side.
+    ///  * `exact` - Exact solution (as computed by a direct solver).
+    pub fn generate_matrix_on_grid(
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        stencil: StencilConfig,
+        grid: ProcessGrid,
+        rank: usize,
+    ) -> (Self, Vec<f64>, Vec<f64>, Vec<f64>) {
+        let size = grid.size();
+        assert!(rank < size);
 
         // The size of our sub-block (must be non-zero)
         let local_nrow = nx * ny * nz;
         assert!(local_nrow > 0);
         // The approximate number of non-zeros per row (excluding boundary nodes)
-        let local_nnz = 27 * local_nrow;
+        let local_nnz = stencil.shape.points() * local_nrow;
 
         // Total number of grid points in mesh
         let total_nrow = local_nrow * size;
-        // Approximately 27 nonzeros per row (except for boundary nodes)
-        let total_nnz = 27 * total_nrow;
+        // Approximately one nonzero per stencil point per row (except for boundary nodes)
+        let total_nnz = stencil.shape.points() * total_nrow;
 
         // In non-mpi mode, the total row, column, and non-zero sizes are the same as the local ones
         // let (total_nnz, total_nrow, local_ncol) = (local_nnz, local_nrow, local_nrow);
         let local_ncol = local_nrow;
 
-        // Each processor gets a section of a chimney stack domain
+        // Each processor gets a brick of the mesh, and owns a contiguous range of rows
         let start_row = local_nrow * rank;
         let stop_row = start_row + local_nrow - 1;
 
+        // The position of our brick in the grid, and the size of the whole mesh
+        let (rx, ry, rz) = grid.coords(rank);
+        let (mesh_nx, mesh_ny, mesh_nz) = (grid.px * nx, grid.py * ny, grid.pz * nz);
+
         // The number of non-zero numbers in each row
         let mut nnz_in_row = Vec::with_capacity(local_nrow);
         // The index of the start of each row into `list_of_vals` and `list_of_inds`
@@ -102,48 +199,48 @@ impl SparseMatrix {
         for iz in 0..nz {
             for iy in 0..ny {
                 for ix in 0..nx {
-                    let currow = start_row + iz * nx * ny + iy * nx + ix;
+                    let (gx, gy, gz) = (rx * nx + ix, ry * ny + iy, rz * nz + iz);
                     let mut nnzrow: usize = 0;
+                    let mut row_sum = 0.0;
                     row_start_inds.push(curvalind);
                     for sz in -1..=1 {
                         for sy in -1..=1 {
                             for sx in -1..=1 {
-                                let curcol = (currow as i32)
-                                    + sz * (nx as i32) * (ny as i32)
-                                    + sy * (nx as i32)
-                                    + sx;
-                                // Since we have a stack of nx by ny by nz domains , stacking
-                                // in the z direction, we check to see if sx and sy are
-                                // reaching outside of the domain, while the check for the
-                                // curcol being valid is sufficient to check the z values
-                                let sx_ix = (ix as i32) + sx;
-                                let sy_iy = (iy as i32) + sy;
-                                #[allow(clippy::collapsible_if)]
-                                if (sx_ix >= 0)
-                                    && (sx_ix < (nx as i32))
-                                    && (sy_iy >= 0)
-                                    && (sy_iy < (ny as i32))
-                                    && (curcol >= 0 && curcol < (total_nrow as i32))
-                                {
-                                    if !use_7pt_stencil || (sz * sz + sy * sy + sx * sx <= 1) {
-                                        // This logic will skip over point that are not part of
-                                        // a 7-pt stencil
-                                        if (curcol as usize) == currow {
-                                            list_of_vals.push(27.0);
-                                        } else {
-                                            list_of_vals.push(-1.0);
-                                        }
-                                        curvalind += 1;
-                                        list_of_inds.push(curcol);
-                                        nnzrow += 1;
+                                // Skip over neighbours which are outside of the mesh, unless it
+                                // wraps around in their dimension
+                                let Some([cx, cy, cz]) = stencil.neighbour(
+                                    [gx, gy, gz],
+                                    [sx, sy, sz],
+                                    [mesh_nx, mesh_ny, mesh_nz],
+                                ) else {
+                                    continue;
+                                };
+                                // This logic will skip over points that are not part of
+                                // the stencil
+                                if let Some(weight) = stencil.weight(sx, sy, sz) {
+                                    let curcol = grid.global_row([cx, cy, cz], [nx, ny, nz]);
+                                    row_sum += weight;
+                                    // A periodic dimension shorter than three points wraps more
+                                    // than one offset onto the same neighbour, whose weights are
+                                    // summed into a single entry
+                                    let row_start = curvalind - nnzrow;
+                                    if let Some(ind) = (row_start..curvalind)
+                                        .find(|&ind| list_of_inds[ind] == curcol as i32)
+                                    {
+                                        list_of_vals[ind] += weight;
+                                        continue;
                                     }
+                                    list_of_vals.push(weight);
+                                    curvalind += 1;
+                                    list_of_inds.push(curcol as i32);
+                                    nnzrow += 1;
                                 }
                             }
                         }
                     }
                     nnz_in_row.push(nnzrow);
                     guess.push(0.0);
-                    rhs.push(27.0 - ((nnzrow - 1) as f64));
+                    rhs.push(row_sum);
                     exact.push(1.0);
                 }
             }
@@ -161,7 +258,16 @@ impl SparseMatrix {
             row_start_inds,
             list_of_vals,
             list_of_inds,
+            geometry: Some(Geometry {
+                nx,
+                ny,
+                nz,
+                stencil,
+                grid,
+                rank,
+            }),
             // ===== MPI only ===== //
+            is_local: false,
             num_external: 0,
             num_send_neighbors: 0,
             external_index: vec![],
@@ -175,4 +281,57 @@ impl SparseMatrix {
         };
         (matrix, guess, rhs, exact)
     }
+
+    /// Finds the global column index of every non-zero, undoing the transform of the column
+    /// indices done by `make_local_matrix`.
+    ///
+    /// Indices of local columns are offset back by `start_row`, and external columns are looked
+    /// up in `external_index`, whether they are still negated (partway through
+    /// `make_local_matrix`) or have been renumbered to follow the local columns. The indices of a
+    /// matrix which has not been through `make_local_matrix` are already global, and are returned
+    /// unchanged.
+    ///
+    /// # Return values
+    ///  * `global_inds` - The global column index of each entry of `list_of_inds`.
+    pub fn global_col_inds(&self) -> Vec<usize> {
+        if !self.is_local {
+            return self.list_of_inds.iter().map(|&ind| ind as usize).collect();
+        }
+        let mut global_cols: Vec<usize> = (self.start_row..=self.stop_row).collect();
+        global_cols.resize(self.local_ncol, 0);
+        for (&global_ind, &local_ind) in self
+            .external_index
+            .iter()
+            .zip(self.external_local_index.iter())
+        {
+            global_cols[local_ind as usize] = global_ind;
+        }
+        self.list_of_inds
+            .iter()
+            .map(|&ind| {
+                if ind < 0 {
+                    -(ind + 1) as usize
+                } else {
+                    global_cols[ind as usize]
+                }
+            })
+            .collect()
+    }
+
+    /// Finds the value on the diagonal of each local row of the matrix, which is zero for rows
+    /// without a diagonal entry. The column indices must already have been made local by
+    /// `make_local_matrix`, so the diagonal of the local row `i` is in the local column `i`.
+    ///
+    /// # Return values
+    ///  * `diagonal` - The diagonal entry of each local row.
+    pub fn diagonal(&self) -> Vec<f64> {
+        (0..self.local_nrow)
+            .map(|row| {
+                let start = self.row_start_inds[row];
+                (start..start + self.nnz_in_row[row])
+                    .find(|&ind| self.list_of_inds[ind] == row as i32)
+                    .map_or(0.0, |ind| self.list_of_vals[ind])
+            })
+            .collect()
+    }
 }
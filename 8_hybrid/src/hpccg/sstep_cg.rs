@@ -0,0 +1,377 @@
+use std::str::FromStr;
+
+use mpi::collective::SystemOperation;
+use mpi::traits::*;
+use rayon::prelude::*;
+
+use super::{
+    ddot, exchange_externals, make_deep_local_matrix, mytimer, sparsemv, tick, tock, waxpby,
+    ConvergenceReason, IterationInfo, SolveReport, SolverConfig, SparseMatrix, StoppingCriteria,
+    Timings, Verbosity,
+};
+
+/// The polynomials the Krylov basis of the s-step conjugate gradient solver is built from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KrylovBasis {
+    /// The powers of the matrix, whose vectors quickly become close to parallel as the step size
+    /// grows.
+    Monomial,
+    /// Products of the matrix shifted by Leja-ordered Chebyshev points of an interval containing
+    /// its eigenvalues, which stay much further from parallel.
+    Newton,
+}
+
+impl FromStr for KrylovBasis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "monomial" => Ok(KrylovBasis::Monomial),
+            "newton" => Ok(KrylovBasis::Newton),
+            _ => Err(format!(
+                "Unknown basis `{s}`, expected one of `monomial` or `newton`"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for KrylovBasis {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KrylovBasis::Monomial => write!(f, "monomial"),
+            KrylovBasis::Newton => write!(f, "newton"),
+        }
+    }
+}
+
+/// A method to compute the approximate solution to `Ax = b` with the s-step (communication
+/// avoiding) conjugate gradient method, which does `s` iterations of the conjugate gradient
+/// method for each exchange of boundary values and global reduction.
+///
+/// Each outer iteration builds the basis `Y = [P, R]` of the `s + 1` vectors `pⱼ(A)p` and the `s`
+/// vectors `pⱼ(A)r` with the matrix-powers kernel, and reduces their Gram matrix `YᵀY` in one
+/// `MPI_Allreduce`. The `s` inner iterations then update the coordinates of `x`, `r` and `p` in
+/// the basis, which only needs the Gram matrix, and the vectors are recovered at the end. This
+/// takes extra floating point operations for the basis, the Gram matrix and the redundant
+/// products in the ghost region, in exchange for `s` times fewer messages. The ghost region is
+/// set up at the start of the solve, so is included in its total time.
+///
+/// The residual is only computed from the Gram matrix, so rounding in the basis limits how small
+/// it can get, more so for larger step sizes and the monomial basis.
+///
+/// # Arguments
+/// * `A` - The input sparse matrix.
+/// * `b` - The known right hand side vector.
+/// * `x` - The current approximate solution, which starts as the initial guess.
+/// * `config` - The settings of the solver.
+/// * `world` - The MPI world to communicate over.
+///
+/// # Return values
+/// * `report` - The approximate result, and how the solver got there.
+#[allow(non_snake_case)]
+pub fn sstep_cg(
+    A: &mut SparseMatrix,
+    b: &[f64],
+    x: &[f64],
+    config: &mut SolverConfig,
+    world: &impl Communicator,
+) -> SolveReport {
+    let t_begin: f64 = mytimer();
+    let mut t_total: f64 = 0.0;
+    let mut t_ddot: f64 = 0.0;
+    let mut t_waxpby: f64 = 0.0;
+    let mut t_sparsemv: f64 = 0.0;
+    let mut t_mpi_allreduce: f64 = 0.0;
+    let mut t_mpi_exchange: f64 = 0.0;
+
+    let nrow = A.local_nrow;
+
+    let mut result = x.to_owned();
+    let mut iteration = 0;
+
+    let rank = world.rank();
+
+    let max_iterations = config.max_iterations;
+    let s = config.step_size;
+    // The number of vectors in the basis
+    let m = 2 * s + 1;
+
+    let powers = make_deep_local_matrix(A, s, world);
+    let (shifts, scale) = basis_shifts(A, s, config.basis, world);
+
+    // The change of basis matrix `B`, with `AY[:, j] = YB[:, j]` for each column except the last
+    // of each of `P` and `R`
+    let mut change = vec![vec![0.0; m]; m];
+    for j in 0..s {
+        change[j][j] = shifts[j];
+        change[j + 1][j] = scale;
+    }
+    for j in s + 1..m - 1 {
+        change[j][j] = shifts[j - s - 1];
+        change[j + 1][j] = scale;
+    }
+
+    // `x` is of length `nrow`, so copy it to a vector which can be extended with the external
+    // values for sparse matrix-vector operation
+    tick(&mut t_total);
+    let mut x_ext = waxpby(nrow, 1.0, &result, 0.0, b);
+    tock(&t_total, &mut t_waxpby);
+
+    tick(&mut t_total);
+    exchange_externals(A, &mut x_ext, world);
+    tock(&t_total, &mut t_mpi_exchange);
+
+    tick(&mut t_total);
+    let Ax = sparsemv(A, &x_ext);
+    tock(&t_total, &mut t_sparsemv);
+
+    tick(&mut t_total);
+    let mut r = waxpby(nrow, 1.0, b, -1.0, &Ax);
+    let mut p = waxpby(nrow, 1.0, &r, 0.0, &r);
+    tock(&t_total, &mut t_waxpby);
+
+    tick(&mut t_total);
+    let mut normr = ddot(nrow, &r, &r, &mut t_mpi_allreduce, world).sqrt();
+    tock(&t_total, &mut t_ddot);
+
+    if rank == 0 && config.verbosity > Verbosity::Quiet {
+        println!("Initial Residual = {normr:+.5e}");
+    }
+    let flow = config.notify(IterationInfo {
+        iteration: 0,
+        residual: normr,
+        alpha: None,
+        beta: None,
+        elapsed: mytimer() - t_begin,
+    });
+
+    tick(&mut t_total);
+    let mut criteria = StoppingCriteria::new(config, normr, || {
+        ddot(nrow, b, b, &mut t_mpi_allreduce, world).sqrt()
+    });
+    tock(&t_total, &mut t_ddot);
+    let mut reason = criteria.check(0, normr, flow);
+
+    while reason.is_none() && iteration + 1 < max_iterations {
+        tick(&mut t_total);
+        let extended = powers.exchange(&[&p, &r], world);
+        tock(&t_total, &mut t_mpi_exchange);
+
+        tick(&mut t_total);
+        let mut basis = powers.powers(&extended[0], &shifts, scale);
+        basis.extend(powers.powers(&extended[1], &shifts[..s - 1], scale));
+        tock(&t_total, &mut t_sparsemv);
+
+        tick(&mut t_total);
+        let gram = gram_matrix(&basis, &mut t_mpi_allreduce, world);
+        tock(&t_total, &mut t_ddot);
+
+        // The coordinates of `x - x₀`, `r` and `p` in the basis
+        let mut x_coords = vec![0.0; m];
+        let mut r_coords = vec![0.0; m];
+        let mut p_coords = vec![0.0; m];
+        r_coords[s + 1] = 1.0;
+        p_coords[0] = 1.0;
+        let mut rtrans = quadratic_form(&gram, &r_coords, &r_coords);
+
+        for _ in 0..s {
+            if reason.is_some() || iteration + 1 >= max_iterations {
+                break;
+            }
+            let ap_coords: Vec<f64> = change
+                .iter()
+                .map(|row| row.iter().zip(p_coords.iter()).map(|(b, p)| b * p).sum())
+                .collect();
+            let pAp = quadratic_form(&gram, &p_coords, &ap_coords);
+            // The method cannot take a step along a direction without positive curvature
+            if pAp.is_nan() || pAp <= 0.0 {
+                reason = Some(ConvergenceReason::Breakdown);
+                break;
+            }
+            let alpha = rtrans / pAp;
+            let (previous_coords, previous_normr) = (x_coords.clone(), normr);
+            for j in 0..m {
+                x_coords[j] += alpha * p_coords[j];
+                r_coords[j] -= alpha * ap_coords[j];
+            }
+            let oldrtrans = rtrans;
+            rtrans = quadratic_form(&gram, &r_coords, &r_coords);
+            let beta = rtrans / oldrtrans;
+            for j in 0..m {
+                p_coords[j] = r_coords[j] + beta * p_coords[j];
+            }
+
+            iteration += 1;
+            // Rounding can make the residual found from the Gram matrix slightly negative
+            normr = rtrans.max(0.0).sqrt();
+            if rank == 0 && config.should_print(iteration) {
+                println!("Iteration = {iteration} , Residual = {normr:+.5e}");
+            }
+            let flow = config.notify(IterationInfo {
+                iteration,
+                residual: normr,
+                alpha: Some(alpha),
+                beta: Some(beta),
+                elapsed: mytimer() - t_begin,
+            });
+            reason = criteria.check(iteration, normr, flow);
+            // A step which broke down or diverged is undone, so the last good iterate is returned
+            if reason.is_some_and(ConvergenceReason::is_failure) {
+                (x_coords, normr) = (previous_coords, previous_normr);
+                iteration -= 1;
+            }
+        }
+
+        tick(&mut t_total);
+        let correction = combine(&basis, &x_coords);
+        result = waxpby(nrow, 1.0, &result, 1.0, &correction);
+        r = combine(&basis, &r_coords);
+        p = combine(&basis, &p_coords);
+        tock(&t_total, &mut t_waxpby);
+    }
+
+    let reason = reason.unwrap_or(ConvergenceReason::MaxIterations);
+
+    SolveReport {
+        solution: result,
+        iterations: iteration,
+        final_residual: normr,
+        reason,
+        residual_history: config.take_residual_history(),
+        times: Timings {
+            total: mytimer() - t_begin,
+            ddot: t_ddot,
+            waxpby: t_waxpby,
+            sparsemv: t_sparsemv,
+            allreduce: t_mpi_allreduce,
+            exchange: t_mpi_exchange,
+            reduction_wait: 0.0,
+            precondition: 0.0,
+        },
+    }
+}
+
+/// The shifts and scale of the products building the basis. The Newton basis uses the Chebyshev
+/// points of the Gershgorin interval of the eigenvalues in Leja order, which spreads out the
+/// first few of them, and is scaled by the half-width of the interval.
+///
+/// # Arguments
+/// * `matrix` - The local part of the matrix.
+/// * `s` - The number of shifts.
+/// * `basis` - The polynomials the basis is built from.
+/// * `world` - The MPI world to communicate over.
+#[allow(clippy::needless_range_loop)]
+fn basis_shifts(
+    matrix: &SparseMatrix,
+    s: usize,
+    basis: KrylovBasis,
+    world: &impl Communicator,
+) -> (Vec<f64>, f64) {
+    if basis == KrylovBasis::Monomial {
+        return (vec![0.0; s], 1.0);
+    }
+
+    let diagonal = matrix.diagonal();
+    let (mut local_lower, mut local_upper) = (f64::MAX, f64::MIN);
+    for row in 0..matrix.local_nrow {
+        let start = matrix.row_start_inds[row];
+        let sum: f64 = matrix.list_of_vals[start..start + matrix.nnz_in_row[row]]
+            .iter()
+            .map(|value| value.abs())
+            .sum();
+        let radius = sum - diagonal[row].abs();
+        local_lower = local_lower.min(diagonal[row] - radius);
+        local_upper = local_upper.max(diagonal[row] + radius);
+    }
+    let (mut lower, mut upper): (f64, f64) = (0.0, 0.0);
+    world.all_reduce_into(&local_lower, &mut lower, SystemOperation::min());
+    world.all_reduce_into(&local_upper, &mut upper, SystemOperation::max());
+    // The matrix is positive definite
+    let lower = lower.max(0.0);
+
+    let centre = (upper + lower) / 2.0;
+    let half_width = (upper - lower) / 2.0;
+    let mut points: Vec<f64> = (0..s)
+        .map(|j| {
+            let angle = (2 * j + 1) as f64 * std::f64::consts::PI / (2 * s) as f64;
+            centre + half_width * angle.cos()
+        })
+        .collect();
+
+    // Each point in turn is the one furthest from those before it, by the product of distances
+    for j in 0..s {
+        let distance = |point: f64| -> f64 {
+            if j == 0 {
+                point.abs()
+            } else {
+                points[..j].iter().map(|p| (point - p).abs()).product()
+            }
+        };
+        let furthest = (j..s)
+            .max_by(|&a, &b| distance(points[a]).total_cmp(&distance(points[b])))
+            .unwrap();
+        points.swap(j, furthest);
+    }
+    let scale = if half_width > 0.0 { half_width } else { 1.0 };
+    (points, scale)
+}
+
+/// The Gram matrix of the vectors of the basis, reduced over all processors with one
+/// `MPI_Allreduce` of its upper triangle.
+///
+/// # Arguments
+/// * `basis` - The local values of the vectors of the basis.
+/// * `time_allreduce` - The time spent in the reduction, which is incremented.
+/// * `world` - The MPI world to communicate over.
+fn gram_matrix(
+    basis: &[Vec<f64>],
+    time_allreduce: &mut f64,
+    world: &impl Communicator,
+) -> Vec<Vec<f64>> {
+    let m = basis.len();
+    let pairs: Vec<(usize, usize)> = (0..m).flat_map(|i| (i..m).map(move |j| (i, j))).collect();
+    let local: Vec<f64> = pairs
+        .iter()
+        .map(|&(i, j)| {
+            basis[i]
+                .par_iter()
+                .zip(basis[j].par_iter())
+                .map(|(a, b)| a * b)
+                .sum()
+        })
+        .collect();
+
+    let t0 = mytimer();
+    let mut global = vec![0.0; local.len()];
+    world.all_reduce_into(&local[..], &mut global[..], SystemOperation::sum());
+    *time_allreduce += mytimer() - t0;
+
+    let mut gram = vec![vec![0.0; m]; m];
+    for ((i, j), entry) in pairs.into_iter().zip(global) {
+        gram[i][j] = entry;
+        gram[j][i] = entry;
+    }
+    gram
+}
+
+/// The inner product `uᵀGv` of two vectors given by their coordinates in the basis.
+fn quadratic_form(gram: &[Vec<f64>], u: &[f64], v: &[f64]) -> f64 {
+    gram.iter()
+        .zip(u.iter())
+        .map(|(row, u)| u * row.iter().zip(v.iter()).map(|(g, v)| g * v).sum::<f64>())
+        .sum()
+}
+
+/// The vector with the given coordinates in the basis.
+fn combine(basis: &[Vec<f64>], coords: &[f64]) -> Vec<f64> {
+    let mut vector = vec![0.0; basis[0].len()];
+    for (basis_vector, &coord) in basis.iter().zip(coords.iter()) {
+        if coord != 0.0 {
+            vector
+                .par_iter_mut()
+                .zip(basis_vector.par_iter())
+                .for_each(|(value, basis_value)| *value += coord * basis_value);
+        }
+    }
+    vector
+}
@@ -0,0 +1,227 @@
+use std::str::FromStr;
+
+/// The shape of the stencil used to connect each point of the mesh to its neighbours.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stencil {
+    /// The point and its face neighbours.
+    SevenPoint,
+    /// The point and its face and edge neighbours.
+    NineteenPoint,
+    /// The point and all of its face, edge and corner neighbours.
+    TwentySevenPoint,
+}
+
+impl Stencil {
+    /// The number of points in the stencil, which is the number of non-zeroes in the rows of
+    /// interior points of the mesh.
+    pub fn points(&self) -> usize {
+        match self {
+            Stencil::SevenPoint => 7,
+            Stencil::NineteenPoint => 19,
+            Stencil::TwentySevenPoint => 27,
+        }
+    }
+
+    /// Whether a neighbour at an offset from a point is part of the stencil.
+    ///
+    /// # Arguments
+    /// * `sx` - The offset in the x dimension, from `-1` to `1`.
+    /// * `sy` - The offset in the y dimension, from `-1` to `1`.
+    /// * `sz` - The offset in the z dimension, from `-1` to `1`.
+    pub fn contains(&self, sx: i32, sy: i32, sz: i32) -> bool {
+        let distance = sx * sx + sy * sy + sz * sz;
+        match self {
+            Stencil::SevenPoint => distance <= 1,
+            Stencil::NineteenPoint => distance <= 2,
+            Stencil::TwentySevenPoint => distance <= 3,
+        }
+    }
+}
+
+impl FromStr for Stencil {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "7" | "7pt" => Ok(Stencil::SevenPoint),
+            "19" | "19pt" => Ok(Stencil::NineteenPoint),
+            "27" | "27pt" => Ok(Stencil::TwentySevenPoint),
+            _ => Err(format!(
+                "Unknown stencil `{s}`, expected one of `7`, `19` or `27`"
+            )),
+        }
+    }
+}
+
+/// The stencil used to generate a matrix, and the weights of its points, built up from the
+/// stencil of the benchmark, for example `StencilConfig::new(Stencil::SevenPoint).weights(6.0,
+/// -1.0)`.
+///
+/// # Fields
+/// * `shape` - Which neighbours of each point are connected to it.
+/// * `diagonal` - The weight of each point itself.
+/// * `off_diagonal` - The weight of each neighbour of a point.
+/// * `anisotropy` - The factors the weight of a neighbour is scaled by for each of the x, y and z
+///   dimensions it is offset along.
+/// * `periodic` - Whether the mesh wraps around in each of the x, y and z dimensions, so points on
+///   the boundary have the full stencil of neighbours.
+/// * `shift` - A shift added to the diagonal, which keeps a periodic matrix non-singular when the
+///   weights of each row sum to zero.
+/// * `convection` - The cell Péclet number of the flow along each of the x, y and z dimensions,
+///   which makes the matrix non-symmetric.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StencilConfig {
+    pub shape: Stencil,
+    pub diagonal: f64,
+    pub off_diagonal: f64,
+    pub anisotropy: [f64; 3],
+    pub periodic: [bool; 3],
+    pub shift: f64,
+    pub convection: [f64; 3],
+}
+
+impl Default for StencilConfig {
+    fn default() -> Self {
+        StencilConfig::new(Stencil::TwentySevenPoint)
+    }
+}
+
+impl From<Stencil> for StencilConfig {
+    fn from(shape: Stencil) -> Self {
+        StencilConfig::new(shape)
+    }
+}
+
+impl StencilConfig {
+    /// Create a configuration with the weights of the benchmark, which are `27` on the diagonal
+    /// and `-1` off the diagonal, without anisotropy, periodic boundaries or convection.
+    ///
+    /// # Arguments
+    /// * `shape` - Which neighbours of each point are connected to it.
+    pub fn new(shape: Stencil) -> Self {
+        StencilConfig {
+            shape,
+            diagonal: 27.0,
+            off_diagonal: -1.0,
+            anisotropy: [1.0; 3],
+            periodic: [false; 3],
+            shift: 0.0,
+            convection: [0.0; 3],
+        }
+    }
+
+    /// Create the 7-point stencil of the convection-diffusion equation, with a flow along the
+    /// diagonal of the mesh.
+    ///
+    /// # Arguments
+    /// * `peclet` - The cell Péclet number of the flow, which is the ratio of convection to
+    ///   diffusion across a cell.
+    pub fn convection_diffusion(peclet: f64) -> Self {
+        StencilConfig::new(Stencil::SevenPoint)
+            .weights(6.0, -1.0)
+            .convection(peclet, [1.0, 1.0, 1.0])
+    }
+
+    /// Set the weights of each point, and of each of its neighbours.
+    pub fn weights(mut self, diagonal: f64, off_diagonal: f64) -> Self {
+        self.diagonal = diagonal;
+        self.off_diagonal = off_diagonal;
+        self
+    }
+
+    /// Set the factors the weight of a neighbour is scaled by for each dimension it is offset
+    /// along.
+    pub fn anisotropy(mut self, ax: f64, ay: f64, az: f64) -> Self {
+        self.anisotropy = [ax, ay, az];
+        self
+    }
+
+    /// Set whether the mesh wraps around in each dimension.
+    pub fn periodic(mut self, x: bool, y: bool, z: bool) -> Self {
+        self.periodic = [x, y, z];
+        self
+    }
+
+    /// Set the shift added to the diagonal.
+    pub fn shift(mut self, shift: f64) -> Self {
+        self.shift = shift;
+        self
+    }
+
+    /// Set the cell Péclet number of a flow in the direction of `flow`, which is discretised with
+    /// central differences between each point and its face neighbours.
+    pub fn convection(mut self, peclet: f64, flow: [f64; 3]) -> Self {
+        let speed = flow.iter().map(|v| v * v).sum::<f64>().sqrt();
+        self.convection = if speed > 0.0 {
+            flow.map(|v| peclet * v / speed)
+        } else {
+            [0.0; 3]
+        };
+        self
+    }
+
+    /// The position of the neighbour at an offset from a point of the mesh, wrapping around the
+    /// mesh in periodic dimensions. A periodic dimension shorter than three points wraps both
+    /// offsets along it onto the same point, which may be the point itself, so the callers sum
+    /// the weights of the offsets with the same neighbour.
+    ///
+    /// # Arguments
+    /// * `point` - The position of the point in the mesh.
+    /// * `offset` - The offset of the neighbour, from `-1` to `1` in each dimension.
+    /// * `mesh` - The size of the mesh in each dimension.
+    ///
+    /// # Return values
+    /// * `neighbour` - The position of the neighbour, if it is inside the (wrapped) mesh.
+    pub fn neighbour(
+        &self,
+        point: [usize; 3],
+        offset: [i32; 3],
+        mesh: [usize; 3],
+    ) -> Option<[usize; 3]> {
+        let mut neighbour = [0; 3];
+        for dim in 0..3 {
+            let position = point[dim] as i64 + offset[dim] as i64;
+            let size = mesh[dim] as i64;
+            neighbour[dim] = if self.periodic[dim] {
+                position.rem_euclid(size) as usize
+            } else if (0..size).contains(&position) {
+                position as usize
+            } else {
+                return None;
+            };
+        }
+        Some(neighbour)
+    }
+
+    /// The weight of a neighbour at an offset from a point, if it is part of the stencil.
+    ///
+    /// # Arguments
+    /// * `sx` - The offset in the x dimension, from `-1` to `1`.
+    /// * `sy` - The offset in the y dimension, from `-1` to `1`.
+    /// * `sz` - The offset in the z dimension, from `-1` to `1`.
+    pub fn weight(&self, sx: i32, sy: i32, sz: i32) -> Option<f64> {
+        if !self.shape.contains(sx, sy, sz) {
+            return None;
+        }
+        if (sx, sy, sz) == (0, 0, 0) {
+            return Some(self.diagonal + self.shift);
+        }
+        let weight = [sx, sy, sz]
+            .iter()
+            .zip(self.anisotropy.iter())
+            .filter(|(&offset, _)| offset != 0)
+            .fold(self.off_diagonal, |weight, (_, &factor)| weight * factor);
+        // The flow carries values downstream, so a point is coupled more strongly to its face
+        // neighbour upstream, and less strongly to the one downstream
+        let convection: f64 = if sx * sx + sy * sy + sz * sz == 1 {
+            [sx, sy, sz]
+                .iter()
+                .zip(self.convection.iter())
+                .map(|(&offset, &peclet)| offset as f64 * peclet / 2.0)
+                .sum()
+        } else {
+            0.0
+        };
+        Some(weight + convection)
+    }
+}
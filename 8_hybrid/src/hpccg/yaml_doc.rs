@@ -0,0 +1,264 @@
+use std::fs;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A value attached to a key of a YAML document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum YamlValue {
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl From<i32> for YamlValue {
+    fn from(value: i32) -> Self {
+        YamlValue::Int(value as i64)
+    }
+}
+
+impl From<i64> for YamlValue {
+    fn from(value: i64) -> Self {
+        YamlValue::Int(value)
+    }
+}
+
+impl From<usize> for YamlValue {
+    fn from(value: usize) -> Self {
+        YamlValue::Int(value as i64)
+    }
+}
+
+impl From<f64> for YamlValue {
+    fn from(value: f64) -> Self {
+        YamlValue::Float(value)
+    }
+}
+
+impl From<&str> for YamlValue {
+    fn from(value: &str) -> Self {
+        YamlValue::Str(value.to_owned())
+    }
+}
+
+impl From<String> for YamlValue {
+    fn from(value: String) -> Self {
+        YamlValue::Str(value)
+    }
+}
+
+impl std::fmt::Display for YamlValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            YamlValue::Int(value) => write!(f, "{value}"),
+            YamlValue::Float(value) => write!(f, "{}", format_double(*value)),
+            YamlValue::Str(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// Format a double the same way as the default C++ stream output, which is equivalent to the
+/// C `%g` conversion with six significant figures.
+fn format_double(value: f64) -> String {
+    if !value.is_finite() {
+        return value.to_string().to_lowercase();
+    }
+    if value == 0.0 {
+        return "0".to_owned();
+    }
+    // Round to six significant figures first, as this can change the exponent
+    let scientific = format!("{value:.5e}");
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or_default();
+    let strip = |digits: &str| {
+        if digits.contains('.') {
+            digits.trim_end_matches('0').trim_end_matches('.').to_owned()
+        } else {
+            digits.to_owned()
+        }
+    };
+    if !(-4..6).contains(&exponent) {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{sign}{:02}", strip(mantissa), exponent.abs())
+    } else {
+        strip(&format!("{value:.*}", (5 - exponent) as usize))
+    }
+}
+
+/// An element of a YAML document, which has either a value or a list of child elements.
+///
+/// # Fields
+/// * `key` - The key of the element.
+/// * `value` - The value of the element, which is emptied if children are added.
+/// * `children` - The nested elements under this key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct YamlElement {
+    pub key: String,
+    pub value: YamlValue,
+    pub children: Vec<YamlElement>,
+}
+
+impl YamlElement {
+    /// Create an element with a value and no children.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the element.
+    /// * `value` - The value of the element.
+    pub fn new(key: &str, value: impl Into<YamlValue>) -> Self {
+        YamlElement {
+            key: key.to_owned(),
+            value: value.into(),
+            children: vec![],
+        }
+    }
+
+    /// Add a child element, removing any value attached to this element.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the child element.
+    /// * `value` - The value of the child element, which should be `""` if it will have children.
+    ///
+    /// # Return values
+    /// * `element` - The newly added child element.
+    pub fn add(&mut self, key: &str, value: impl Into<YamlValue>) -> &mut YamlElement {
+        self.value = YamlValue::Str(String::new());
+        self.children.push(YamlElement::new(key, value));
+        self.children.last_mut().unwrap()
+    }
+
+    /// Find the first child element with a given key.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the child element.
+    pub fn get(&mut self, key: &str) -> Option<&mut YamlElement> {
+        self.children.iter_mut().find(|child| child.key == key)
+    }
+
+    /// Print this element and its children as lines of a YAML document.
+    ///
+    /// # Arguments
+    /// * `space` - The indentation of this element, with each level of children indented by
+    ///   two more spaces.
+    pub fn print_yaml(&self, space: &str) -> String {
+        let mut yaml = format!("{space}{}: {}\n", self.key, self.value);
+        let space = format!("{space}  ");
+        for child in self.children.iter() {
+            yaml += &child.print_yaml(&space);
+        }
+        yaml
+    }
+}
+
+/// A YAML document collecting the results of a run of a mini-application, laid out in the same
+/// way as the `YAML_Doc` class of the reference implementation.
+///
+/// # Fields
+/// * `mini_app_name` - The name of the mini-application.
+/// * `mini_app_version` - The version of the mini-application.
+/// * `destination_directory` - The directory to write the results file into.
+/// * `destination_file_name` - The root of the name of the results file.
+/// * `root` - The element holding the top level elements of the document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct YamlDoc {
+    pub mini_app_name: String,
+    pub mini_app_version: String,
+    pub destination_directory: PathBuf,
+    pub destination_file_name: String,
+    root: YamlElement,
+}
+
+impl YamlDoc {
+    /// Create an empty document, which will be written to the current directory as
+    /// `{mini_app_name}-{mini_app_version}_{date}.yaml`.
+    ///
+    /// # Arguments
+    /// * `mini_app_name` - The name of the mini-application.
+    /// * `mini_app_version` - The version of the mini-application.
+    pub fn new(mini_app_name: &str, mini_app_version: &str) -> Self {
+        YamlDoc {
+            mini_app_name: mini_app_name.to_owned(),
+            mini_app_version: mini_app_version.to_owned(),
+            destination_directory: PathBuf::from("."),
+            destination_file_name: format!("{mini_app_name}-{mini_app_version}_"),
+            root: YamlElement::new("", ""),
+        }
+    }
+
+    /// Print the document as YAML, without writing it to a file.
+    pub fn print_yaml(&self) -> String {
+        let mut yaml = format!(
+            "Mini-Application Name: {}\nMini-Application Version: {}\n",
+            self.mini_app_name, self.mini_app_version
+        );
+        for child in self.root.children.iter() {
+            yaml += &child.print_yaml("");
+        }
+        yaml
+    }
+
+    /// Print the document as YAML, and save it to a file in the destination directory, named
+    /// with the destination file name followed by the current (UTC) date and time.
+    ///
+    /// # Return values
+    /// * `yaml` - The YAML document.
+    pub fn generate_yaml(&self) -> io::Result<String> {
+        fs::create_dir_all(&self.destination_directory)?;
+        let file_name = format!("{}{}.yaml", self.destination_file_name, date_stamp());
+        self.write_yaml(self.destination_directory.join(file_name))
+    }
+
+    /// Print the document as YAML, and save it to the given file.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file to write.
+    ///
+    /// # Return values
+    /// * `yaml` - The YAML document.
+    pub fn write_yaml(&self, path: impl AsRef<Path>) -> io::Result<String> {
+        let yaml = self.print_yaml();
+        fs::write(path, &yaml)?;
+        Ok(yaml)
+    }
+}
+
+impl Deref for YamlDoc {
+    type Target = YamlElement;
+
+    fn deref(&self) -> &YamlElement {
+        &self.root
+    }
+}
+
+impl DerefMut for YamlDoc {
+    fn deref_mut(&mut self) -> &mut YamlElement {
+        &mut self.root
+    }
+}
+
+/// The current UTC date and time, formatted as `YYYY_MM_DD__hh_mm_ss`.
+fn date_stamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let (days, secs_of_day) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // Convert days since the epoch to a civil date (http://howardhinnant.github.io/date_algorithms.html)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}_{month:02}_{day:02}__{:02}_{:02}_{:02}",
+        secs_of_day / 3600,
+        (secs_of_day % 3600) / 60,
+        secs_of_day % 60
+    )
+}
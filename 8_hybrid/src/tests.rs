@@ -1,11 +1,22 @@
 #[cfg(test)]
 mod unit_tests {
+    use std::ops::ControlFlow;
+
     use mpi::environment::Universe;
     use once_cell::sync::Lazy;
     use serial_test::serial;
 
     use crate::hpccg::hpccg_internals::{ddot, sparsemv, waxpby};
-    use crate::hpccg::{compute_residual, solver, SparseMatrix};
+    use crate::hpccg::{
+        bicgstab, chronopoulos_gear, compute_residual, gmres, make_deep_local_matrix,
+        make_local_matrix, pcg, pipelined_cg, read_matrix_market_vector, solver, sstep_cg,
+        write_matrix_market_vector, Chebyshev, CoefficientField, ConvergenceReason, Decomposition,
+        DiffusionConfig, IncompleteCholesky, IterationInfo, Jacobi, KrylovBasis,
+        ManufacturedSolution, Method, Monitor, Multigrid, OutputFormat, Preconditioner,
+        PreconditionerKind, ProcessGrid, ResidualHistory, RunSummary, Smoother, SmootherKind,
+        SolverConfig, SparseMatrix, Stencil, StencilConfig, SymmetricGaussSeidel, Symmetry,
+        Timings, Verbosity, YamlDoc, YamlValue,
+    };
 
     // Use `once_cell` to define a shared MPI universe,` as rs-mpi panics when initialising a second time
     static UNIVERSE: Lazy<Universe> = Lazy::new(|| mpi::initialize().unwrap());
@@ -73,6 +84,399 @@ mod unit_tests {
         assert_eq!(guess, vec![0.0; 8]);
         assert_eq!(rhs, vec![20.0; 8]);
         assert_eq!(exact, vec![1.0; 8]);
+        assert_eq!(matrix.diagonal(), vec![27.0; 8]);
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_read_hpc_row() {
+        let data_file = std::env::temp_dir().join("hpccg_test_read_hpc_row.txt");
+        std::fs::write(
+            &data_file,
+            "3 7\n2 3 2\n2 4.0 0 -1.0 1\n3 -1.0 0 4.0 1 -1.0 2\n2 -1.0 1 4.0 2\n\
+             0.0 3.0 1.0\n0.0 2.0 1.0\n0.0 3.0 1.0\n",
+        )
+        .unwrap();
+        let (matrix, guess, rhs, exact) =
+            SparseMatrix::read_hpc_row(&data_file, &UNIVERSE.world()).unwrap();
+        std::fs::remove_file(&data_file).unwrap();
+
+        assert_eq!(matrix.total_nrow, 3);
+        assert_eq!(matrix.total_nnz, 7);
+        assert_eq!(matrix.local_nnz, 7);
+        assert_eq!(matrix.nnz_in_row, vec![2, 3, 2]);
+        assert_eq!(matrix.row_start_inds, vec![0, 2, 5]);
+        assert_eq!(
+            matrix.list_of_vals,
+            vec![4.0, -1.0, -1.0, 4.0, -1.0, -1.0, 4.0]
+        );
+        assert_eq!(matrix.list_of_inds, vec![0, 1, 0, 1, 2, 1, 2]);
+        assert_eq!(guess, vec![0.0; 3]);
+        assert_eq!(rhs, vec![3.0, 2.0, 3.0]);
+        assert_eq!(exact, vec![1.0; 3]);
+
+        assert!(SparseMatrix::read_hpc_row(&data_file, &UNIVERSE.world()).is_err());
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_matrix_market() {
+        let dir = std::env::temp_dir();
+        let matrix_file = dir.join("hpccg_test_matrix_market.mtx");
+        let vector_file = dir.join("hpccg_test_matrix_market_vector.mtx");
+
+        // A symmetric file only stores the lower triangle
+        std::fs::write(
+            &matrix_file,
+            "%%MatrixMarket matrix coordinate real symmetric\n\
+             % A 3x3 tridiagonal matrix\n\
+             3 3 5\n1 1 4.0\n2 1 -1.0\n2 2 4.0\n3 2 -1.0\n3 3 4.0\n",
+        )
+        .unwrap();
+        let mut matrix = SparseMatrix::read_matrix_market(&matrix_file, &UNIVERSE.world()).unwrap();
+        assert_eq!(matrix.total_nrow, 3);
+        assert_eq!(matrix.total_nnz, 7);
+        assert_eq!(matrix.nnz_in_row, vec![2, 3, 2]);
+        assert_eq!(matrix.row_start_inds, vec![0, 2, 5]);
+        assert_eq!(
+            matrix.list_of_vals,
+            vec![4.0, -1.0, -1.0, 4.0, -1.0, -1.0, 4.0]
+        );
+        assert_eq!(matrix.list_of_inds, vec![0, 1, 0, 1, 2, 1, 2]);
+
+        // Round trip through the symmetric format, then the general format after making the
+        // indices local
+        for symmetry in [Symmetry::Symmetric, Symmetry::General] {
+            if symmetry == Symmetry::General {
+                make_local_matrix(&mut matrix, &UNIVERSE.world());
+            }
+            matrix
+                .write_matrix_market(&matrix_file, symmetry, &UNIVERSE.world())
+                .unwrap();
+            let reread = SparseMatrix::read_matrix_market(&matrix_file, &UNIVERSE.world()).unwrap();
+            assert_eq!(reread.row_start_inds, matrix.row_start_inds);
+            assert_eq!(reread.list_of_vals, matrix.list_of_vals);
+            assert_eq!(reread.list_of_inds, vec![0, 1, 0, 1, 2, 1, 2]);
+        }
+
+        let rhs = vec![3.0, 2.0, 3.0];
+        write_matrix_market_vector(&vector_file, &rhs, &UNIVERSE.world()).unwrap();
+        assert_eq!(
+            read_matrix_market_vector(&vector_file, &UNIVERSE.world()).unwrap(),
+            rhs
+        );
+
+        // Errors name the offending line
+        std::fs::write(
+            &matrix_file,
+            "%%MatrixMarket matrix coordinate real general\n2 2 2\n1 1 1.0\n2 x 1.0\n",
+        )
+        .unwrap();
+        let err = SparseMatrix::read_matrix_market(&matrix_file, &UNIVERSE.world()).unwrap_err();
+        assert!(err.to_string().contains("line 4"));
+        assert!(read_matrix_market_vector(&matrix_file, &UNIVERSE.world()).is_err());
+
+        std::fs::remove_file(&matrix_file).unwrap();
+        std::fs::remove_file(&vector_file).unwrap();
+    }
+
+    #[test]
+    fn test_stencil() {
+        let counts: Vec<usize> = [
+            Stencil::SevenPoint,
+            Stencil::NineteenPoint,
+            Stencil::TwentySevenPoint,
+        ]
+        .iter()
+        .map(|shape| {
+            (-1..=1)
+                .flat_map(|sz| (-1..=1).flat_map(move |sy| (-1..=1).map(move |sx| (sx, sy, sz))))
+                .filter(|&(sx, sy, sz)| shape.contains(sx, sy, sz))
+                .count()
+        })
+        .collect();
+        assert_eq!(counts, vec![7, 19, 27]);
+        assert_eq!("19pt".parse(), Ok(Stencil::NineteenPoint));
+
+        let stencil = StencilConfig::new(Stencil::NineteenPoint)
+            .weights(10.0, -0.5)
+            .anisotropy(1.0, 2.0, 4.0);
+        assert_eq!(stencil.weight(0, 0, 0), Some(10.0));
+        assert_eq!(stencil.weight(1, 0, 0), Some(-0.5));
+        assert_eq!(stencil.weight(0, -1, 0), Some(-1.0));
+        assert_eq!(stencil.weight(0, 1, 1), Some(-4.0));
+        assert_eq!(stencil.weight(1, 1, 1), None);
+
+        // Convection makes the weights of face neighbours depend on the direction of the flow
+        let stencil = StencilConfig::new(Stencil::SevenPoint)
+            .weights(6.0, -1.0)
+            .convection(2.0, [0.0, 3.0, 0.0]);
+        assert_eq!(stencil.weight(0, 0, 0), Some(6.0));
+        assert_eq!(stencil.weight(0, -1, 0), Some(-2.0));
+        assert_eq!(stencil.weight(0, 1, 0), Some(0.0));
+        assert_eq!(stencil.weight(1, 0, 0), Some(-1.0));
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_sparse_matrix_7pt() {
+        let (matrix, _, rhs, _) = SparseMatrix::generate_matrix_with_stencil(
+            2,
+            2,
+            2,
+            Stencil::SevenPoint.into(),
+            &UNIVERSE.world(),
+        );
+        assert_eq!(matrix.nnz_in_row, vec![4; 8]);
+        assert_eq!(matrix.list_of_inds[..4], [0, 1, 2, 4]);
+        assert_eq!(rhs, vec![24.0; 8]);
+        assert_eq!("7".parse(), Ok(Stencil::SevenPoint));
+        assert!("9".parse::<Stencil>().is_err());
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_sparse_matrix_anisotropic() {
+        let stencil = StencilConfig::new(Stencil::NineteenPoint)
+            .weights(30.0, -1.5)
+            .anisotropy(1.0, 0.5, 2.0);
+        let (matrix, _, rhs, exact) =
+            SparseMatrix::generate_matrix_with_stencil(3, 3, 3, stencil, &UNIVERSE.world());
+        assert_eq!(matrix.local_nnz, 19 * 27);
+        assert_eq!(matrix.nnz_in_row[13], 19);
+        assert_eq!(matrix.nnz_in_row[0], 7);
+        // The right hand side is the product of the matrix with the all ones exact solution
+        assert_eq!(sparsemv(&matrix, &exact), rhs);
+    }
+
+    #[test]
+    fn test_process_grid() {
+        let shape = |size, decomposition| {
+            let grid = ProcessGrid::new(size, decomposition);
+            (grid.px, grid.py, grid.pz)
+        };
+        assert_eq!(shape(8, Decomposition::Grid), (2, 2, 2));
+        assert_eq!(shape(12, Decomposition::Grid), (2, 2, 3));
+        assert_eq!(shape(27, Decomposition::Grid), (3, 3, 3));
+        assert_eq!(shape(7, Decomposition::Grid), (1, 1, 7));
+        assert_eq!(shape(8, Decomposition::Slab), (1, 1, 8));
+        assert_eq!("grid".parse(), Ok(Decomposition::Grid));
+
+        let grid = ProcessGrid::new(12, Decomposition::Grid);
+        for rank in 0..12 {
+            let (rx, ry, rz) = grid.coords(rank);
+            assert_eq!(grid.rank(rx, ry, rz), rank);
+        }
+    }
+
+    #[test]
+    fn test_generate_matrix_on_grid() {
+        let (nx, ny, nz) = (2, 3, 2);
+        let grid = ProcessGrid::new(27, Decomposition::Grid);
+        let stencil = StencilConfig::from(Stencil::TwentySevenPoint);
+
+        // Assemble the rows of the whole matrix from each processor's brick
+        let mut entries = std::collections::HashMap::new();
+        let mut neighbours = vec![];
+        for rank in 0..grid.size() {
+            let (matrix, _, rhs, _) =
+                SparseMatrix::generate_matrix_on_grid(nx, ny, nz, stencil, grid, rank);
+            assert_eq!(matrix.start_row, rank * nx * ny * nz);
+            let mut owners = std::collections::HashSet::new();
+            for (i, &row_rhs) in rhs.iter().enumerate() {
+                let start = matrix.row_start_inds[i];
+                let row = &matrix.list_of_vals[start..start + matrix.nnz_in_row[i]];
+                assert_eq!(row.iter().sum::<f64>(), row_rhs);
+                for j in start..start + matrix.nnz_in_row[i] {
+                    let col = matrix.list_of_inds[j] as usize;
+                    entries.insert((matrix.start_row + i, col), matrix.list_of_vals[j]);
+                    if !(matrix.start_row..=matrix.stop_row).contains(&col) {
+                        owners.insert(col / matrix.local_nrow);
+                    }
+                }
+            }
+            neighbours.push(owners.len());
+        }
+        // The centre brick touches every other brick, and a corner brick touches seven
+        assert_eq!(neighbours[13], 26);
+        assert_eq!(neighbours[0], 7);
+
+        // The whole matrix is the same as a single brick of the whole mesh, with the rows
+        // renumbered, so it has the same number of non-zeroes and is still symmetric
+        let (whole, _, _, _) = SparseMatrix::generate_matrix_on_grid(
+            3 * nx,
+            3 * ny,
+            3 * nz,
+            stencil,
+            ProcessGrid::new(1, Decomposition::Grid),
+            0,
+        );
+        assert_eq!(entries.len(), whole.nnz_in_row.iter().sum::<usize>());
+        for (&(row, col), &val) in entries.iter() {
+            assert_eq!(entries.get(&(col, row)), Some(&val));
+        }
+    }
+
+    #[test]
+    fn test_generate_matrix_on_grid_periodic() {
+        let (nx, ny, nz) = (2, 2, 3);
+        let grid = ProcessGrid::new(27, Decomposition::Grid);
+        let stencil = StencilConfig::default().periodic(true, true, true);
+
+        let mut entries = std::collections::HashMap::new();
+        for rank in 0..grid.size() {
+            let (matrix, _, rhs, _) =
+                SparseMatrix::generate_matrix_on_grid(nx, ny, nz, stencil, grid, rank);
+            assert_eq!(matrix.nnz_in_row, vec![27; nx * ny * nz]);
+            assert_eq!(rhs, vec![1.0; nx * ny * nz]);
+            let owners: std::collections::HashSet<usize> = matrix
+                .list_of_inds
+                .iter()
+                .map(|&col| col as usize / matrix.local_nrow)
+                .filter(|&owner| owner != rank)
+                .collect();
+            // Every brick touches every other brick once the mesh wraps around
+            assert_eq!(owners.len(), 26);
+            for i in 0..matrix.local_nrow {
+                let start = matrix.row_start_inds[i];
+                for j in start..start + matrix.nnz_in_row[i] {
+                    let col = matrix.list_of_inds[j] as usize;
+                    entries.insert((matrix.start_row + i, col), matrix.list_of_vals[j]);
+                }
+            }
+        }
+        for (&(row, col), &val) in entries.iter() {
+            assert_eq!(entries.get(&(col, row)), Some(&val));
+        }
+
+        // A periodic dimension shorter than three points wraps both of its offsets onto one
+        // point, which has a single entry with their weights summed
+        let grid = ProcessGrid::new(1, Decomposition::Grid);
+        let stencil = StencilConfig::new(Stencil::SevenPoint)
+            .weights(6.0, -1.0)
+            .periodic(true, false, false)
+            .shift(0.5);
+        for (nx, cols, vals) in [
+            (1, vec![0, 1, 3], vec![4.5, -1.0, -1.0]),
+            (2, vec![1, 0, 2, 6], vec![-2.0, 6.5, -1.0, -1.0]),
+        ] {
+            let (matrix, _, rhs, _) =
+                SparseMatrix::generate_matrix_on_grid(nx, 3, 3, stencil, grid, 0);
+            assert_eq!(matrix.nnz_in_row[0], cols.len());
+            assert_eq!(matrix.list_of_inds[..cols.len()], cols);
+            assert_eq!(matrix.list_of_vals[..vals.len()], vals);
+            for (row, &start) in matrix.row_start_inds.iter().enumerate() {
+                let nnz = matrix.nnz_in_row[row];
+                let columns = &matrix.list_of_inds[start..start + nnz];
+                let unique: std::collections::HashSet<&i32> = columns.iter().collect();
+                assert_eq!(unique.len(), nnz);
+                let row_sum: f64 = matrix.list_of_vals[start..start + nnz].iter().sum();
+                assert_eq!(row_sum, rhs[row]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_diffusion_config() {
+        let config = DiffusionConfig::new(CoefficientField::Layered)
+            .contrast(10.0)
+            .layers(2);
+        let layers: Vec<f64> = (0..4)
+            .map(|gz| config.coefficient([0, 0, gz], [4, 4, 4]))
+            .collect();
+        assert_eq!(layers, vec![1.0, 1.0, 10.0, 10.0]);
+
+        let config = DiffusionConfig::new(CoefficientField::Random).contrast(1e4);
+        let coefficients: Vec<f64> = (0..64)
+            .map(|gx| config.coefficient([gx, 0, 0], [64, 1, 1]))
+            .collect();
+        assert!(coefficients.iter().all(|&k| (1.0..1e4).contains(&k)));
+        assert_ne!(
+            config.coefficient([5, 6, 7], [8, 8, 8]),
+            config.seed(1).coefficient([5, 6, 7], [8, 8, 8])
+        );
+        assert_eq!("channels".parse(), Ok(CoefficientField::Channelised));
+        assert_eq!("sine".parse(), Ok(ManufacturedSolution::Sine));
+    }
+
+    #[test]
+    fn test_generate_diffusion_matrix_on_grid() {
+        let diffusion = DiffusionConfig::new(CoefficientField::Random)
+            .seed(3)
+            .solution(ManufacturedSolution::Linear);
+        // Gather the entries of the whole matrix, keyed by the positions of the cells in the mesh
+        let assemble = |n: usize, grid: ProcessGrid| {
+            let mut entries = std::collections::HashMap::new();
+            let mut rhs = std::collections::HashMap::new();
+            let mut exact = std::collections::HashMap::new();
+            let cell = |row: usize| {
+                let (rx, ry, rz) = grid.coords(row / (n * n * n));
+                let local = row % (n * n * n);
+                (
+                    rx * n + local % n,
+                    ry * n + local / n % n,
+                    rz * n + local / (n * n),
+                )
+            };
+            for rank in 0..grid.size() {
+                let (matrix, _, b, x) =
+                    SparseMatrix::generate_diffusion_matrix_on_grid(n, n, n, diffusion, grid, rank);
+                for i in 0..matrix.local_nrow {
+                    let row = cell(matrix.start_row + i);
+                    let start = matrix.row_start_inds[i];
+                    for j in start..start + matrix.nnz_in_row[i] {
+                        let col = cell(matrix.list_of_inds[j] as usize);
+                        entries.insert((row, col), matrix.list_of_vals[j]);
+                    }
+                    rhs.insert(row, b[i]);
+                    exact.insert(row, x[i]);
+                }
+            }
+            (entries, rhs, exact)
+        };
+
+        let (entries, rhs, exact) = assemble(2, ProcessGrid::new(8, Decomposition::Grid));
+        // The problem is the same as on a single processor with the whole mesh
+        let (whole_entries, whole_rhs, _) = assemble(4, ProcessGrid::new(1, Decomposition::Grid));
+        assert_eq!(entries, whole_entries);
+        assert_eq!(rhs, whole_rhs);
+        let mut products = std::collections::HashMap::new();
+        for (&(row, col), &val) in entries.iter() {
+            assert_eq!(entries.get(&(col, row)), Some(&val));
+            *products.entry(row).or_insert(0.0) += val * exact[&col];
+        }
+        for (row, product) in products {
+            assert!((product - rhs[&row]).abs() < 1e-9 * rhs[&row].abs().max(1.0));
+        }
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_dump_matlab() {
+        let (mut matrix, _, _, _) = SparseMatrix::generate_matrix(2, 2, 2, &UNIVERSE.world());
+        make_local_matrix(&mut matrix, &UNIVERSE.world());
+        assert_eq!(matrix.global_col_inds()[..8], [0, 1, 2, 3, 4, 5, 6, 7]);
+
+        matrix.dump_matlab(0).unwrap();
+        let dump = std::fs::read_to_string("mat0.dat").unwrap();
+        std::fs::remove_file("mat0.dat").unwrap();
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 64);
+        assert_eq!(lines[0], " 1 1 2.7000000000000000e+01");
+        assert_eq!(lines[1], " 1 2 -1.0000000000000000e+00");
+        assert_eq!(lines[63], " 8 8 2.7000000000000000e+01");
+
+        // External columns are still negated partway through `make_local_matrix`
+        matrix.list_of_inds[1] = -(12 + 1);
+        assert_eq!(matrix.global_col_inds()[..2], [0, 12]);
+
+        // Before `make_local_matrix`, the indices are global even if the rows do not start at zero
+        let (mut matrix, _, _, _) = SparseMatrix::generate_matrix(2, 2, 2, &UNIVERSE.world());
+        assert!(!matrix.is_local);
+        matrix.start_row += 8;
+        matrix.stop_row += 8;
+        matrix.list_of_inds.iter_mut().for_each(|ind| *ind += 8);
+        assert_eq!(matrix.global_col_inds()[..3], [8, 9, 10]);
     }
 
     #[test]
@@ -117,26 +521,915 @@ mod unit_tests {
 
     #[test]
     #[serial] // Tests using MPI must not run concurrently
-    fn test_solver() {
-        let (nx, ny, nz) = (5, 5, 5);
+    fn test_chronopoulos_gear() {
+        let world = UNIVERSE.world();
+        let (mut matrix, guess, rhs, exact) = SparseMatrix::generate_matrix(6, 6, 6, &world);
+        make_local_matrix(&mut matrix, &world);
+        let mut residuals = vec![];
+        let mut config = SolverConfig::new()
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet)
+            .callback(|_, normr| residuals.push(normr));
+        let report = chronopoulos_gear(&mut matrix, &rhs, &guess, &mut config, &world);
+        drop(config);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.final_residual <= 1e-10);
+        for (actual, expected) in report.solution.iter().zip(exact) {
+            assert!((expected - actual).abs() < 1e-8);
+        }
+
+        // The iterations and residuals are those of the conjugate gradient solver, up to rounding
+        let mut cg_residuals = vec![];
+        let mut config = SolverConfig::new()
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet)
+            .callback(|_, normr| cg_residuals.push(normr));
+        let cg_report = solver(&mut matrix, &rhs, &guess, &mut config, &world);
+        drop(config);
+        assert_eq!(report.iterations, cg_report.iterations);
+        assert_eq!(residuals.len(), cg_residuals.len());
+        for (actual, expected) in residuals.iter().zip(cg_residuals) {
+            assert!((expected - actual).abs() <= 1e-6 * expected);
+        }
+        assert_eq!("chrongear".parse(), Ok(Method::ChronopoulosGear));
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_matrix_powers() {
+        let world = UNIVERSE.world();
+        let (mut matrix, _, _, _) = SparseMatrix::generate_matrix(4, 4, 4, &world);
+        make_local_matrix(&mut matrix, &world);
+        let nrow = matrix.local_nrow;
+        let powers = make_deep_local_matrix(&matrix, 3, &world);
+        // A single processor has no ghost region
+        assert_eq!(powers.level_ends, vec![nrow; 4]);
+
+        let vector: Vec<f64> = (0..nrow).map(|i| (i as f64).sin()).collect();
+        let extended = powers.exchange(&[&vector, &vector], &world);
+        assert_eq!(extended, vec![vector.clone(), vector.clone()]);
+
+        // The monomial basis is the repeated products with the matrix
+        let basis = powers.powers(&extended[0], &[0.0; 3], 1.0);
+        let mut expected = vector.clone();
+        for basis_vector in basis.iter().skip(1) {
+            expected = sparsemv(&matrix, &expected);
+            assert_eq!(basis_vector, &expected);
+        }
+        // A shifted and scaled basis subtracts a multiple of the previous vector
+        let basis = powers.powers(&extended[0], &[2.0], 4.0);
+        let product = sparsemv(&matrix, &vector);
+        for ((actual, product), value) in basis[1].iter().zip(product).zip(vector) {
+            assert!((actual - (product - 2.0 * value) / 4.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_sstep_cg() {
+        let world = UNIVERSE.world();
+        let (mut matrix, guess, rhs, exact) = SparseMatrix::generate_matrix(6, 6, 6, &world);
+        make_local_matrix(&mut matrix, &world);
+        let mut config = SolverConfig::new()
+            .tolerance(1e-8)
+            .verbosity(Verbosity::Quiet);
+        let cg_report = solver(&mut matrix, &rhs, &guess, &mut config, &world);
+
+        for basis in [KrylovBasis::Monomial, KrylovBasis::Newton] {
+            for step_size in [1, 3, 5] {
+                let mut residuals = vec![];
+                let mut config = SolverConfig::new()
+                    .tolerance(1e-8)
+                    .verbosity(Verbosity::Quiet)
+                    .step_size(step_size)
+                    .basis(basis)
+                    .callback(|_, normr| residuals.push(normr));
+                let report = sstep_cg(&mut matrix, &rhs, &guess, &mut config, &world);
+                drop(config);
+                assert_eq!(report.reason, ConvergenceReason::Converged);
+                assert_eq!(residuals.len(), report.iterations as usize + 1);
+                // In exact arithmetic, the iterates are those of the conjugate gradient solver
+                assert!((report.iterations - cg_report.iterations).abs() <= 2);
+                for (actual, expected) in report.solution.iter().zip(exact.iter()) {
+                    assert!((expected - actual).abs() < 1e-6);
+                }
+            }
+        }
+        assert_eq!("sstep".parse(), Ok(Method::SStepCg));
+        assert_eq!("newton".parse(), Ok(KrylovBasis::Newton));
+        assert!("chebyshev".parse::<KrylovBasis>().is_err());
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_stopping_criteria() {
+        let world = UNIVERSE.world();
+        let (mut matrix, guess, rhs, _) = SparseMatrix::generate_matrix(6, 6, 6, &world);
+        make_local_matrix(&mut matrix, &world);
+
+        let mut residuals = vec![];
+        let mut config = SolverConfig::new()
+            .max_iterations(500)
+            .relative_tolerance(1e-6)
+            .verbosity(Verbosity::Quiet)
+            .callback(|_, normr| residuals.push(normr));
+        let report = solver(&mut matrix, &rhs, &guess, &mut config, &world);
+        drop(config);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.final_residual <= 1e-6 * residuals[0]);
+        assert!(residuals[residuals.len() - 2] > 1e-6 * residuals[0]);
+
+        let normb = rhs.iter().map(|b| b * b).sum::<f64>().sqrt();
+        let mut config = SolverConfig::new()
+            .max_iterations(500)
+            .rhs_tolerance(1e-8)
+            .verbosity(Verbosity::Quiet);
+        let report = bicgstab(&mut matrix, &rhs, &guess, &mut config, &world);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.final_residual <= 1e-8 * normb);
+
+        // GMRES restarts from the true residual, which stops getting smaller at the limit of the
+        // rounding
+        let mut config = SolverConfig::new()
+            .max_iterations(500)
+            .restart(5)
+            .stagnation_window(10)
+            .verbosity(Verbosity::Quiet);
+        let report = gmres(&mut matrix, &rhs, &guess, &mut config, &world);
+        assert_eq!(report.reason, ConvergenceReason::Stagnated);
+        assert!(report.iterations < 100);
+
+        let mut config = SolverConfig::new()
+            .divergence_tolerance(0.5)
+            .verbosity(Verbosity::Quiet);
+        let report = solver(&mut matrix, &rhs, &guess, &mut config, &world);
+        assert_eq!(report.reason, ConvergenceReason::Diverged);
+        assert_eq!(report.iterations, 0);
+
+        // The conjugate gradient method does not converge for a non-symmetric matrix, so the
+        // residual grows past the limit partway through the run
+        let stencil = StencilConfig::convection_diffusion(1.5);
+        let (mut matrix, guess, rhs, _) =
+            SparseMatrix::generate_matrix_with_stencil(6, 6, 6, stencil, &world);
+        make_local_matrix(&mut matrix, &world);
+        let nrow = matrix.local_nrow;
+        let mut config = SolverConfig::new()
+            .divergence_tolerance(1.5)
+            .verbosity(Verbosity::Quiet)
+            .residual_history();
+        let report = solver(&mut matrix, &rhs, &guess, &mut config, &world);
+        assert_eq!(report.reason, ConvergenceReason::Diverged);
+        assert!(report.iterations > 1);
+        let history = &report.residual_history;
+        assert!(*history.last().unwrap() > 1.5 * history[0]);
+
+        // The step which diverged is undone, so the solution is the last iterate within the limit
+        assert_eq!(history.len(), report.iterations as usize + 2);
+        assert_eq!(report.final_residual, history[history.len() - 2]);
+        assert!(report.final_residual <= 1.5 * history[0]);
+        assert!(report.solution.iter().all(|value| value.is_finite()));
+        let residual = waxpby(nrow, 1.0, &rhs, -1.0, &sparsemv(&matrix, &report.solution));
+        let normr = ddot(nrow, &residual, &residual, &mut 0.0, &world).sqrt();
+        assert!((normr - report.final_residual).abs() < 1e-8 * normr);
+
+        // A negative definite matrix has no direction of positive curvature
+        let stencil = StencilConfig::new(Stencil::SevenPoint).weights(-6.0, 1.0);
+        let (mut matrix, guess, rhs, _) =
+            SparseMatrix::generate_matrix_with_stencil(6, 6, 6, stencil, &world);
+        make_local_matrix(&mut matrix, &world);
+        let mut config = SolverConfig::new().verbosity(Verbosity::Quiet);
+        for report in [
+            solver(&mut matrix, &rhs, &guess, &mut config, &world),
+            chronopoulos_gear(&mut matrix, &rhs, &guess, &mut config, &world),
+            sstep_cg(&mut matrix, &rhs, &guess, &mut config, &world),
+            pipelined_cg(&mut matrix, &rhs, &guess, &mut config, &world),
+        ] {
+            assert_eq!(report.reason, ConvergenceReason::Breakdown);
+            assert_eq!(report.iterations, 0);
+            assert_eq!(report.solution, guess);
+        }
+        assert_eq!(ConvergenceReason::Stagnated.to_string(), "stagnated");
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_monitor() {
+        /// A monitor which stops the solver at an iteration, and keeps the state it is given.
+        struct StopAt(i32, Vec<IterationInfo>);
+
+        impl Monitor for StopAt {
+            fn monitor(&mut self, info: &IterationInfo) -> ControlFlow<()> {
+                self.1.push(*info);
+                if info.iteration >= self.0 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+        }
+
+        let world = UNIVERSE.world();
+        let (mut matrix, guess, rhs, _) = SparseMatrix::generate_matrix(6, 6, 6, &world);
+        make_local_matrix(&mut matrix, &world);
+
+        let mut residuals = vec![];
+        let mut stop_at = StopAt(5, vec![]);
+        let mut config = SolverConfig::new()
+            .verbosity(Verbosity::Quiet)
+            .residual_history()
+            .monitor(&mut stop_at)
+            .callback(|_, normr| residuals.push(normr));
+        let report = solver(&mut matrix, &rhs, &guess, &mut config, &world);
+        drop(config);
+        assert_eq!(report.reason, ConvergenceReason::Stopped);
+        assert_eq!(report.iterations, 5);
+        assert_eq!(report.residual_history, residuals);
+        assert_eq!(report.residual_history.len(), 6);
+
+        // The solution is the iterate whose residual the monitor was given, and the iterations are
+        // the steps taken, which are as many as in a run limited to them
+        let nrow = matrix.local_nrow;
+        let mut config = SolverConfig::new()
+            .verbosity(Verbosity::Quiet)
+            .residual_history()
+            .monitor(StopAt(5, vec![]));
+        let mut limited = SolverConfig::new()
+            .max_iterations(6)
+            .verbosity(Verbosity::Quiet);
+        for (report, steps) in [
+            (
+                report,
+                solver(&mut matrix, &rhs, &guess, &mut limited, &world),
+            ),
+            (
+                chronopoulos_gear(&mut matrix, &rhs, &guess, &mut config, &world),
+                chronopoulos_gear(&mut matrix, &rhs, &guess, &mut limited, &world),
+            ),
+            (
+                sstep_cg(&mut matrix, &rhs, &guess, &mut config, &world),
+                sstep_cg(&mut matrix, &rhs, &guess, &mut limited, &world),
+            ),
+            (
+                pipelined_cg(&mut matrix, &rhs, &guess, &mut config, &world),
+                pipelined_cg(&mut matrix, &rhs, &guess, &mut limited, &world),
+            ),
+        ] {
+            assert_eq!(report.reason, ConvergenceReason::Stopped);
+            assert_eq!((report.iterations, steps.iterations), (5, 5));
+            assert_eq!(report.solution, steps.solution);
+            // The initial residual is recorded once, and then the residual of each step
+            let history = &report.residual_history;
+            assert_eq!(history.len(), report.iterations as usize + 1);
+            assert!(history.windows(2).all(|pair| pair[0] != pair[1]));
+            let residual = waxpby(nrow, 1.0, &rhs, -1.0, &sparsemv(&matrix, &report.solution));
+            let normr = ddot(nrow, &residual, &residual, &mut 0.0, &world).sqrt();
+            assert!((normr - report.final_residual).abs() < 1e-8 * normr);
+        }
+
+        let infos = stop_at.1;
+        assert!((0..).zip(&infos).all(|(k, info)| info.iteration == k));
+        assert_eq!((infos[0].alpha, infos[0].beta), (None, None));
+        assert!(infos[2].alpha.unwrap() > 0.0 && infos[2].beta.unwrap() > 0.0);
+        assert!(infos
+            .windows(2)
+            .all(|pair| pair[0].elapsed <= pair[1].elapsed));
+
+        // The s-step solver can stop part way through the steps of a reduction
+        let mut history = ResidualHistory::default();
+        let mut config = SolverConfig::new()
+            .verbosity(Verbosity::Quiet)
+            .step_size(4)
+            .monitor(StopAt(6, vec![]))
+            .monitor(&mut history);
+        let report = sstep_cg(&mut matrix, &rhs, &guess, &mut config, &world);
+        drop(config);
+        assert_eq!(report.reason, ConvergenceReason::Stopped);
+        assert_eq!(report.iterations, 6);
+        assert_eq!(report.residual_history.len(), 7);
+        assert!(history.residuals.is_empty());
+
+        // Nothing is recorded without a residual history monitor
+        let mut config = SolverConfig::new()
+            .max_iterations(3)
+            .verbosity(Verbosity::Quiet);
+        let report = chronopoulos_gear(&mut matrix, &rhs, &guess, &mut config, &world);
+        assert!(report.residual_history.is_empty());
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_bicgstab() {
+        let stencil = StencilConfig::convection_diffusion(1.5);
         let (mut matrix, guess, rhs, exact) =
-            SparseMatrix::generate_matrix(nx, ny, nz, &UNIVERSE.world());
-        let max_iter = 150;
-        let tolerance = 5e-40;
-        let (result, iterations, normr, _) = solver(
+            SparseMatrix::generate_matrix_with_stencil(6, 6, 6, stencil, &UNIVERSE.world());
+        make_local_matrix(&mut matrix, &UNIVERSE.world());
+        let mut residuals = vec![];
+        let mut config = SolverConfig::new()
+            .max_iterations(200)
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet)
+            .callback(|_, normr| residuals.push(normr));
+        let report = bicgstab(&mut matrix, &rhs, &guess, &mut config, &UNIVERSE.world());
+        drop(config);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.final_residual <= 1e-10);
+        assert_eq!(residuals.len(), report.iterations as usize + 1);
+        for (actual, expected) in report.solution.iter().zip(exact) {
+            assert!((expected - actual).abs() < 1e-8);
+        }
+
+        // The half step is exact for a multiple of the identity, which leaves `omega` undefined
+        let stencil = StencilConfig::new(Stencil::SevenPoint).weights(2.0, 0.0);
+        let (mut matrix, guess, rhs, exact) =
+            SparseMatrix::generate_matrix_with_stencil(3, 3, 3, stencil, &UNIVERSE.world());
+        make_local_matrix(&mut matrix, &UNIVERSE.world());
+        let mut config = SolverConfig::new().verbosity(Verbosity::Quiet);
+        let report = bicgstab(&mut matrix, &rhs, &guess, &mut config, &UNIVERSE.world());
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert_eq!(report.iterations, 1);
+        assert_eq!(report.final_residual, 0.0);
+        assert_eq!(report.solution, exact);
+
+        // With a zero diagonal, the first search direction is orthogonal to the shadow residual
+        let stencil = StencilConfig::new(Stencil::SevenPoint).weights(0.0, 1.0);
+        let (mut matrix, guess, mut rhs, _) =
+            SparseMatrix::generate_matrix_with_stencil(3, 3, 3, stencil, &UNIVERSE.world());
+        make_local_matrix(&mut matrix, &UNIVERSE.world());
+        rhs.fill(0.0);
+        rhs[0] = 1.0;
+        let report = bicgstab(&mut matrix, &rhs, &guess, &mut config, &UNIVERSE.world());
+        assert_eq!(report.reason, ConvergenceReason::Breakdown);
+        assert_eq!(report.iterations, 0);
+        assert_eq!(report.solution, guess);
+
+        assert_eq!("bicgstab".parse(), Ok(Method::BiCgStab));
+        assert_eq!(Method::ConjugateGradient.to_string(), "cg");
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_gmres() {
+        let stencil = StencilConfig::convection_diffusion(2.5);
+        let (mut matrix, guess, rhs, exact) =
+            SparseMatrix::generate_matrix_with_stencil(6, 6, 6, stencil, &UNIVERSE.world());
+        make_local_matrix(&mut matrix, &UNIVERSE.world());
+        for reorthogonalise in [false, true] {
+            let mut residuals = vec![];
+            let mut config = SolverConfig::new()
+                .max_iterations(300)
+                .tolerance(1e-10)
+                .verbosity(Verbosity::Quiet)
+                .restart(10)
+                .reorthogonalise(reorthogonalise)
+                .callback(|_, normr| residuals.push(normr));
+            let report = gmres(&mut matrix, &rhs, &guess, &mut config, &UNIVERSE.world());
+            drop(config);
+            assert_eq!(report.reason, ConvergenceReason::Converged);
+            assert!(report.final_residual <= 1e-10);
+            assert_eq!(residuals.len(), report.iterations as usize + 1);
+            // The residual never increases within a cycle, as each iteration minimises it over a
+            // larger space
+            for cycle in residuals[1..].chunks(10) {
+                assert!(cycle.windows(2).all(|pair| pair[1] <= pair[0]));
+            }
+            for (actual, expected) in report.solution.iter().zip(exact.iter()) {
+                assert!((expected - actual).abs() < 1e-8);
+            }
+        }
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_pipelined_cg() {
+        let world = UNIVERSE.world();
+        let (mut matrix, guess, rhs, exact) = SparseMatrix::generate_matrix(6, 6, 6, &world);
+        make_local_matrix(&mut matrix, &world);
+        let mut residuals = vec![];
+        let mut config = SolverConfig::new()
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet)
+            .callback(|_, normr| residuals.push(normr));
+        let report = pipelined_cg(&mut matrix, &rhs, &guess, &mut config, &world);
+        drop(config);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.final_residual <= 1e-10);
+        assert_eq!(residuals.len(), report.iterations as usize + 1);
+        assert!(report.times.reduction_wait >= 0.0);
+        assert!(report.times.allreduce >= report.times.reduction_wait);
+        for (actual, expected) in report.solution.iter().zip(exact) {
+            assert!((expected - actual).abs() < 1e-8);
+        }
+
+        // In exact arithmetic, the iterates are the same as those of the conjugate gradient solver
+        let mut config = SolverConfig::new()
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet);
+        let cg_report = solver(&mut matrix, &rhs, &guess, &mut config, &world);
+        assert!((report.iterations - cg_report.iterations).abs() <= 1);
+        assert_eq!("pipecg".parse(), Ok(Method::PipelinedCg));
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_pcg() {
+        let diffusion = DiffusionConfig::new(CoefficientField::Layered).contrast(1e4);
+        let (mut matrix, guess, rhs, exact) =
+            SparseMatrix::generate_diffusion_matrix(8, 8, 8, diffusion, &UNIVERSE.world());
+        make_local_matrix(&mut matrix, &UNIVERSE.world());
+        let mut jacobi = Jacobi::new(&matrix);
+        assert_eq!(jacobi.inverse_diagonal.len(), matrix.local_nrow);
+
+        let mut residuals = vec![];
+        let mut config = SolverConfig::new()
+            .max_iterations(500)
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet)
+            .callback(|_, normr| residuals.push(normr));
+        let report = pcg(
             &mut matrix,
             &rhs,
             &guess,
-            max_iter,
-            tolerance,
+            &mut jacobi,
+            &mut config,
             &UNIVERSE.world(),
         );
+        drop(config);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.final_residual <= 1e-10);
+        assert_eq!(residuals.len(), report.iterations as usize + 1);
+        assert!(report.times.precondition > 0.0);
+        for (actual, expected) in report.solution.iter().zip(exact.iter()) {
+            assert!((expected - actual).abs() < 1e-6);
+        }
+
+        // Scaling by the diagonal evens out the jumps in the coefficients, so fewer iterations
+        // are needed than without a preconditioner
+        let mut config = SolverConfig::new()
+            .max_iterations(500)
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet);
+        let unpreconditioned = solver(&mut matrix, &rhs, &guess, &mut config, &UNIVERSE.world());
+        assert!(report.iterations < unpreconditioned.iterations);
+        assert_eq!(unpreconditioned.times.precondition, 0.0);
+
+        assert_eq!("pcg".parse(), Ok(Method::Pcg));
+        assert_eq!("jacobi".parse(), Ok(PreconditionerKind::Jacobi));
+        assert!("ilu".parse::<PreconditionerKind>().is_err());
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_multicolour_ordering() {
+        let (nx, ny, nz) = (4, 3, 5);
+        let (mut matrix, _, _, _) = SparseMatrix::generate_matrix(nx, ny, nz, &UNIVERSE.world());
+        make_local_matrix(&mut matrix, &UNIVERSE.world());
+        let colours = matrix.multicolour_ordering();
+        assert_eq!(colours.len(), 8);
+        assert_eq!(colours[0][..3], [0, 2, nx * 2]);
+        let coloured: usize = colours.iter().map(Vec::len).sum();
+        assert_eq!(coloured, matrix.local_nrow);
+        for rows in colours.iter() {
+            for &row in rows {
+                let start = matrix.row_start_inds[row];
+                let cols = &matrix.list_of_inds[start..start + matrix.nnz_in_row[row]];
+                assert!(cols
+                    .iter()
+                    .all(|&col| col as usize == row || !rows.contains(&(col as usize))));
+            }
+        }
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_symmetric_gauss_seidel() {
+        let world = UNIVERSE.world();
+        let (mut matrix, _, _, _) = SparseMatrix::generate_matrix(4, 4, 4, &world);
+        make_local_matrix(&mut matrix, &world);
+        let mut symgs = SymmetricGaussSeidel::new(&matrix, &world);
+        // The preconditioner is symmetric, so `x·M⁻¹y = y·M⁻¹x`
+        let x: Vec<f64> = (0..64).map(|i| (i as f64).sin()).collect();
+        let y: Vec<f64> = (0..64).map(|i| (i as f64).cos()).collect();
+        let xy: f64 = x.iter().zip(symgs.apply(&y)).map(|(a, b)| a * b).sum();
+        let yx: f64 = y.iter().zip(symgs.apply(&x)).map(|(a, b)| a * b).sum();
+        assert!((xy - yx).abs() < 1e-12);
+
+        // The Laplacian has a constant diagonal, so Jacobi is no better than no preconditioner
+        let stencil = StencilConfig::new(Stencil::SevenPoint).weights(6.0, -1.0);
+        let (mut matrix, guess, rhs, exact) =
+            SparseMatrix::generate_matrix_with_stencil(16, 16, 16, stencil, &world);
+        make_local_matrix(&mut matrix, &world);
+        let mut config = SolverConfig::new()
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet);
+        let mut symgs = SymmetricGaussSeidel::new(&matrix, &world);
+        let report = pcg(&mut matrix, &rhs, &guess, &mut symgs, &mut config, &world);
+        let mut jacobi = Jacobi::new(&matrix);
+        let jacobi_report = pcg(&mut matrix, &rhs, &guess, &mut jacobi, &mut config, &world);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.iterations < jacobi_report.iterations);
+        for (actual, expected) in report.solution.iter().zip(exact) {
+            assert!((expected - actual).abs() < 1e-8);
+        }
+        assert_eq!(
+            "symgs".parse(),
+            Ok(PreconditionerKind::SymmetricGaussSeidel)
+        );
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_incomplete_cholesky() {
+        let world = UNIVERSE.world();
+        // A tridiagonal matrix has no fill-in, so its incomplete factorisation is exact
+        let stencil = StencilConfig::new(Stencil::SevenPoint).weights(2.0, -1.0);
+        let (mut matrix, _, rhs, exact) =
+            SparseMatrix::generate_matrix_with_stencil(10, 1, 1, stencil, &world);
+        make_local_matrix(&mut matrix, &world);
+        let mut ic = IncompleteCholesky::new(&matrix).unwrap();
+        assert_eq!(ic.forward_levels.len(), 10);
+        assert_eq!(ic.upper[0], vec![(1, ic.lower[1][0].1)]);
+        for (actual, expected) in ic.apply(&rhs).iter().zip(exact) {
+            assert!((expected - actual).abs() < 1e-12);
+        }
+
+        // Rows of the 7-point stencil on the same anti-diagonal plane of the mesh are independent
+        let stencil = StencilConfig::new(Stencil::SevenPoint)
+            .weights(24.0, -1.0)
+            .anisotropy(1.0, 1.0, 10.0);
+        let (mut matrix, guess, rhs, exact) =
+            SparseMatrix::generate_matrix_with_stencil(8, 8, 8, stencil, &world);
+        make_local_matrix(&mut matrix, &world);
+        let mut ic = IncompleteCholesky::new(&matrix).unwrap();
+        assert_eq!(ic.forward_levels.len(), 8 * 3 - 2);
+        assert_eq!(ic.backward_levels[0], vec![8 * 8 * 8 - 1]);
+
+        // The strong coupling in the z dimension is captured by the factorisation, but not by
+        // the diagonal alone
+        let mut config = SolverConfig::new()
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet);
+        let report = pcg(&mut matrix, &rhs, &guess, &mut ic, &mut config, &world);
+        let mut jacobi = Jacobi::new(&matrix);
+        let jacobi_report = pcg(&mut matrix, &rhs, &guess, &mut jacobi, &mut config, &world);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.iterations < jacobi_report.iterations);
+        for (actual, expected) in report.solution.iter().zip(exact) {
+            assert!((expected - actual).abs() < 1e-8);
+        }
+        assert_eq!("ic0".parse(), Ok(PreconditionerKind::IncompleteCholesky));
+
+        // A negative definite matrix has no positive pivots, which is an error rather than a
+        // factorisation of not-a-numbers
+        let stencil = StencilConfig::new(Stencil::SevenPoint).weights(-6.0, 1.0);
+        let (mut matrix, _, _, _) =
+            SparseMatrix::generate_matrix_with_stencil(4, 4, 4, stencil, &world);
+        make_local_matrix(&mut matrix, &world);
+        assert!(IncompleteCholesky::new(&matrix).is_err());
+        let kind = PreconditionerKind::IncompleteCholesky;
+        let smoother = SmootherKind::SymmetricGaussSeidel;
+        assert!(kind.build(&matrix, smoother, &world).is_err());
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_multigrid() {
+        let world = UNIVERSE.world();
+        let smoother = SmootherKind::SymmetricGaussSeidel;
+        let (mut matrix, _, _, _) = SparseMatrix::generate_matrix(16, 16, 16, &world);
+        make_local_matrix(&mut matrix, &world);
+        let multigrid = Multigrid::new(&matrix, 4, smoother, &world).unwrap();
+        let nrows: Vec<usize> = multigrid
+            .levels
+            .iter()
+            .map(|level| level.matrix().local_nrow)
+            .collect();
+        assert_eq!(nrows, vec![4096, 512, 64, 8]);
+        assert_eq!(multigrid.injections[0][..3], [0, 2, 4]);
+        assert_eq!(multigrid.injections[0][8], 2 * 16);
+        assert_eq!(multigrid.levels[3].matrix().geometry.unwrap().nx, 2);
+        // Coarsening stops at a dimension of odd size
+        let (matrix, _, _, _) = SparseMatrix::generate_matrix(12, 4, 8, &world);
+        let geometry = matrix.geometry.unwrap();
+        assert_eq!(geometry.coarsen().unwrap().nx, 6);
+        let multigrid = Multigrid::new(&matrix, 4, smoother, &world).unwrap();
+        assert_eq!(multigrid.levels.len(), 3);
+
+        let diffusion = DiffusionConfig::new(CoefficientField::Constant);
+        let (matrix, _, _, _) = SparseMatrix::generate_diffusion_matrix(4, 4, 4, diffusion, &world);
+        assert!(Multigrid::new(&matrix, 4, smoother, &world).is_err());
+
+        // The coarse levels remove the smooth error which Gauss-Seidel alone is slow to reduce
+        let stencil = StencilConfig::new(Stencil::SevenPoint).weights(6.0, -1.0);
+        let (mut matrix, guess, rhs, exact) =
+            SparseMatrix::generate_matrix_with_stencil(16, 16, 16, stencil, &world);
+        make_local_matrix(&mut matrix, &world);
+        let mut config = SolverConfig::new()
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet);
+        let mut multigrid = Multigrid::new(&matrix, 4, smoother, &world).unwrap();
+        let report = pcg(
+            &mut matrix,
+            &rhs,
+            &guess,
+            &mut multigrid,
+            &mut config,
+            &world,
+        );
+        let mut symgs = SymmetricGaussSeidel::new(&matrix, &world);
+        let symgs_report = pcg(&mut matrix, &rhs, &guess, &mut symgs, &mut config, &world);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.iterations < symgs_report.iterations);
+        for (actual, expected) in report.solution.iter().zip(exact.iter()) {
+            assert!((expected - actual).abs() < 1e-8);
+        }
+        assert_eq!("mg".parse(), Ok(PreconditionerKind::Multigrid));
+
+        // The levels can instead be smoothed with Chebyshev steps, which need no sweeps, and the
+        // coarse levels still make it converge faster than the Chebyshev preconditioner alone
+        let mut multigrid = Multigrid::new(&matrix, 4, SmootherKind::Chebyshev, &world).unwrap();
+        assert!(matches!(multigrid.levels[3], Smoother::Chebyshev(_)));
+        let report = pcg(
+            &mut matrix,
+            &rhs,
+            &guess,
+            &mut multigrid,
+            &mut config,
+            &world,
+        );
+        let mut chebyshev = Chebyshev::new(&matrix, 4, &world);
+        let chebyshev_report = pcg(
+            &mut matrix,
+            &rhs,
+            &guess,
+            &mut chebyshev,
+            &mut config,
+            &world,
+        );
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.iterations < chebyshev_report.iterations);
+        for (actual, expected) in report.solution.iter().zip(exact) {
+            assert!((expected - actual).abs() < 1e-8);
+        }
+        assert_eq!("chebyshev".parse(), Ok(SmootherKind::Chebyshev));
+        assert!("jacobi".parse::<SmootherKind>().is_err());
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_chebyshev() {
+        let world = UNIVERSE.world();
+        let stencil = StencilConfig::new(Stencil::SevenPoint).weights(6.0, -1.0);
+        let (mut matrix, guess, rhs, exact) =
+            SparseMatrix::generate_matrix_with_stencil(16, 16, 16, stencil, &world);
+        make_local_matrix(&mut matrix, &world);
+        let mut chebyshev = Chebyshev::new(&matrix, 4, &world);
+        // The eigenvalues of `D⁻¹A` for the Laplacian are inside `(0, 2)`, and the smallest is
+        // `1 - cos(π/17)`
+        assert!(0.0 < chebyshev.lower && chebyshev.lower < 0.05);
+        assert!(chebyshev.upper > 1.5 && chebyshev.upper < 2.2);
+
+        // The preconditioner is symmetric, so `x·M⁻¹y = y·M⁻¹x`
+        let x: Vec<f64> = (0..4096).map(|i| (i as f64).sin()).collect();
+        let y: Vec<f64> = (0..4096).map(|i| (i as f64).cos()).collect();
+        let xy: f64 = x.iter().zip(chebyshev.apply(&y)).map(|(a, b)| a * b).sum();
+        let yx: f64 = y.iter().zip(chebyshev.apply(&x)).map(|(a, b)| a * b).sum();
+        assert!((xy - yx).abs() < 1e-10 * xy.abs());
+
+        // Smoothing from zero is the same as applying the preconditioner
+        let mut z = vec![0.0; 4096];
+        chebyshev.smooth(&x, &mut z);
+        assert_eq!(z, chebyshev.apply(&x));
+
+        let mut config = SolverConfig::new()
+            .tolerance(1e-10)
+            .verbosity(Verbosity::Quiet);
+        let report = pcg(
+            &mut matrix,
+            &rhs,
+            &guess,
+            &mut chebyshev,
+            &mut config,
+            &world,
+        );
+        let mut jacobi = Jacobi::new(&matrix);
+        let jacobi_report = pcg(&mut matrix, &rhs, &guess, &mut jacobi, &mut config, &world);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert!(report.iterations < jacobi_report.iterations);
+        for (actual, expected) in report.solution.iter().zip(exact) {
+            assert!((expected - actual).abs() < 1e-8);
+        }
+        assert_eq!("chebyshev".parse(), Ok(PreconditionerKind::Chebyshev));
+    }
+
+    #[test]
+    #[serial] // Tests using MPI must not run concurrently
+    fn test_solver() {
+        let (nx, ny, nz) = (5, 5, 5);
+        let (mut matrix, guess, rhs, exact) =
+            SparseMatrix::generate_matrix(nx, ny, nz, &UNIVERSE.world());
+        let max_iter = 150;
+        let tolerance = 5e-40;
+        let mut residuals = vec![];
+        let mut config = SolverConfig::new()
+            .max_iterations(max_iter)
+            .tolerance(tolerance)
+            .callback(|_, normr| residuals.push(normr));
+        let report = solver(&mut matrix, &rhs, &guess, &mut config, &UNIVERSE.world());
+        drop(config);
+        let (result, iterations, normr) =
+            (report.solution, report.iterations, report.final_residual);
         let residual = compute_residual(matrix.local_nrow, &result, &exact);
         assert!(normr < tolerance);
         assert!(iterations < max_iter);
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+        assert_eq!(residuals.len(), iterations as usize + 1);
         assert!(residual < 1e-15);
         for (actual, expected) in result.iter().zip(exact) {
             assert!((expected - actual).abs() < 1e-5);
         }
+
+        // The initial residual is only checked once, and the residual falls at every step after it
+        let mut config = SolverConfig::new()
+            .stagnation_window(1)
+            .verbosity(Verbosity::Quiet);
+        let report = solver(&mut matrix, &rhs, &guess, &mut config, &UNIVERSE.world());
+        assert_eq!(report.reason, ConvergenceReason::Converged);
+    }
+
+    #[test]
+    fn test_solver_config() {
+        let config = SolverConfig::new();
+        assert_eq!((config.max_iterations, config.tolerance), (150, 0.0));
+        assert!(config.should_print(15));
+        assert!(!config.should_print(16));
+        assert!(config.should_print(149));
+
+        let config = SolverConfig::new().max_iterations(1000).print_freq(7);
+        assert!(config.should_print(14));
+        assert!(!config.should_print(50));
+        let config = config.verbosity(Verbosity::Verbose);
+        assert!(config.should_print(51));
+        let config = config.verbosity(Verbosity::Quiet);
+        assert!(!config.should_print(999));
+
+        let config = SolverConfig::new().restart(0);
+        assert_eq!((config.restart, config.reorthogonalise), (1, false));
+        let config = config.restart(3).reorthogonalise(true);
+        assert_eq!(
+            Method::Gmres.kernel_calls(&config),
+            [5.0 + 1.0 / 3.0, 6.0 + 2.0 / 3.0, 1.0 + 1.0 / 3.0]
+        );
+    }
+
+    #[test]
+    fn test_yaml_doc() {
+        let mut doc = YamlDoc::new("hpccg", "1.0");
+        doc.destination_directory = std::env::temp_dir().join("hpccg_test_yaml_doc");
+        doc.add("final_residual", 1.4523e-13);
+        doc.add("time", "4.893");
+        doc.get("time").unwrap().add("total", 4.243);
+        let total = doc.get("time").unwrap().get("total").unwrap();
+        total.add("time", 2.457);
+        total.add("flops", 4.88e5);
+        doc.get("time").unwrap().add("ddot", 1.243);
+        let sparsemv = doc.get("time").unwrap().add("sparsemv", "");
+        sparsemv.add("time", 0.3445);
+        let overhead = sparsemv.add("overhead", "");
+        overhead.add("time", 0.0123);
+        overhead.add("percentage", 0.034);
+        doc.add("Number of iterations", 149);
+
+        let yaml = doc.generate_yaml().unwrap();
+        assert_eq!(
+            yaml,
+            "Mini-Application Name: hpccg\n\
+             Mini-Application Version: 1.0\n\
+             final_residual: 1.4523e-13\n\
+             time: \n  total: \n    time: 2.457\n    flops: 488000\n  ddot: 1.243\n  \
+             sparsemv: \n    time: 0.3445\n    overhead: \n      time: 0.0123\n      \
+             percentage: 0.034\n\
+             Number of iterations: 149\n"
+        );
+        let written = std::fs::read_dir(&doc.destination_directory)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .any(|contents| contents == yaml);
+        assert!(written);
+        std::fs::remove_dir_all(&doc.destination_directory).unwrap();
+
+        assert_eq!(YamlValue::from(0.0).to_string(), "0");
+        assert_eq!(YamlValue::from(123456.7).to_string(), "123457");
+        assert_eq!(YamlValue::from(1234567.0).to_string(), "1.23457e+06");
+        assert_eq!(YamlValue::from(-0.00001234).to_string(), "-1.234e-05");
+    }
+
+    #[test]
+    fn test_run_summary() {
+        let summary = RunSummary {
+            num_ranks: 2,
+            num_threads: 4,
+            dimensions: Some((5, 5, 5)),
+            process_grid: Some((1, 1, 2)),
+            data_file: None,
+            method: Method::ConjugateGradient,
+            preconditioner: None,
+            smoother: None,
+            kernel_calls: [2.0, 3.0, 1.0],
+            total_nrow: 250,
+            total_nnz: 6750,
+            iterations: 10,
+            final_residual: 1.5e-3,
+            reason: ConvergenceReason::Converged,
+            difference: 2.0e-4,
+            times: Timings {
+                total: 2.0,
+                ddot: 0.25,
+                waxpby: 0.25,
+                sparsemv: 1.0,
+                allreduce: 0.125,
+                exchange: 0.5,
+                reduction_wait: 0.0,
+                precondition: 0.0,
+            },
+            make_local_matrix_time: 0.5,
+            allreduce_min: 0.125,
+            allreduce_max: 0.25,
+            allreduce_avg: 0.1875,
+            reduction_wait_min: 0.0,
+            reduction_wait_max: 0.0,
+            reduction_wait_avg: 0.0,
+        };
+        assert_eq!(summary.flops(), [160000.0, 10000.0, 15000.0, 135000.0]);
+        assert_eq!(summary.mflops()[3], 0.135);
+        let bicgstab_summary = RunSummary {
+            method: Method::BiCgStab,
+            kernel_calls: Method::BiCgStab.kernel_calls(&SolverConfig::new()),
+            ..summary.clone()
+        };
+        assert_eq!(bicgstab_summary.flops()[0], 330000.0);
+        let pcg_summary = RunSummary {
+            method: Method::Pcg,
+            preconditioner: Some(PreconditionerKind::Multigrid),
+            smoother: Some(SmootherKind::Chebyshev),
+            times: Timings {
+                precondition: 0.5,
+                ..summary.times
+            },
+            ..summary.clone()
+        };
+        let pcg_row = pcg_summary.to_csv_row();
+        assert!(pcg_row.contains(",1,1,2,pcg,mg,"));
+        assert!(pcg_row.ends_with(",converged,0.5,chebyshev"));
+        let pcg_yaml = pcg_summary.to_yaml_doc().print_yaml();
+        assert!(pcg_yaml.contains("Preconditioner: mg\nSmoother: chebyshev\n"));
+        assert!(pcg_yaml.contains("  PRECOND : 0.5\n"));
+
+        let json = summary.to_json();
+        assert!(json.starts_with("{\n  \"mpi_ranks\": 2,\n"));
+        assert!(json.contains("  \"data_file\": \"\",\n"));
+        assert!(json.contains("  \"final_residual\": 0.0015,\n"));
+        assert!(json.contains("  \"sparsemv_exchange_pct\": 25.0,\n"));
+        assert!(json.contains("  \"convergence_reason\": \"converged\",\n"));
+        assert!(json.ends_with("  \"time_precondition\": 0.0,\n  \"smoother\": \"\"\n}\n"));
+
+        let yaml = summary.to_yaml_doc().print_yaml();
+        assert!(yaml.contains("  Number of Rayon threads: 4\n  Process grid: \n    px: 1\n"));
+        assert!(yaml.contains("  SPARSEMV PARALLEL OVERHEAD Pct: 50\n"));
+        assert!(!yaml.contains("PRECOND"));
+
+        // Rows are appended under a single header
+        let csv_file = std::env::temp_dir().join("hpccg_test_run_summary.csv");
+        let _ = std::fs::remove_file(&csv_file);
+        summary.append_csv(&csv_file).unwrap();
+        summary.append_csv(&csv_file).unwrap();
+        let csv = std::fs::read_to_string(&csv_file).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], summary.csv_header());
+        assert!(lines[0].starts_with("mpi_ranks,rayon_threads,nx,ny,nz,data_file,iterations,"));
+        assert!(lines[0].contains(",sparsemv_exchange_pct,px,py,pz,solver,preconditioner,"));
+        assert!(lines[0].ends_with(",convergence_reason,time_precondition,smoother"));
+        assert_eq!(lines[1], lines[2]);
+        assert!(lines[1].starts_with("2,4,5,5,5,,10,0.0015,0.0002,2.0,"));
+        assert!(lines[1].ends_with(",25.0,1,1,2,cg,,0.0,0.0,0.0,0.0,converged,0.0,"));
+
+        // A file with the header of an earlier version is extended with the new fields
+        let old_header = lines[0].split(',').take(34).collect::<Vec<_>>().join(",");
+        let old_row = lines[1].split(',').take(34).collect::<Vec<_>>().join(",");
+        std::fs::write(&csv_file, format!("{old_header}\n{old_row}\n")).unwrap();
+        summary.append_csv(&csv_file).unwrap();
+        let csv = std::fs::read_to_string(&csv_file).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], summary.csv_header());
+        assert_eq!(lines[1], format!("{old_row},,,,,,,,,,,,"));
+        assert_eq!(lines[2], summary.to_csv_row());
+
+        std::fs::write(&csv_file, "some,other,header\n").unwrap();
+        assert!(summary.append_csv(&csv_file).is_err());
+        std::fs::remove_file(&csv_file).unwrap();
+
+        assert_eq!("csv".parse(), Ok(OutputFormat::Csv));
+        assert!("xml".parse::<OutputFormat>().is_err());
     }
 }